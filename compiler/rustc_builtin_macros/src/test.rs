@@ -135,6 +135,18 @@ pub fn expand_test_or_bench(
         )
     };
 
+    // creates test::TargetFilter::$name
+    let target_filter_path = |name| {
+        cx.path(
+            sp,
+            vec![
+                test_id,
+                Ident::from_str_and_span("TargetFilter", sp),
+                Ident::from_str_and_span(name, sp),
+            ],
+        )
+    };
+
     // creates test::TestType::$name
     let test_type_path = |name| {
         cx.path(
@@ -249,6 +261,15 @@ pub fn expand_test_or_bench(
                                         "ignore",
                                         cx.expr_bool(sp, should_ignore(&cx.sess, &item)),
                                     ),
+                                    // ignore_message: Some("...") | None
+                                    field(
+                                        "ignore_message",
+                                        if let Some(msg) = should_ignore_message(cx, &item) {
+                                            cx.expr_some(sp, cx.expr_str(sp, msg))
+                                        } else {
+                                            cx.expr_none(sp)
+                                        },
+                                    ),
                                     // allow_fail: true | false
                                     field(
                                         "allow_fail",
@@ -258,6 +279,17 @@ pub fn expand_test_or_bench(
                                     field("compile_fail", cx.expr_bool(sp, false)),
                                     // no_run: true | false
                                     field("no_run", cx.expr_bool(sp, false)),
+                                    // target_filter: test::TargetFilter::Any
+                                    field("target_filter", cx.expr_path(target_filter_path("Any"))),
+                                    // min_compute_units: Some(N) | None
+                                    field(
+                                        "min_compute_units",
+                                        if let Some(units) = min_compute_units(cx, &item) {
+                                            cx.expr_some(sp, cx.expr_u64(sp, units))
+                                        } else {
+                                            cx.expr_none(sp)
+                                        },
+                                    ),
                                     // should_panic: ...
                                     field(
                                         "should_panic",
@@ -356,10 +388,36 @@ fn should_ignore(sess: &Session, i: &ast::Item) -> bool {
     sess.contains_name(&i.attrs, sym::ignore)
 }
 
+fn should_ignore_message(cx: &ExtCtxt<'_>, i: &ast::Item) -> Option<Symbol> {
+    match cx.sess.find_by_name(&i.attrs, sym::ignore) {
+        Some(attr) => {
+            match attr.meta_item_list() {
+                // Handle #[ignore(bar = "foo")]
+                Some(_) => None,
+                // Handle #[ignore] and #[ignore = "message"]
+                None => attr.value_str(),
+            }
+        }
+        None => None,
+    }
+}
+
 fn should_fail(sess: &Session, i: &ast::Item) -> bool {
     sess.contains_name(&i.attrs, sym::allow_fail)
 }
 
+fn min_compute_units(cx: &ExtCtxt<'_>, i: &ast::Item) -> Option<u64> {
+    let attr = cx.sess.find_by_name(&i.attrs, sym::min_compute_units)?;
+    let units = attr.value_str().and_then(|units| units.as_str().parse().ok());
+    if units.is_none() {
+        cx.sess
+            .parse_sess
+            .span_diagnostic
+            .span_err(attr.span, "`min_compute_units` must be a non-negative integer");
+    }
+    units
+}
+
 fn should_panic(cx: &ExtCtxt<'_>, i: &ast::Item) -> ShouldPanic {
     match cx.sess.find_by_name(&i.attrs, sym::should_panic) {
         Some(attr) => {
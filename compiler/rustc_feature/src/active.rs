@@ -663,6 +663,14 @@ pub fn set(&self, features: &mut Features, span: Span) {
     /// Allows qualified paths in struct expressions, struct patterns and tuple struct patterns.
     (active, more_qualified_paths, "1.54.0", Some(80080), None),
 
+    // no-tracking-issue-start
+
+    /// Allows `#[min_compute_units = "N"]` on tests, which the test harness
+    /// ignores when its BPF compute budget is lower.
+    (active, min_compute_units, "1.54.0", None, None),
+
+    // no-tracking-issue-end
+
     // -------------------------------------------------------------------------
     // feature-group-end: actual feature gates
     // -------------------------------------------------------------------------
@@ -317,6 +317,10 @@ macro_rules! experimental {
 
     // Testing:
     gated!(allow_fail, Normal, template!(Word), experimental!(allow_fail)),
+    gated!(
+        min_compute_units, Normal, template!(NameValueStr: "N"),
+        experimental!(min_compute_units),
+    ),
     gated!(
         test_runner, CrateLevel, template!(List: "path"), custom_test_frameworks,
         "custom test frameworks are an unstable feature",
@@ -743,6 +743,7 @@
         metadata_type,
         min_align_of,
         min_align_of_val,
+        min_compute_units,
         min_const_fn,
         min_const_generics,
         min_const_unsafe_fn,
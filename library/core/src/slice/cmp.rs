@@ -18,6 +18,16 @@
     fn memcmp(s1: *const u8, s2: *const u8, n: usize) -> i32;
 }
 
+#[cfg(target_arch = "bpf")]
+extern "C" {
+    /// Compares `n` bytes with the runtime's syscall, which costs far fewer
+    /// compute units than a byte loop.
+    ///
+    /// Writes 0 to `result` for equal, < 0 for less than and > 0 for greater
+    /// than.
+    fn sol_memcmp_(s1: *const u8, s2: *const u8, n: u64, result: *mut i32);
+}
+
 #[stable(feature = "rust1", since = "1.0.0")]
 impl<A, B> PartialEq<[B]> for [A]
 where
@@ -89,7 +99,21 @@ fn equal(&self, other: &[B]) -> bool {
         // The two slices have been checked to have the same size above.
         unsafe {
             let size = mem::size_of_val(self);
-            memcmp(self.as_ptr() as *const u8, other.as_ptr() as *const u8, size) == 0
+            #[cfg(not(target_arch = "bpf"))]
+            {
+                memcmp(self.as_ptr() as *const u8, other.as_ptr() as *const u8, size) == 0
+            }
+            #[cfg(target_arch = "bpf")]
+            {
+                let mut result = 0;
+                sol_memcmp_(
+                    self.as_ptr() as *const u8,
+                    other.as_ptr() as *const u8,
+                    size as u64,
+                    &mut result,
+                );
+                result == 0
+            }
         }
     }
 }
@@ -2094,3 +2094,18 @@ fn foo(x: &Cell<isize>) -> Foo<'_> {
 
     assert_eq!(x.get(), 1);
 }
+
+#[test]
+fn test_byte_slice_eq() {
+    let a: Vec<u8> = (0..64).collect();
+    let mut b = a.clone();
+    assert!(a[..] == b[..]);
+    assert!(a[..] != b[..63]);
+
+    b[63] ^= 1;
+    assert!(a[..] != b[..]);
+    assert!(a[..63] == b[..63]);
+    b[0] ^= 1;
+    assert!(a[1..63] == b[1..63]);
+    assert!(a[..1] != b[..1]);
+}
@@ -2881,6 +2881,13 @@ pub fn new() -> RandomState {
             RandomState { k0, k1 }
         })
     }
+
+    /// Creates a `RandomState` with the given keys, bypassing the per-thread
+    /// keys. Used by `sys::bpf::seeded_random_state`.
+    #[cfg(target_arch = "bpf")]
+    pub(crate) fn with_keys((k0, k1): (u64, u64)) -> RandomState {
+        RandomState { k0, k1 }
+    }
 }
 
 #[stable(feature = "hashmap_build_hasher", since = "1.7.0")]
@@ -48,6 +48,8 @@ pub fn copy<R: ?Sized, W: ?Sized>(reader: &mut R, writer: &mut W) -> Result<u64>
     cfg_if::cfg_if! {
         if #[cfg(any(target_os = "linux", target_os = "android"))] {
             crate::sys::kernel_copy::copy_spec(reader, writer)
+        } else if #[cfg(target_arch = "bpf")] {
+            crate::sys::copy::copy_spec(reader, writer)
         } else {
             generic_copy(reader, writer)
         }
@@ -8,6 +8,8 @@
 #[cfg(not(target_arch = "bpf"))]
 use crate::cell::{Cell, RefCell};
 use crate::fmt;
+#[cfg(target_arch = "bpf")]
+use crate::marker::PhantomData;
 #[cfg(not(target_arch = "bpf"))]
 use crate::io::{self, BufReader, Initializer, IoSlice, IoSliceMut, LineWriter};
 #[cfg(target_arch = "bpf")]
@@ -391,6 +393,14 @@ pub fn lock(&self) -> StdinLock<'_> {
     pub fn read_line(&self, buf: &mut String) -> io::Result<usize> {
         self.lock().read_line(buf)
     }
+
+    /// Reads a line of the input seeded with `set_stdin`, appending it to the
+    /// specified buffer. Returns 0 once the input is exhausted.
+    #[stable(feature = "rust1", since = "1.0.0")]
+    #[cfg(target_arch = "bpf")]
+    pub fn read_line(&self, buf: &mut String) -> io::Result<usize> {
+        stdio::Stdin.read_line(buf)
+    }
 }
 
 #[stable(feature = "std_debug", since = "1.16.0")]
@@ -565,7 +575,8 @@ pub struct StdoutLock<'a> {
 /// BPF dummy
 #[stable(feature = "rust1", since = "1.0.0")]
 #[cfg(target_arch = "bpf")]
-pub struct StdoutLock {
+pub struct StdoutLock<'a> {
+    _stdout: PhantomData<&'a Stdout>,
 }
 
 #[cfg(not(target_arch = "bpf"))]
@@ -628,6 +639,11 @@ pub fn stdout() -> Stdout {
     Stdout {}
 }
 
+#[cfg(target_arch = "bpf")]
+pub fn cleanup() {
+    crate::sys::stdio::cleanup()
+}
+
 #[cfg(not(target_arch = "bpf"))]
 pub fn cleanup() {
     if let Some(instance) = STDOUT.get() {
@@ -671,6 +687,15 @@ pub fn lock(&self) -> StdoutLock<'_> {
             inner: self.inner.lock()
         }
     }
+
+    /// Returns a handle that writes like `Stdout` itself. There is nothing
+    /// to lock on BPF; this exists so that code written against the locked
+    /// handle works unchanged.
+    #[stable(feature = "rust1", since = "1.0.0")]
+    #[cfg(target_arch = "bpf")]
+    pub fn lock(&self) -> StdoutLock<'_> {
+        StdoutLock { _stdout: PhantomData }
+    }
 }
 
 #[stable(feature = "std_debug", since = "1.16.0")]
@@ -711,29 +736,24 @@ fn write_fmt(&mut self, args: fmt::Arguments<'_>) -> io::Result<()> {
 #[cfg(target_arch = "bpf")]
 impl Write for Stdout {
     fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
-        unsafe {
-            crate::sys::sol_log(core::str::from_utf8_unchecked(buf));
-        }
-        Ok(buf.len())
+        stdio::handle_unsupported(stdio::Stdout.write(buf), buf.len())
     }
-    fn write_vectored(&mut self, _bufs: &[IoSlice<'_>]) -> io::Result<usize> {
-        Ok(0)
+    fn write_vectored(&mut self, bufs: &[IoSlice<'_>]) -> io::Result<usize> {
+        let total = bufs.iter().map(|b| b.len()).sum();
+        stdio::handle_unsupported(stdio::Stdout.write_vectored(bufs), total)
     }
     #[inline]
     fn is_write_vectored(&self) -> bool {
         false
     }
     fn flush(&mut self) -> io::Result<()> {
-        Ok(())
+        stdio::handle_unsupported(stdio::Stdout.flush(), ())
     }
     fn write_all(&mut self, buf: &[u8]) -> io::Result<()> {
-        unsafe {
-            crate::sys::sol_log(core::str::from_utf8_unchecked(buf));
-        }
-        Ok(())
+        stdio::handle_unsupported(stdio::Stdout.write_all(buf), ())
     }
-    fn write_all_vectored(&mut self, _bufs: &mut [IoSlice<'_>]) -> io::Result<()> {
-        Ok(())
+    fn write_all_vectored(&mut self, bufs: &mut [IoSlice<'_>]) -> io::Result<()> {
+        stdio::handle_unsupported(stdio::Stdout.write_all_vectored(bufs), ())
     }
     fn write_fmt(&mut self, _args: fmt::Arguments<'_>) -> io::Result<()> {
         Ok(())
@@ -791,6 +811,31 @@ fn write_all_vectored(&mut self, bufs: &mut [IoSlice<'_>]) -> io::Result<()> {
     }
 }
 
+// Line buffered through `sys::stdio::Stdout`, like `Stdout`.
+#[stable(feature = "rust1", since = "1.0.0")]
+#[cfg(target_arch = "bpf")]
+impl Write for StdoutLock<'_> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        stdio::Stdout.write(buf)
+    }
+    fn write_vectored(&mut self, bufs: &[IoSlice<'_>]) -> io::Result<usize> {
+        stdio::Stdout.write_vectored(bufs)
+    }
+    #[inline]
+    fn is_write_vectored(&self) -> bool {
+        false
+    }
+    fn flush(&mut self) -> io::Result<()> {
+        stdio::Stdout.flush()
+    }
+    fn write_all(&mut self, buf: &[u8]) -> io::Result<()> {
+        stdio::Stdout.write_all(buf)
+    }
+    fn write_all_vectored(&mut self, bufs: &mut [IoSlice<'_>]) -> io::Result<()> {
+        stdio::Stdout.write_all_vectored(bufs)
+    }
+}
+
 #[stable(feature = "std_debug", since = "1.16.0")]
 #[cfg(not(target_arch = "bpf"))]
 impl fmt::Debug for StdoutLock<'_> {
@@ -801,7 +846,7 @@ fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
 
 #[stable(feature = "std_debug", since = "1.16.0")]
 #[cfg(target_arch = "bpf")]
-impl fmt::Debug for StdoutLock {
+impl fmt::Debug for StdoutLock<'_> {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         f.pad("StdoutLock { .. }")
     }
@@ -841,7 +886,8 @@ pub struct StderrLock<'a> {
 /// BPF dummy
 #[stable(feature = "rust1", since = "1.0.0")]
 #[cfg(target_arch = "bpf")]
-pub struct StderrLock {
+pub struct StderrLock<'a> {
+    _stderr: PhantomData<&'a Stderr>,
 }
 
 /// Constructs a new handle to the standard error of the current process.
@@ -930,6 +976,15 @@ impl Stderr {
     pub fn lock(&self) -> StderrLock<'_> {
         StderrLock { inner: self.inner.lock() }
     }
+
+    /// Returns a handle that writes like `Stderr` itself. There is nothing
+    /// to lock on BPF; this exists so that code written against the locked
+    /// handle works unchanged.
+    #[stable(feature = "rust1", since = "1.0.0")]
+    #[cfg(target_arch = "bpf")]
+    pub fn lock(&self) -> StderrLock<'_> {
+        StderrLock { _stderr: PhantomData }
+    }
 }
 
 #[stable(feature = "std_debug", since = "1.16.0")]
@@ -1050,6 +1105,18 @@ fn write_all_vectored(&mut self, bufs: &mut [IoSlice<'_>]) -> io::Result<()> {
     }
 }
 
+// Unbuffered: every write is its own log entry, like `Stderr`.
+#[stable(feature = "rust1", since = "1.0.0")]
+#[cfg(target_arch = "bpf")]
+impl Write for StderrLock<'_> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        stdio::Stderr.write(buf)
+    }
+    fn flush(&mut self) -> io::Result<()> {
+        stdio::Stderr.flush()
+    }
+}
+
 #[stable(feature = "std_debug", since = "1.16.0")]
 #[cfg(not(target_arch = "bpf"))]
 impl fmt::Debug for StderrLock<'_> {
@@ -1060,7 +1127,7 @@ fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
 
 #[stable(feature = "std_debug", since = "1.16.0")]
 #[cfg(target_arch = "bpf")]
-impl fmt::Debug for StderrLock {
+impl fmt::Debug for StderrLock<'_> {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         f.pad("StderrLock { .. }")
     }
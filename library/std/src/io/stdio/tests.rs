@@ -45,3 +45,26 @@ fn panic_doesnt_poison() {
     let _a = stderr();
     let _a = _a.lock();
 }
+
+#[test]
+#[cfg(target_arch = "bpf")]
+fn locked_handles_write_like_unlocked_ones() {
+    static mut CAPTURED: Vec<String> = Vec::new();
+    fn sink(message: &str) {
+        unsafe { CAPTURED.push(message.to_string()) };
+    }
+
+    let stdout = stdout();
+    let mut handle = stdout.lock();
+    write!(handle, "locked {}\npartial", 1).unwrap();
+    assert_eq!(unsafe { crate::sys::stdio::LOGGED.last() }, Some(&b"locked 1".to_vec()));
+    handle.flush().unwrap();
+    assert_eq!(unsafe { crate::sys::stdio::LOGGED.last() }, Some(&b"partial".to_vec()));
+
+    crate::sys::set_log_sink(Some(sink));
+    let stderr = stderr();
+    let mut handle = stderr.lock();
+    handle.write_all(b"not buffered").unwrap();
+    crate::sys::set_log_sink(None);
+    assert_eq!(unsafe { &CAPTURED }, &["not buffered"]);
+}
@@ -1921,7 +1921,9 @@ pub fn is_absolute(&self) -> bool {
             // FIXME: Allow Redox prefixes
             self.has_root() || has_redox_scheme(self.as_u8_slice())
         } else {
-            self.has_root() && (cfg!(any(unix, target_os = "wasi")) || self.prefix().is_some())
+            self.has_root()
+                && (cfg!(any(unix, target_os = "wasi", target_arch = "bpf"))
+                    || self.prefix().is_some())
         }
     }
 
@@ -1654,6 +1654,15 @@ impl ExitCode {
     pub const FAILURE: ExitCode = ExitCode(imp::ExitCode::FAILURE);
 }
 
+/// On BPF the code is what the program entrypoint returns to the runtime.
+#[cfg(target_arch = "bpf")]
+#[unstable(feature = "process_exitcode_placeholder", issue = "48711")]
+impl From<u8> for ExitCode {
+    fn from(code: u8) -> Self {
+        ExitCode(imp::ExitCode::from(code))
+    }
+}
+
 impl Child {
     /// Forces the child process to exit. If the child has already exited, an [`InvalidInput`]
     /// error is returned.
@@ -1887,7 +1896,6 @@ pub fn wait_with_output(mut self) -> io::Result<Output> {
 /// [platform-specific behavior]: #platform-specific-behavior
 #[stable(feature = "rust1", since = "1.0.0")]
 pub fn exit(code: i32) -> ! {
-    #[cfg(not(target_arch = "bpf"))]
     crate::sys_common::rt::cleanup();
     crate::sys::os::exit(code)
 }
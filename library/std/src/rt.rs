@@ -18,6 +18,8 @@
 pub use crate::panicking::{begin_panic, begin_panic_fmt};
 #[cfg(not(target_arch = "bpf"))]
 pub use crate::panicking::panic_count;
+#[cfg(target_arch = "bpf")]
+pub use crate::sys_common::rt::{cleanup, init};
 
 // To reduce the generated code of the new `lang_start`, this function is doing
 // the real work.
@@ -6,32 +6,212 @@
 //! synchronization as there are no threads!
 
 use crate::alloc::{GlobalAlloc, Layout, System};
+use crate::cell::Cell;
+
+#[cfg(test)]
+mod tests;
+
+// Alignment of the blocks handed out by `sol_alloc_free_`.
+const MIN_ALIGN: usize = 8;
+
+// Number of allocations handed out and not yet deallocated. The bump
+// allocator never reuses freed memory, so this is the only way for tests to
+// notice that something they expected to be freed was leaked.
+#[cfg(debug_assertions)]
+struct Outstanding {
+    count: Cell<usize>,
+}
+
+#[cfg(debug_assertions)]
+unsafe impl Sync for Outstanding {} // no threads on BPF
+
+#[cfg(debug_assertions)]
+static OUTSTANDING: Outstanding = Outstanding { count: Cell::new(0) };
+
+/// Returns the number of allocations that have not been deallocated yet.
+///
+/// Only tracked in builds with debug assertions enabled.
+#[cfg(debug_assertions)]
+pub fn outstanding_allocations() -> usize {
+    OUTSTANDING.count.get()
+}
+
+#[inline]
+fn track_alloc(ptr: *mut u8) -> *mut u8 {
+    #[cfg(debug_assertions)]
+    if !ptr.is_null() {
+        OUTSTANDING.count.set(OUTSTANDING.count.get() + 1);
+    }
+    ptr
+}
+
+#[inline]
+fn track_dealloc() {
+    #[cfg(debug_assertions)]
+    OUTSTANDING.count.set(OUTSTANDING.count.get().saturating_sub(1));
+}
+
+/// Size of the heap the runtime provides unless the program asks for more.
+pub const DEFAULT_HEAP_LEN: usize = 32 * 1024;
+
+// Length of the heap, once `set_heap_region` has been called or the first
+// allocation has fallen back to `DEFAULT_HEAP_LEN`.
+struct HeapRegion {
+    len: Cell<Option<usize>>,
+}
+
+unsafe impl Sync for HeapRegion {} // no threads on BPF
+
+static HEAP_REGION: HeapRegion = HeapRegion { len: Cell::new(None) };
+
+/// Records that the heap has been set up with `len` bytes. Loaders that
+/// configure the heap size must call this before anything allocates; with
+/// debug assertions enabled, an earlier allocation aborts.
+pub fn set_heap_region(len: usize) {
+    HEAP_REGION.len.set(Some(len));
+}
+
+/// Returns the length of the heap, `DEFAULT_HEAP_LEN` unless
+/// `set_heap_region` said otherwise.
+pub fn heap_len() -> usize {
+    HEAP_REGION.len.get().unwrap_or(DEFAULT_HEAP_LEN)
+}
+
+// Makes sure the heap region is known before the first allocation. Without
+// debug assertions, a missing `set_heap_region` falls back to the default
+// region; with them, `abort` is called first. Split out so that tests can
+// observe the abort.
+fn check_heap_initialized(abort: impl FnOnce(&str)) {
+    if HEAP_REGION.len.get().is_none() {
+        if cfg!(debug_assertions) {
+            abort("allocation before the heap region was set");
+        }
+        HEAP_REGION.len.set(Some(DEFAULT_HEAP_LEN));
+    }
+}
+
+#[cfg(not(test))]
+fn heap_not_initialized(message: &str) {
+    crate::sys::sol_log(message);
+    crate::sys::abort_internal();
+}
+
+// The mock heap needs no setup, and tests allocate before anything else runs.
+#[cfg(test)]
+fn heap_not_initialized(_message: &str) {}
+
+// Allocates a block for `layout`. The runtime only aligns blocks to
+// `MIN_ALIGN`, so larger alignments are met by asking for enough padding to
+// round the block up. Returns null if the padded block does not fit.
+unsafe fn alloc_aligned(layout: Layout) -> *mut u8 {
+    check_heap_initialized(heap_not_initialized);
+    if layout.align() <= MIN_ALIGN {
+        let ptr = sol_alloc_free_(layout.size() as u64, 0);
+        return track_alloc(checked_block(ptr, layout.size()));
+    }
+    let padded = match layout.size().checked_add(layout.align() - MIN_ALIGN) {
+        Some(padded) => padded,
+        None => return crate::ptr::null_mut(),
+    };
+    let ptr = checked_block(sol_alloc_free_(padded as u64, 0), padded);
+    if ptr.is_null() {
+        return ptr;
+    }
+    track_alloc(ptr.add(ptr.align_offset(layout.align())))
+}
+
+// Returns `ptr`, or null if a block of `size` bytes starting there would wrap
+// around the end of the address space. The heap is handed out by bumping an
+// offset from its base, so a corrupted offset must not turn into a pointer
+// that aliases memory below the heap.
+fn checked_block(ptr: *mut u8, size: usize) -> *mut u8 {
+    match (ptr as usize).checked_add(size) {
+        Some(_) => ptr,
+        None => crate::ptr::null_mut(),
+    }
+}
 
 #[stable(feature = "alloc_system_type", since = "1.28.0")]
 unsafe impl GlobalAlloc for System {
     #[inline]
     unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
-        sol_alloc_free_(layout.size() as u64, 0)
+        alloc_aligned(layout)
         // 0 as *mut u8
     }
 
     #[inline]
     unsafe fn alloc_zeroed(&self, layout: Layout) -> *mut u8 {
-        sol_alloc_free_(layout.size() as u64, 0)
+        alloc_aligned(layout)
         // 0 as *mut u8
     }
 
     #[inline]
     unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        track_dealloc();
         sol_alloc_free_(layout.size() as u64, ptr as u64);
     }
 
-    // #[inline]
-    // unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
-    //     sol_alloc_free_(layout.size() as u64, 0)
-    //     // 0 as *mut u8
-    // }
+    #[inline]
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        // Unlike the default implementation, don't trust `new_size` to fit in
+        // a `Layout`: a size that overflows once rounded up fails instead.
+        let new_layout = match Layout::from_size_align(new_size, layout.align()) {
+            Ok(new_layout) => new_layout,
+            Err(_) => return crate::ptr::null_mut(),
+        };
+        let new_ptr = alloc_aligned(new_layout);
+        if !new_ptr.is_null() {
+            crate::ptr::copy_nonoverlapping(ptr, new_ptr, crate::cmp::min(layout.size(), new_size));
+            self.dealloc(ptr, layout);
+        }
+        new_ptr
+    }
 }
+#[cfg(not(test))]
 extern "C" {
     fn sol_alloc_free_(size: u64, ptr: u64) -> *mut u8;
 }
+
+#[cfg(test)]
+const MOCK_HEAP_LEN: usize = 4096;
+
+#[cfg(test)]
+thread_local! {
+    // Base of this thread's mock heap and the number of bytes used so far.
+    static MOCK_HEAP: (*mut u8, crate::cell::Cell<usize>) = (
+        crate::boxed::Box::into_raw(crate::boxed::Box::new([0u64; MOCK_HEAP_LEN / 8])) as *mut u8,
+        crate::cell::Cell::new(0),
+    );
+}
+
+// Bump allocates `MIN_ALIGN` aligned blocks from a per-thread mock heap, like
+// the runtime does from the program's heap.
+#[cfg(test)]
+unsafe fn sol_alloc_free_(size: u64, ptr: u64) -> *mut u8 {
+    if ptr != 0 {
+        return crate::ptr::null_mut();
+    }
+    MOCK_HEAP.with(|(base, used)| {
+        let start = (used.get() + MIN_ALIGN - 1) & !(MIN_ALIGN - 1);
+        match start.checked_add(size as usize) {
+            Some(end) if end <= MOCK_HEAP_LEN => {
+                used.set(end);
+                base.add(start)
+            }
+            _ => crate::ptr::null_mut(),
+        }
+    })
+}
+
+// Starts this thread's mock heap over, as if the program had just started.
+// Blocks handed out before must not be used anymore.
+#[cfg(test)]
+fn reset_mock_heap() {
+    MOCK_HEAP.with(|(_, used)| used.set(0));
+}
+
+// Returns the number of bytes left on this thread's mock heap.
+#[cfg(test)]
+fn mock_heap_remaining() -> usize {
+    MOCK_HEAP.with(|(_, used)| MOCK_HEAP_LEN - used.get())
+}
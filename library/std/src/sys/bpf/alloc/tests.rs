@@ -0,0 +1,92 @@
+use super::*;
+
+#[test]
+#[cfg(debug_assertions)]
+fn outstanding_allocations_are_counted() {
+    let layout = Layout::new::<u64>();
+    let before = outstanding_allocations();
+
+    unsafe {
+        let a = System.alloc(layout);
+        let b = System.alloc_zeroed(layout);
+        assert_eq!(outstanding_allocations(), before + 2);
+        System.dealloc(a, layout);
+        System.dealloc(b, layout);
+    }
+    assert_eq!(outstanding_allocations(), before);
+
+    // A missing `dealloc` shows up as a leak.
+    unsafe { System.alloc(layout) };
+    assert_eq!(outstanding_allocations(), before + 1);
+}
+
+#[repr(align(32))]
+struct Aligned32([u8; 32]);
+
+#[test]
+fn over_aligned_allocations_are_aligned() {
+    let layout = Layout::new::<Aligned32>();
+    for _ in 0..4 {
+        // Throw the bump offset off a 32 byte boundary.
+        unsafe { System.alloc(Layout::new::<u64>()) };
+        let ptr = unsafe { System.alloc(layout) };
+        assert!(!ptr.is_null());
+        assert_eq!(ptr as usize % 32, 0);
+        let value = ptr.cast::<Aligned32>();
+        unsafe { value.write(Aligned32([0xaa; 32])) };
+        assert_eq!(unsafe { (*value).0[31] }, 0xaa);
+    }
+}
+
+#[test]
+fn alignment_padding_counts_against_the_heap() {
+    // Start from a fresh mock heap, which the test exhausts.
+    reset_mock_heap();
+    let filler = mock_heap_remaining() - 40;
+    assert!(!unsafe { System.alloc(Layout::from_size_align(filler, 8).unwrap()) }.is_null());
+
+    // 32 bytes would fit, but not with the padding needed to align them.
+    let layout = Layout::new::<Aligned32>();
+    assert!(unsafe { System.alloc(layout) }.is_null());
+    assert!(!unsafe { System.alloc(Layout::from_size_align(32, 8).unwrap()) }.is_null());
+    reset_mock_heap();
+}
+
+#[test]
+fn sizes_near_usize_max_fail_instead_of_wrapping() {
+    let huge = Layout::from_size_align(usize::MAX - 7, 8).unwrap();
+    assert!(unsafe { System.alloc(huge) }.is_null());
+    let huge = Layout::from_size_align(usize::MAX - 31, 32).unwrap();
+    assert!(unsafe { System.alloc(huge) }.is_null());
+
+    let layout = Layout::new::<u64>();
+    let ptr = unsafe { System.alloc(layout) };
+    unsafe { ptr.cast::<u64>().write(42) };
+    assert!(unsafe { System.realloc(ptr, layout, usize::MAX) }.is_null());
+    assert!(unsafe { System.realloc(ptr, layout, usize::MAX - 7) }.is_null());
+    // A failed realloc leaves the original block alone.
+    assert_eq!(unsafe { ptr.cast::<u64>().read() }, 42);
+
+    assert!(checked_block((usize::MAX - 15) as *mut u8, 16).is_null());
+    assert!(!checked_block((usize::MAX - 15) as *mut u8, 15).is_null());
+}
+
+#[test]
+fn allocating_before_heap_init_is_caught() {
+    let mut aborted = None;
+    HEAP_REGION.len.set(None);
+    check_heap_initialized(|message| aborted = Some(message.to_string()));
+    if cfg!(debug_assertions) {
+        assert_eq!(aborted.as_deref(), Some("allocation before the heap region was set"));
+    } else {
+        assert_eq!(aborted, None);
+    }
+    assert_eq!(heap_len(), DEFAULT_HEAP_LEN);
+
+    // Once the region is set, allocating is fine.
+    aborted = None;
+    set_heap_region(64 * 1024);
+    check_heap_initialized(|message| aborted = Some(message.to_string()));
+    assert_eq!(aborted, None);
+    assert_eq!(heap_len(), 64 * 1024);
+}
@@ -0,0 +1,57 @@
+use super::*;
+use crate::cell::Cell;
+
+#[test]
+fn push_to_capacity_then_overflow() {
+    let mut v = ArrayVec::<u32, 3>::new();
+    assert!(v.is_empty());
+    for i in 0..3 {
+        assert_eq!(v.push(i), Ok(()));
+    }
+    assert!(v.is_full());
+    assert_eq!(v.push(3), Err(3));
+    assert_eq!(v.as_slice(), &[0, 1, 2]);
+
+    assert_eq!(v.pop(), Some(2));
+    assert_eq!(v.push(4), Ok(()));
+    assert_eq!(v.as_slice(), &[0, 1, 4]);
+}
+
+#[test]
+fn pop_returns_elements_in_reverse() {
+    let mut v = ArrayVec::<String, 2>::new();
+    v.push("a".to_string()).unwrap();
+    v.push("b".to_string()).unwrap();
+    assert_eq!(v.pop().as_deref(), Some("b"));
+    assert_eq!(v.pop().as_deref(), Some("a"));
+    assert_eq!(v.pop(), None);
+}
+
+struct DropCounter<'a>(&'a Cell<usize>);
+
+impl Drop for DropCounter<'_> {
+    fn drop(&mut self) {
+        self.0.set(self.0.get() + 1);
+    }
+}
+
+#[test]
+fn elements_are_dropped_exactly_once() {
+    let drops = Cell::new(0);
+    {
+        let mut v = ArrayVec::<DropCounter<'_>, 4>::new();
+        for _ in 0..3 {
+            assert!(v.push(DropCounter(&drops)).is_ok());
+        }
+        drop(v.pop());
+        assert_eq!(drops.get(), 1);
+    }
+    // The two remaining elements, but not the unused slots.
+    assert_eq!(drops.get(), 3);
+
+    let mut full = ArrayVec::<DropCounter<'_>, 0>::new();
+    let rejected = full.push(DropCounter(&drops));
+    assert_eq!(drops.get(), 3);
+    drop(rejected);
+    assert_eq!(drops.get(), 4);
+}
@@ -0,0 +1,115 @@
+//! Base58 encoding with the bitcoin alphabet, as used for Solana pubkeys.
+//!
+//! Both directions work entirely in a caller-provided buffer so that handling
+//! a pubkey doesn't need to touch the (non-freeing) heap.
+
+#[cfg(test)]
+mod tests;
+
+const ALPHABET: &[u8; 58] = b"123456789ABCDEFGHJKLMNPQRSTUVWXYZabcdefghijkmnopqrstuvwxyz";
+
+// Maps an ASCII byte to its base58 digit, or `INVALID` if it isn't part of the alphabet.
+const INVALID: u8 = 0xff;
+const DECODE: [u8; 128] = {
+    let mut table = [INVALID; 128];
+    let mut i = 0;
+    while i < ALPHABET.len() {
+        table[ALPHABET[i] as usize] = i as u8;
+        i += 1;
+    }
+    table
+};
+
+/// Errors returned by `base58_decode`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Base58Error {
+    /// The input contains a character outside of the base58 alphabet.
+    InvalidCharacter,
+    /// The output buffer is too small to hold the decoded bytes.
+    BufferTooSmall,
+}
+
+/// Encodes `bytes` as base58 into `out`.
+///
+/// Returns the number of bytes written, or `Err` if `out` is too small to
+/// hold the encoding.
+pub fn base58_encode(bytes: &[u8], out: &mut [u8]) -> Result<usize, ()> {
+    // Every leading zero byte is encoded as a single '1'.
+    let zeros = bytes.iter().take_while(|&&b| b == 0).count();
+
+    // Accumulate the base58 digits of the remaining bytes in `out`, least
+    // significant digit first.
+    let mut len = 0;
+    for &byte in &bytes[zeros..] {
+        let mut carry = byte as u32;
+        for digit in &mut out[..len] {
+            carry += (*digit as u32) << 8;
+            *digit = (carry % 58) as u8;
+            carry /= 58;
+        }
+        while carry > 0 {
+            if len == out.len() {
+                return Err(());
+            }
+            out[len] = (carry % 58) as u8;
+            len += 1;
+            carry /= 58;
+        }
+    }
+
+    let total = zeros + len;
+    if total > out.len() {
+        return Err(());
+    }
+
+    out[..len].reverse();
+    out.copy_within(..len, zeros);
+    out[..zeros].fill(ALPHABET[0]);
+    for digit in &mut out[zeros..total] {
+        *digit = ALPHABET[*digit as usize];
+    }
+    Ok(total)
+}
+
+/// Decodes the base58 string `input` into `out`.
+///
+/// Returns the number of bytes written. Each leading '1' decodes to a leading
+/// zero byte.
+pub fn base58_decode(input: &str, out: &mut [u8]) -> Result<usize, Base58Error> {
+    let input = input.as_bytes();
+    let zeros = input.iter().take_while(|&&c| c == ALPHABET[0]).count();
+
+    // Accumulate the decoded bytes in `out`, least significant byte first.
+    let mut len = 0;
+    for &c in &input[zeros..] {
+        let digit = DECODE.get(c as usize).copied().unwrap_or(INVALID);
+        if digit == INVALID {
+            return Err(Base58Error::InvalidCharacter);
+        }
+
+        let mut carry = digit as u32;
+        for byte in &mut out[..len] {
+            carry += *byte as u32 * 58;
+            *byte = carry as u8;
+            carry >>= 8;
+        }
+        while carry > 0 {
+            if len == out.len() {
+                return Err(Base58Error::BufferTooSmall);
+            }
+            out[len] = carry as u8;
+            len += 1;
+            carry >>= 8;
+        }
+    }
+
+    let total = zeros + len;
+    if total > out.len() {
+        return Err(Base58Error::BufferTooSmall);
+    }
+
+    out[..len].reverse();
+    out.copy_within(..len, zeros);
+    out[..zeros].fill(0);
+    Ok(total)
+}
@@ -0,0 +1,77 @@
+use super::*;
+
+fn encode(bytes: &[u8]) -> String {
+    let mut out = [0u8; 128];
+    let len = base58_encode(bytes, &mut out).unwrap();
+    String::from_utf8(out[..len].to_vec()).unwrap()
+}
+
+#[test]
+fn encode_known_vectors() {
+    assert_eq!(encode(b""), "");
+    assert_eq!(encode(b"a"), "2g");
+    assert_eq!(encode(b"abc"), "ZiCa");
+    assert_eq!(encode(b"Hello World!"), "2NEpo7TZRRrLZSi2U");
+    assert_eq!(encode(&[0xff; 4]), "7YXq9G");
+}
+
+#[test]
+fn encode_leading_zeros() {
+    assert_eq!(encode(&[0]), "1");
+    assert_eq!(encode(&[0, 0, 0]), "111");
+    assert_eq!(encode(&[0, 0, 0x28, 0x7f, 0xb4, 0xcd]), "11233QC4");
+    assert_eq!(encode(&[0; 32]), "11111111111111111111111111111111");
+}
+
+#[test]
+fn encode_into_too_small_buffer() {
+    let mut out = [0u8; 3];
+    assert_eq!(base58_encode(b"abc", &mut out), Err(()));
+    assert_eq!(base58_encode(&[0, 0, 0, 0], &mut out), Err(()));
+
+    let mut out = [0u8; 4];
+    assert_eq!(base58_encode(b"abc", &mut out), Ok(4));
+    assert_eq!(&out, b"ZiCa");
+}
+
+fn decode(input: &str) -> Vec<u8> {
+    let mut out = [0u8; 128];
+    let len = base58_decode(input, &mut out).unwrap();
+    out[..len].to_vec()
+}
+
+#[test]
+fn decode_round_trips_with_encode() {
+    let inputs: [&[u8]; 6] =
+        [b"", b"a", b"Hello World!", &[0, 0, 0x28, 0x7f, 0xb4, 0xcd], &[0; 32], &[0xff; 32]];
+    for input in inputs.iter() {
+        assert_eq!(decode(&encode(input)), *input);
+    }
+}
+
+#[test]
+fn decode_leading_ones() {
+    assert_eq!(decode("1"), [0]);
+    assert_eq!(decode("111"), [0, 0, 0]);
+    assert_eq!(decode("11233QC4"), [0, 0, 0x28, 0x7f, 0xb4, 0xcd]);
+}
+
+#[test]
+fn decode_invalid_character() {
+    let mut out = [0u8; 32];
+    // '0', 'O', 'I' and 'l' are deliberately excluded from the alphabet.
+    for input in ["0", "2gO", "I", "l1", "2g!", "é"].iter() {
+        assert_eq!(base58_decode(input, &mut out), Err(Base58Error::InvalidCharacter));
+    }
+}
+
+#[test]
+fn decode_into_too_small_buffer() {
+    let mut out = [0u8; 2];
+    assert_eq!(base58_decode("ZiCa", &mut out), Err(Base58Error::BufferTooSmall));
+    assert_eq!(base58_decode("111", &mut out), Err(Base58Error::BufferTooSmall));
+
+    let mut out = [0u8; 3];
+    assert_eq!(base58_decode("ZiCa", &mut out), Ok(3));
+    assert_eq!(&out, b"abc");
+}
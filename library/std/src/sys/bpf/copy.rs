@@ -0,0 +1,135 @@
+//! `io::copy` specialization for BPF.
+//!
+//! Copying from an in-memory slice into a `Vec` needs no intermediate
+//! buffer: the bytes are moved with a single `sol_memcpy_` syscall, which
+//! costs far fewer compute units than a byte-wise read/write loop.
+//!
+//! `AccountWriter` applies the same idea to account data written in many
+//! small pieces.
+
+use crate::cmp;
+use crate::io::copy::generic_copy;
+use crate::io::{Read, Result, Write};
+
+#[cfg(test)]
+mod tests;
+
+#[cfg(not(test))]
+extern "C" {
+    fn sol_memcpy_(dst: *mut u8, src: *const u8, n: u64);
+}
+
+pub(crate) fn copy_spec<R: Read + ?Sized, W: Write + ?Sized>(
+    read: &mut R,
+    write: &mut W,
+) -> Result<u64> {
+    let copier = Copier { read, write };
+    SpecCopy::copy(copier)
+}
+
+struct Copier<'a, 'b, R: Read + ?Sized, W: Write + ?Sized> {
+    read: &'a mut R,
+    write: &'b mut W,
+}
+
+trait SpecCopy {
+    fn copy(self) -> Result<u64>;
+}
+
+impl<R: Read + ?Sized, W: Write + ?Sized> SpecCopy for Copier<'_, '_, R, W> {
+    default fn copy(self) -> Result<u64> {
+        generic_copy(self.read, self.write)
+    }
+}
+
+impl SpecCopy for Copier<'_, '_, &[u8], Vec<u8>> {
+    fn copy(self) -> Result<u64> {
+        let src: &[u8] = *self.read;
+        let dst = self.write;
+        dst.reserve(src.len());
+        unsafe {
+            sol_memcpy_(dst.as_mut_ptr().add(dst.len()), src.as_ptr(), src.len() as u64);
+            dst.set_len(dst.len() + src.len());
+        }
+        // Reading consumes the slice, as `Read for &[u8]` would.
+        *self.read = &src[src.len()..];
+        Ok(src.len() as u64)
+    }
+}
+
+/// Writes into an account's data through a buffer of `N` bytes, so that a
+/// run of small writes is moved with one `sol_memcpy_` when the buffer fills
+/// up or is flushed, rather than one per write. Writes of at least `N` bytes
+/// skip the buffer.
+///
+/// Like writing to a `&mut [u8]`, writing past the end of the data writes
+/// nothing. Buffered bytes are flushed on drop.
+pub struct AccountWriter<'a, const N: usize> {
+    data: &'a mut [u8],
+    // Bytes of `data` already written.
+    pos: usize,
+    buf: [u8; N],
+    len: usize,
+}
+
+impl<'a, const N: usize> AccountWriter<'a, N> {
+    pub fn new(data: &'a mut [u8]) -> AccountWriter<'a, N> {
+        AccountWriter { data, pos: 0, buf: [0; N], len: 0 }
+    }
+
+    /// Returns the number of bytes written so far, including buffered ones.
+    pub fn position(&self) -> usize {
+        self.pos + self.len
+    }
+
+    fn copy_out(&mut self, src: *const u8, n: usize) {
+        debug_assert!(n <= self.data.len() - self.pos);
+        unsafe { sol_memcpy_(self.data.as_mut_ptr().add(self.pos), src, n as u64) };
+        self.pos += n;
+    }
+
+    fn flush_buf(&mut self) {
+        if self.len > 0 {
+            let len = self.len;
+            self.len = 0;
+            self.copy_out(self.buf.as_ptr(), len);
+        }
+    }
+}
+
+impl<const N: usize> Write for AccountWriter<'_, N> {
+    fn write(&mut self, buf: &[u8]) -> Result<usize> {
+        let n = cmp::min(buf.len(), self.data.len() - self.position());
+        if self.len + n > N {
+            self.flush_buf();
+        }
+        if n >= N {
+            self.copy_out(buf.as_ptr(), n);
+        } else {
+            self.buf[self.len..self.len + n].copy_from_slice(&buf[..n]);
+            self.len += n;
+        }
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> Result<()> {
+        self.flush_buf();
+        Ok(())
+    }
+}
+
+impl<const N: usize> Drop for AccountWriter<'_, N> {
+    fn drop(&mut self) {
+        self.flush_buf();
+    }
+}
+
+// Tests run without the runtime, so the syscall is emulated and counted.
+#[cfg(test)]
+pub static mut MEMCPY_CALLS: usize = 0;
+
+#[cfg(test)]
+unsafe fn sol_memcpy_(dst: *mut u8, src: *const u8, n: u64) {
+    MEMCPY_CALLS += 1;
+    crate::ptr::copy_nonoverlapping(src, dst, n as usize);
+}
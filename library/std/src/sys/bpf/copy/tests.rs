@@ -0,0 +1,73 @@
+use crate::io::{self, Read, Write};
+
+#[test]
+fn copy_slice_to_vec_uses_memcpy() {
+    let data: Vec<u8> = (0..4096).map(|i| i as u8).collect();
+    let mut reader = &data[..];
+    let mut writer = b"prefix".to_vec();
+
+    let calls = unsafe { super::MEMCPY_CALLS };
+    assert_eq!(io::copy(&mut reader, &mut writer).unwrap(), 4096);
+    assert_eq!(unsafe { super::MEMCPY_CALLS }, calls + 1);
+
+    assert!(reader.is_empty());
+    assert_eq!(&writer[..6], b"prefix");
+    assert_eq!(&writer[6..], &data[..]);
+}
+
+#[test]
+fn copy_falls_back_for_other_types() {
+    let mut reader = io::repeat(7).take(10);
+    let mut writer = Vec::new();
+
+    let calls = unsafe { super::MEMCPY_CALLS };
+    assert_eq!(io::copy(&mut reader, &mut writer).unwrap(), 10);
+    assert_eq!(unsafe { super::MEMCPY_CALLS }, calls);
+    assert_eq!(writer, [7; 10]);
+}
+
+#[test]
+fn account_writer_batches_small_writes() {
+    let mut data = [0u8; 64];
+    let calls = unsafe { super::MEMCPY_CALLS };
+    {
+        let mut writer = super::AccountWriter::<16>::new(&mut data);
+        for chunk in [&b"ab"[..], b"cde", b"f"].iter() {
+            writer.write_all(chunk).unwrap();
+        }
+        assert_eq!(unsafe { super::MEMCPY_CALLS }, calls);
+        assert_eq!(writer.position(), 6);
+        writer.flush().unwrap();
+        assert_eq!(unsafe { super::MEMCPY_CALLS }, calls + 1);
+    }
+    assert_eq!(unsafe { super::MEMCPY_CALLS }, calls + 1);
+    assert_eq!(&data[..7], b"abcdef\0");
+}
+
+#[test]
+fn account_writer_flushes_when_the_buffer_overflows() {
+    let mut data = [0u8; 64];
+    let calls = unsafe { super::MEMCPY_CALLS };
+    {
+        let mut writer = super::AccountWriter::<8>::new(&mut data);
+        writer.write_all(b"12345").unwrap();
+        writer.write_all(b"6789").unwrap();
+        assert_eq!(unsafe { super::MEMCPY_CALLS }, calls + 1);
+
+        // A write that does not fit the buffer at all goes out directly.
+        writer.write_all(b"abcdefghij").unwrap();
+        assert_eq!(unsafe { super::MEMCPY_CALLS }, calls + 3);
+    }
+    assert_eq!(&data[..19], b"123456789abcdefghij");
+}
+
+#[test]
+fn account_writer_stops_at_the_end_of_the_data() {
+    let mut data = [0u8; 4];
+    let mut writer = super::AccountWriter::<8>::new(&mut data);
+    assert_eq!(writer.write(b"abcdef").unwrap(), 4);
+    let err = writer.write_all(b"g").unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::WriteZero);
+    drop(writer);
+    assert_eq!(&data, b"abcd");
+}
@@ -0,0 +1,219 @@
+//! Ed25519 signature verification.
+//!
+//! The runtime has no verify syscall, so a signature `(R, s)` by the key `A`
+//! is checked as `[s]B - [k]A == R` with the curve25519 group-op syscall,
+//! where `k` is `SHA-512(R || A || message)` reduced modulo the group order.
+//! The hash and the reduction run in the program.
+
+#[cfg(test)]
+mod tests;
+#[cfg(test)]
+use self::tests::sol_curve_group_op;
+
+const CURVE25519_EDWARDS: u64 = 0;
+const SUB: u64 = 1;
+const MUL: u64 = 2;
+
+/// The compressed Ed25519 base point.
+const BASEPOINT: [u8; 32] = [
+    0x58, 0x66, 0x66, 0x66, 0x66, 0x66, 0x66, 0x66, 0x66, 0x66, 0x66, 0x66, 0x66, 0x66, 0x66, 0x66,
+    0x66, 0x66, 0x66, 0x66, 0x66, 0x66, 0x66, 0x66, 0x66, 0x66, 0x66, 0x66, 0x66, 0x66, 0x66, 0x66,
+];
+
+/// The order of the base point, `2^252 + 27742317777372353535851937790883648493`,
+/// as little-endian limbs.
+const L: [u64; 4] = [0x5812631a5cf5d3ed, 0x14def9dea2f79cd6, 0, 0x1000000000000000];
+
+#[cfg(not(test))]
+extern "C" {
+    fn sol_curve_group_op(
+        curve_id: u64,
+        group_op: u64,
+        left_input: *const u8,
+        right_input: *const u8,
+        result: *mut u8,
+    ) -> u64;
+}
+
+/// Returns whether `signature` over `message` was made by `pubkey`.
+/// Malformed keys and signatures, including ones with a non-canonical `s`,
+/// fail verification.
+pub fn ed25519_verify(message: &[u8], signature: &[u8; 64], pubkey: &[u8; 32]) -> bool {
+    let (r, s) = signature.split_at(32);
+    if !less_than(&limbs(s), &L) {
+        return false;
+    }
+    let k = to_bytes(&reduce(&limbs(&sha512(&[r, pubkey, message]))));
+    let expected_r = || -> Option<[u8; 32]> {
+        let sb = group_op(MUL, s, &BASEPOINT)?;
+        let ka = group_op(MUL, &k, pubkey)?;
+        group_op(SUB, &sb, &ka)
+    };
+    expected_r().map_or(false, |expected_r| expected_r[..] == r[..])
+}
+
+// Returns `None` if the runtime rejects the inputs, e.g. because a point is
+// not on the curve.
+fn group_op(op: u64, left: &[u8], right: &[u8]) -> Option<[u8; 32]> {
+    let mut result = [0u8; 32];
+    let status = unsafe {
+        sol_curve_group_op(CURVE25519_EDWARDS, op, left.as_ptr(), right.as_ptr(), result.as_mut_ptr())
+    };
+    if status == 0 { Some(result) } else { None }
+}
+
+// Reads little-endian bytes into limbs; `bytes` must be `8 * N` long.
+fn limbs<const N: usize>(bytes: &[u8]) -> [u64; N] {
+    let mut limbs = [0u64; N];
+    for (limb, chunk) in limbs.iter_mut().zip(bytes.chunks_exact(8)) {
+        *limb = super::parse::read_u64_le(chunk, 0).unwrap();
+    }
+    limbs
+}
+
+fn to_bytes(limbs: &[u64; 4]) -> [u8; 32] {
+    let mut bytes = [0u8; 32];
+    for (chunk, limb) in bytes.chunks_exact_mut(8).zip(limbs) {
+        chunk.copy_from_slice(&limb.to_le_bytes());
+    }
+    bytes
+}
+
+fn less_than(a: &[u64; 4], b: &[u64; 4]) -> bool {
+    for i in (0..4).rev() {
+        if a[i] != b[i] {
+            return a[i] < b[i];
+        }
+    }
+    false
+}
+
+fn sub(a: &[u64; 4], b: &[u64; 4]) -> [u64; 4] {
+    let mut result = [0u64; 4];
+    let mut borrow = false;
+    for i in 0..4 {
+        let (d, b1) = a[i].overflowing_sub(b[i]);
+        let (d, b2) = d.overflowing_sub(borrow as u64);
+        result[i] = d;
+        borrow = b1 || b2;
+    }
+    result
+}
+
+// Reduces a 512-bit number modulo `L`, one bit at a time. Slow, but it only
+// runs once per signature.
+fn reduce(wide: &[u64; 8]) -> [u64; 4] {
+    let mut r = [0u64; 4];
+    for bit in (0..512).rev() {
+        // `r < L < 2^253`, so doubling it cannot overflow.
+        r = [
+            r[0] << 1 | wide[bit / 64] >> (bit % 64) & 1,
+            r[1] << 1 | r[0] >> 63,
+            r[2] << 1 | r[1] >> 63,
+            r[3] << 1 | r[2] >> 63,
+        ];
+        if !less_than(&r, &L) {
+            r = sub(&r, &L);
+        }
+    }
+    r
+}
+
+const SHA512_K: [u64; 80] = [
+    0x428a2f98d728ae22, 0x7137449123ef65cd, 0xb5c0fbcfec4d3b2f, 0xe9b5dba58189dbbc,
+    0x3956c25bf348b538, 0x59f111f1b605d019, 0x923f82a4af194f9b, 0xab1c5ed5da6d8118,
+    0xd807aa98a3030242, 0x12835b0145706fbe, 0x243185be4ee4b28c, 0x550c7dc3d5ffb4e2,
+    0x72be5d74f27b896f, 0x80deb1fe3b1696b1, 0x9bdc06a725c71235, 0xc19bf174cf692694,
+    0xe49b69c19ef14ad2, 0xefbe4786384f25e3, 0x0fc19dc68b8cd5b5, 0x240ca1cc77ac9c65,
+    0x2de92c6f592b0275, 0x4a7484aa6ea6e483, 0x5cb0a9dcbd41fbd4, 0x76f988da831153b5,
+    0x983e5152ee66dfab, 0xa831c66d2db43210, 0xb00327c898fb213f, 0xbf597fc7beef0ee4,
+    0xc6e00bf33da88fc2, 0xd5a79147930aa725, 0x06ca6351e003826f, 0x142929670a0e6e70,
+    0x27b70a8546d22ffc, 0x2e1b21385c26c926, 0x4d2c6dfc5ac42aed, 0x53380d139d95b3df,
+    0x650a73548baf63de, 0x766a0abb3c77b2a8, 0x81c2c92e47edaee6, 0x92722c851482353b,
+    0xa2bfe8a14cf10364, 0xa81a664bbc423001, 0xc24b8b70d0f89791, 0xc76c51a30654be30,
+    0xd192e819d6ef5218, 0xd69906245565a910, 0xf40e35855771202a, 0x106aa07032bbd1b8,
+    0x19a4c116b8d2d0c8, 0x1e376c085141ab53, 0x2748774cdf8eeb99, 0x34b0bcb5e19b48a8,
+    0x391c0cb3c5c95a63, 0x4ed8aa4ae3418acb, 0x5b9cca4f7763e373, 0x682e6ff3d6b2b8a3,
+    0x748f82ee5defb2fc, 0x78a5636f43172f60, 0x84c87814a1f0ab72, 0x8cc702081a6439ec,
+    0x90befffa23631e28, 0xa4506cebde82bde9, 0xbef9a3f7b2c67915, 0xc67178f2e372532b,
+    0xca273eceea26619c, 0xd186b8c721c0c207, 0xeada7dd6cde0eb1e, 0xf57d4f7fee6ed178,
+    0x06f067aa72176fba, 0x0a637dc5a2c898a6, 0x113f9804bef90dae, 0x1b710b35131c471b,
+    0x28db77f523047d84, 0x32caab7b40c72493, 0x3c9ebe0a15c9bebc, 0x431d67c49c100d4c,
+    0x4cc5d4becb3e42b6, 0x597f299cfc657e2a, 0x5fcb6fab3ad6faec, 0x6c44198c4a475817,
+];
+
+const SHA512_INIT: [u64; 8] = [
+    0x6a09e667f3bcc908, 0xbb67ae8584caa73b, 0x3c6ef372fe94f82b, 0xa54ff53a5f1d36f1,
+    0x510e527fade682d1, 0x9b05688c2b3e6c1f, 0x1f83d9abfb41bd6b, 0x5be0cd19137e2179,
+];
+
+/// SHA-512 of the concatenation of `parts`.
+fn sha512(parts: &[&[u8]]) -> [u8; 64] {
+    let mut state = SHA512_INIT;
+    let mut block = [0u8; 128];
+    let mut block_len = 0;
+    let mut total_len = 0u128;
+    for part in parts {
+        for &byte in *part {
+            block[block_len] = byte;
+            block_len += 1;
+            if block_len == 128 {
+                sha512_compress(&mut state, &block);
+                block_len = 0;
+            }
+        }
+        total_len += part.len() as u128;
+    }
+
+    // Pad with a one bit, zeros and the length in bits, spilling into
+    // another block if the length does not fit.
+    block[block_len] = 0x80;
+    block[block_len + 1..].iter_mut().for_each(|byte| *byte = 0);
+    if block_len >= 112 {
+        sha512_compress(&mut state, &block);
+        block = [0u8; 128];
+    }
+    block[112..].copy_from_slice(&(total_len * 8).to_be_bytes());
+    sha512_compress(&mut state, &block);
+
+    let mut digest = [0u8; 64];
+    for (chunk, word) in digest.chunks_exact_mut(8).zip(&state) {
+        chunk.copy_from_slice(&word.to_be_bytes());
+    }
+    digest
+}
+
+fn sha512_compress(state: &mut [u64; 8], block: &[u8; 128]) {
+    let mut w = [0u64; 80];
+    for (word, chunk) in w.iter_mut().zip(block.chunks_exact(8)) {
+        let mut bytes = [0u8; 8];
+        bytes.copy_from_slice(chunk);
+        *word = u64::from_be_bytes(bytes);
+    }
+    for i in 16..80 {
+        let s0 = w[i - 15].rotate_right(1) ^ w[i - 15].rotate_right(8) ^ (w[i - 15] >> 7);
+        let s1 = w[i - 2].rotate_right(19) ^ w[i - 2].rotate_right(61) ^ (w[i - 2] >> 6);
+        w[i] = w[i - 16].wrapping_add(s0).wrapping_add(w[i - 7]).wrapping_add(s1);
+    }
+
+    let [mut a, mut b, mut c, mut d, mut e, mut f, mut g, mut h] = *state;
+    for i in 0..80 {
+        let s1 = e.rotate_right(14) ^ e.rotate_right(18) ^ e.rotate_right(41);
+        let ch = (e & f) ^ (!e & g);
+        let t1 = h.wrapping_add(s1).wrapping_add(ch).wrapping_add(SHA512_K[i]).wrapping_add(w[i]);
+        let s0 = a.rotate_right(28) ^ a.rotate_right(34) ^ a.rotate_right(39);
+        let maj = (a & b) ^ (a & c) ^ (b & c);
+        let t2 = s0.wrapping_add(maj);
+        h = g;
+        g = f;
+        f = e;
+        e = d.wrapping_add(t1);
+        d = c;
+        c = b;
+        b = a;
+        a = t1.wrapping_add(t2);
+    }
+    for (word, value) in state.iter_mut().zip(&[a, b, c, d, e, f, g, h]) {
+        *word = word.wrapping_add(*value);
+    }
+}
@@ -0,0 +1,297 @@
+use super::*;
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+fn unhex<const N: usize>(input: &str) -> [u8; N] {
+    let mut bytes = [0; N];
+    assert_eq!(super::super::hex::decode(input, &mut bytes), Ok(N));
+    bytes
+}
+
+// Implements the group ops on the host, following the runtime: points are
+// compressed Edwards points, and `MUL` takes a canonical scalar and a point.
+// Affine coordinates with an inversion per addition are slow, but simple.
+pub(super) unsafe fn sol_curve_group_op(
+    curve_id: u64,
+    group_op: u64,
+    left_input: *const u8,
+    right_input: *const u8,
+    result: *mut u8,
+) -> u64 {
+    let left = crate::slice::from_raw_parts(left_input, 32);
+    let right = crate::slice::from_raw_parts(right_input, 32);
+    let result = crate::slice::from_raw_parts_mut(result, 32);
+    let value = match (curve_id, group_op) {
+        (CURVE25519_EDWARDS, MUL) if less_than(&limbs(left), &L) => {
+            decompress(right).map(|point| edwards_mul(left, &point))
+        }
+        (CURVE25519_EDWARDS, SUB) => decompress(left).and_then(|left| {
+            let (x, y) = decompress(right)?;
+            Some(edwards_add(&left, &(fe_sub(&[0; 4], &x), y)))
+        }),
+        _ => None,
+    };
+    match value {
+        Some(value) => {
+            result.copy_from_slice(&compress(&value));
+            0
+        }
+        None => 1,
+    }
+}
+
+/// `2^255 - 19`, the field modulus, as little-endian limbs.
+const P: [u64; 4] = [0xffffffffffffffed, u64::MAX, u64::MAX, 0x7fffffffffffffff];
+
+/// The curve constant `d = -121665 / 121666`.
+const D: [u64; 4] =
+    [0x75eb4dca135978a3, 0x00700a4d4141d8ab, 0x8cc740797779e898, 0x52036cee2b6ffe73];
+
+/// A square root of -1.
+const SQRT_M1: [u64; 4] =
+    [0xc4ee1b274a0ea0b0, 0x2f431806ad2fe478, 0x2b4d00993dfbd7a7, 0x2b8324804fc1df0b];
+
+/// `(P - 5) / 8`, the exponent for square roots in `decompress`.
+const P_MINUS_5_OVER_8: [u64; 4] = [0xfffffffffffffffd, u64::MAX, u64::MAX, 0x0fffffffffffffff];
+
+const ONE: [u64; 4] = [1, 0, 0, 0];
+
+type Point = ([u64; 4], [u64; 4]);
+
+// Brings a number below `2 * P` into `[0, P)`.
+fn fe_canonical(a: &[u64; 4]) -> [u64; 4] {
+    if less_than(a, &P) { *a } else { sub(a, &P) }
+}
+
+fn fe_add(a: &[u64; 4], b: &[u64; 4]) -> [u64; 4] {
+    let mut sum = [0u64; 4];
+    let mut carry = 0u128;
+    for i in 0..4 {
+        let limb = a[i] as u128 + b[i] as u128 + carry;
+        sum[i] = limb as u64;
+        carry = limb >> 64;
+    }
+    fe_canonical(&sum)
+}
+
+fn fe_sub(a: &[u64; 4], b: &[u64; 4]) -> [u64; 4] {
+    fe_add(a, &sub(&P, b))
+}
+
+fn fe_mul(a: &[u64; 4], b: &[u64; 4]) -> [u64; 4] {
+    let mut wide = [0u64; 8];
+    for i in 0..4 {
+        let mut carry = 0u128;
+        for j in 0..4 {
+            let product = a[i] as u128 * b[j] as u128 + wide[i + j] as u128 + carry;
+            wide[i + j] = product as u64;
+            carry = product >> 64;
+        }
+        wide[i + 4] = carry as u64;
+    }
+    // `2^256 = 38` modulo `P`, so the high half folds onto the low one, twice
+    // to absorb the carry out of the first fold.
+    let mut folded = [0u64; 4];
+    let mut carry = 0u128;
+    for i in 0..4 {
+        let limb = wide[i] as u128 + 38 * wide[i + 4] as u128 + carry;
+        folded[i] = limb as u64;
+        carry = limb >> 64;
+    }
+    let mut carry = 38 * carry;
+    for limb in folded.iter_mut() {
+        let sum = *limb as u128 + carry;
+        *limb = sum as u64;
+        carry = sum >> 64;
+    }
+    fe_canonical(&fe_canonical(&folded))
+}
+
+fn fe_pow(a: &[u64; 4], exponent: &[u64; 4]) -> [u64; 4] {
+    let mut result = ONE;
+    for bit in (0..256).rev() {
+        result = fe_mul(&result, &result);
+        if exponent[bit / 64] >> (bit % 64) & 1 == 1 {
+            result = fe_mul(&result, a);
+        }
+    }
+    result
+}
+
+fn fe_inv(a: &[u64; 4]) -> [u64; 4] {
+    fe_pow(a, &sub(&P, &[2, 0, 0, 0]))
+}
+
+fn edwards_add(p: &Point, q: &Point) -> Point {
+    let t = fe_mul(&D, &fe_mul(&fe_mul(&p.0, &q.0), &fe_mul(&p.1, &q.1)));
+    let x = fe_add(&fe_mul(&p.0, &q.1), &fe_mul(&p.1, &q.0));
+    let y = fe_add(&fe_mul(&p.1, &q.1), &fe_mul(&p.0, &q.0));
+    (fe_mul(&x, &fe_inv(&fe_add(&ONE, &t))), fe_mul(&y, &fe_inv(&fe_sub(&ONE, &t))))
+}
+
+fn edwards_mul(scalar: &[u8], point: &Point) -> Point {
+    let mut result = ([0; 4], ONE);
+    for bit in (0..256).rev() {
+        result = edwards_add(&result, &result);
+        if scalar[bit / 8] >> (bit % 8) & 1 == 1 {
+            result = edwards_add(&result, point);
+        }
+    }
+    result
+}
+
+// Recovers `x` from `y` and its sign bit, as RFC 8032 section 5.1.3 does.
+fn decompress(bytes: &[u8]) -> Option<Point> {
+    let mut y = limbs(bytes);
+    let sign = y[3] >> 63;
+    y[3] &= !(1 << 63);
+    let y = fe_canonical(&y);
+    let yy = fe_mul(&y, &y);
+    let u = fe_sub(&yy, &ONE);
+    let v = fe_add(&fe_mul(&D, &yy), &ONE);
+    let v3 = fe_mul(&fe_mul(&v, &v), &v);
+    let uv7 = fe_mul(&fe_mul(&u, &v3), &fe_mul(&v3, &v));
+    let mut x = fe_mul(&fe_mul(&u, &v3), &fe_pow(&uv7, &P_MINUS_5_OVER_8));
+    let vxx = fe_mul(&v, &fe_mul(&x, &x));
+    if vxx != u {
+        if vxx != fe_sub(&[0; 4], &u) {
+            return None;
+        }
+        x = fe_mul(&x, &SQRT_M1);
+    }
+    if x == [0; 4] && sign == 1 {
+        return None;
+    }
+    if x[0] & 1 != sign {
+        x = fe_sub(&[0; 4], &x);
+    }
+    Some((x, y))
+}
+
+fn compress(point: &Point) -> [u8; 32] {
+    let mut bytes = to_bytes(&point.1);
+    bytes[31] |= ((point.0[0] & 1) as u8) << 7;
+    bytes
+}
+
+fn scalar_add(a: &[u64; 4], b: &[u64; 4]) -> [u64; 4] {
+    let mut wide = [0u64; 8];
+    let mut carry = 0u128;
+    for i in 0..4 {
+        let sum = a[i] as u128 + b[i] as u128 + carry;
+        wide[i] = sum as u64;
+        carry = sum >> 64;
+    }
+    wide[4] = carry as u64;
+    reduce(&wide)
+}
+
+fn scalar_mul(a: &[u64; 4], b: &[u64; 4]) -> [u64; 4] {
+    let mut wide = [0u64; 8];
+    for i in 0..4 {
+        let mut carry = 0u128;
+        for j in 0..4 {
+            let product = a[i] as u128 * b[j] as u128 + wide[i + j] as u128 + carry;
+            wide[i + j] = product as u64;
+            carry = product >> 64;
+        }
+        wide[i + 4] = carry as u64;
+    }
+    reduce(&wide)
+}
+
+// Signs `message` with the secret scalar `a` and nonce `r`, as `(R, s)` for
+// `R = [r]B` and `s = r + k * a`, and returns it with the public key `[a]B`.
+fn sign(message: &[u8], a: &[u64; 4], r: &[u64; 4]) -> ([u8; 64], [u8; 32]) {
+    let base = decompress(&BASEPOINT).unwrap();
+    let pubkey = compress(&edwards_mul(&to_bytes(a), &base));
+    let r_point = compress(&edwards_mul(&to_bytes(r), &base));
+    let k = reduce(&limbs(&sha512(&[&r_point, &pubkey, message])));
+    let s = scalar_add(r, &scalar_mul(&k, a));
+    let mut signature = [0u8; 64];
+    signature[..32].copy_from_slice(&r_point);
+    signature[32..].copy_from_slice(&to_bytes(&s));
+    (signature, pubkey)
+}
+
+#[test]
+fn sha512_matches_known_digests() {
+    assert_eq!(
+        hex(&sha512(&[b"abc"])),
+        "ddaf35a193617abacc417349ae20413112e6fa4e89a97ea20a9eeee64b55d39a\
+         2192992a274fc1a836ba3c23a3feebbd454d4423643ce80e2a9ac94fa54ca49f"
+    );
+    // Long enough that the length spills into a second block.
+    let message = b"abcdefghbcdefghicdefghijdefghijkefghijklfghijklmghijklmnhijklmno\
+                    ijklmnopjklmnopqklmnopqrlmnopqrsmnopqrstnopqrstu";
+    assert_eq!(
+        hex(&sha512(&[&message[..50], &message[50..]])),
+        "8e959b75dae313da8cf4f72814fc143f8f7779c6eb9f7fa17299aeadb6889018\
+         501d289e4900f7e4331b99dec4b5433ac7d329eeb6dd26545e96e55b874be909"
+    );
+}
+
+#[test]
+fn reduce_is_modulo_the_group_order() {
+    assert_eq!(
+        hex(&to_bytes(&reduce(&limbs(&sha512(&[b"abc"]))))),
+        "d15dbef29abf1ff29f9cf91c4b75ee0bb1012cb031d9605d684e841df034de0b"
+    );
+    assert_eq!(
+        hex(&to_bytes(&reduce(&[u64::MAX; 8]))),
+        "000f9c44e31106a447938568a71b0ed065bef517d273ecce3d9a307c1b419903"
+    );
+    let mut l = [0u64; 8];
+    l[..4].copy_from_slice(&L);
+    assert_eq!(reduce(&l), [0; 4]);
+}
+
+#[test]
+fn ed25519_verify_checks_the_signature() {
+    let message = b"transfer 10 lamports";
+    let (signature, pubkey) = sign(message, &[0x1234_5678, 7, 0, 0x0123], &[99, 0, 42, 0]);
+    assert!(ed25519_verify(message, &signature, &pubkey));
+
+    assert!(!ed25519_verify(b"transfer 11 lamports", &signature, &pubkey));
+    let mut tampered = signature;
+    tampered[40] ^= 1;
+    assert!(!ed25519_verify(message, &tampered, &pubkey));
+}
+
+#[test]
+fn ed25519_verify_accepts_rfc_8032_test_vectors() {
+    // Tests 1 and 2 of RFC 8032, section 7.1.
+    let pubkey = unhex("d75a980182b10ab7d54bfed3c964073a0ee172f3daa62325af021a68f707511a");
+    let signature = unhex(
+        "e5564300c360ac729086e2cc806e828a84877f1eb8e5d974d873e06522490155\
+         5fb8821590a33bacc61e39701cf9b46bd25bf5f0595bbe24655141438e7a100b",
+    );
+    assert!(ed25519_verify(b"", &signature, &pubkey));
+    assert!(!ed25519_verify(b"\x72", &signature, &pubkey));
+
+    let pubkey = unhex("3d4017c3e843895a92b70aa74d1b7ebc9c982ccf2ec4968cc0cd55f12af4660c");
+    let signature = unhex(
+        "92a009a9f0d4cab8720e820b5f642540a2b27b5416503f8fb3762223ebdb69da\
+         085ac1e43e15996e458f3613d0f11d8c387b2eaeb4302aeeb00d291612bb0c00",
+    );
+    assert!(ed25519_verify(b"\x72", &signature, &pubkey));
+    assert!(!ed25519_verify(b"", &signature, &pubkey));
+}
+
+#[test]
+fn ed25519_verify_rejects_malformed_input() {
+    let message = b"transfer 10 lamports";
+    let (signature, pubkey) = sign(message, &[5, 0, 0, 0], &[9, 0, 0, 0]);
+
+    // `L` reduces to zero, but is not a canonical scalar.
+    let mut non_canonical = signature;
+    non_canonical[32..].copy_from_slice(&to_bytes(&L));
+    assert!(!ed25519_verify(message, &non_canonical, &pubkey));
+
+    // No point has `y = 8`.
+    let mut not_a_point = [0; 32];
+    not_a_point[0] = 8;
+    assert!(!ed25519_verify(message, &signature, &not_a_point));
+}
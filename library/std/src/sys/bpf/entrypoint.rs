@@ -0,0 +1,85 @@
+//! Helpers for the serialized program input.
+//!
+//! The runtime passes the accounts and instruction data to the entrypoint as
+//! a single buffer and reads the accounts back from it once the program
+//! returns. Each account is laid out as
+//!
+//! ```text
+//! u8       duplicate marker (u8::MAX, or the index of the account it duplicates)
+//! u8       is_signer        \
+//! u8       is_writable       | only present if not a duplicate, otherwise
+//! u8       executable        | replaced by 7 bytes of padding
+//! [u8; 4]  padding          /
+//! [u8; 32] key
+//! [u8; 32] owner
+//! u64      lamports
+//! u64      data length
+//! [u8]     data, followed by MAX_PERMITTED_DATA_INCREASE spare bytes,
+//!          padded to an 8 byte boundary
+//! u64      rent epoch
+//! ```
+//!
+//! preceded by the number of accounts as a `u64`.
+
+use crate::mem::size_of;
+use crate::ptr;
+
+#[cfg(test)]
+mod tests;
+
+/// Number of bytes an account's data may grow by during an instruction.
+pub const MAX_PERMITTED_DATA_INCREASE: usize = 10 * 1024;
+
+const NON_DUP_MARKER: u8 = u8::MAX;
+const ACCOUNT_DATA_ALIGN: usize = 8;
+
+// Offset of the data length field from the start of an account.
+const DATA_LEN_OFFSET: usize = 8 + 32 + 32 + size_of::<u64>();
+
+unsafe fn read_u64(input: *const u8, offset: usize) -> u64 {
+    ptr::read_unaligned(input.add(offset) as *const u64)
+}
+
+/// Returns the offset of the (non-duplicate) account `account_index` refers
+/// to. Panics if there are not that many accounts.
+unsafe fn account_offset(input: *const u8, account_index: usize) -> usize {
+    let num_accounts = read_u64(input, 0) as usize;
+    assert!(account_index < num_accounts, "account index {} out of range", account_index);
+
+    let mut offset = size_of::<u64>();
+    for index in 0..account_index {
+        let dup_info = *input.add(offset);
+        if dup_info == NON_DUP_MARKER {
+            let data_len = read_u64(input, offset + DATA_LEN_OFFSET) as usize;
+            offset += DATA_LEN_OFFSET + size_of::<u64>() + data_len + MAX_PERMITTED_DATA_INCREASE;
+            offset = (offset + ACCOUNT_DATA_ALIGN - 1) & !(ACCOUNT_DATA_ALIGN - 1);
+            offset += size_of::<u64>(); // rent epoch
+        } else {
+            debug_assert!((dup_info as usize) < index);
+            offset += 8;
+        }
+    }
+
+    match *input.add(offset) {
+        NON_DUP_MARKER => offset,
+        original => account_offset(input, original as usize),
+    }
+}
+
+/// Writes `new_len` as the data length of account `account_index` back into
+/// the serialized program `input`, so that the runtime picks up the resized
+/// data. A duplicate account updates the account it duplicates.
+///
+/// Accounts are located through the lengths stored in `input`, so once an
+/// account's length has changed, the accounts after it can no longer be
+/// found. Commit resized accounts starting with the last one.
+///
+/// # Safety
+///
+/// `input` must point to the program input as serialized by the runtime.
+/// `new_len` must not exceed the original length by more than
+/// `MAX_PERMITTED_DATA_INCREASE`.
+pub unsafe fn commit_account_data(input: *mut u8, account_index: usize, new_len: u64) {
+    let offset = account_offset(input, account_index) + DATA_LEN_OFFSET;
+    ptr::write_unaligned(input.add(offset) as *mut u64, new_len);
+}
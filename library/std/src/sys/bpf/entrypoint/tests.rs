@@ -0,0 +1,39 @@
+use super::*;
+
+fn push_account(input: &mut Vec<u8>, data_len: usize) {
+    input.push(NON_DUP_MARKER);
+    input.extend_from_slice(&[1, 1, 0, 0, 0, 0, 0]);
+    input.extend_from_slice(&[0; 64]);
+    input.extend_from_slice(&100u64.to_le_bytes());
+    input.extend_from_slice(&(data_len as u64).to_le_bytes());
+    input.resize(input.len() + data_len + MAX_PERMITTED_DATA_INCREASE, 0);
+    input.resize((input.len() + 7) / 8 * 8, 0);
+    input.extend_from_slice(&0u64.to_le_bytes());
+}
+
+fn read_len(input: &[u8], offset: usize) -> u64 {
+    let mut bytes = [0; 8];
+    bytes.copy_from_slice(&input[offset..offset + 8]);
+    u64::from_le_bytes(bytes)
+}
+
+#[test]
+fn commit_updates_data_len() {
+    let mut input = 3u64.to_le_bytes().to_vec();
+    let first = input.len();
+    push_account(&mut input, 3);
+    input.extend_from_slice(&[0, 0, 0, 0, 0, 0, 0, 0]); // duplicate of account 0
+    let third = input.len();
+    push_account(&mut input, 5);
+
+    // 8 (header) + 64 (key, owner) + 8 (lamports), then 3 + 10240 bytes of
+    // data padded to 10248 and the rent epoch.
+    assert_eq!(third, first + 80 + 8 + 10248 + 8 + 8);
+
+    unsafe { commit_account_data(input.as_mut_ptr(), 2, 9) };
+    assert_eq!(read_len(&input, third + 80), 9);
+    assert_eq!(read_len(&input, first + 80), 3);
+
+    unsafe { commit_account_data(input.as_mut_ptr(), 1, 7) };
+    assert_eq!(read_len(&input, first + 80), 7);
+}
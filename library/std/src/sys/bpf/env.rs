@@ -1,3 +1,9 @@
+use crate::cell::{Cell, UnsafeCell};
+use crate::ffi::{OsStr, OsString};
+
+#[cfg(test)]
+mod tests;
+
 pub mod os {
     pub const FAMILY: &str = "";
     pub const OS: &str = "";
@@ -7,3 +13,62 @@ pub mod os {
     pub const EXE_SUFFIX: &str = ".so";
     pub const EXE_EXTENSION: &str = "so";
 }
+
+/// Variables returned by `getenv`. There is no environment on BPF, so it is
+/// empty unless seeded with `set_env` or changed with `setenv`.
+struct EnvTable {
+    vars: UnsafeCell<Vec<(OsString, OsString)>>,
+    // Whether keys are compared ignoring ASCII case, like on Windows.
+    case_insensitive: Cell<bool>,
+}
+
+unsafe impl Sync for EnvTable {} // no threads on BPF
+
+static ENV: EnvTable =
+    EnvTable { vars: UnsafeCell::new(Vec::new()), case_insensitive: Cell::new(false) };
+
+/// Replaces the environment with `vars`. Later entries win over earlier ones
+/// with a matching key.
+pub fn set_env(vars: Vec<(OsString, OsString)>) {
+    unsafe { (*ENV.vars.get()).clear() };
+    for (k, v) in vars {
+        set(&k, &v);
+    }
+}
+
+/// Makes keys match ignoring ASCII case, so that code written for Windows
+/// finds `Path` when asking for `PATH`. Keys are case-sensitive by default.
+pub fn set_env_case_insensitive(enabled: bool) {
+    ENV.case_insensitive.set(enabled);
+}
+
+fn keys_match(a: &OsStr, b: &OsStr) -> bool {
+    if ENV.case_insensitive.get() { a.eq_ignore_ascii_case(b) } else { a == b }
+}
+
+/// Runs `f` with exclusive access to the variables. `f` must not call back
+/// into this module.
+fn with_vars<R>(f: impl FnOnce(&mut Vec<(OsString, OsString)>) -> R) -> R {
+    f(unsafe { &mut *ENV.vars.get() })
+}
+
+pub(crate) fn get(k: &OsStr) -> Option<OsString> {
+    with_vars(|vars| vars.iter().find(|(key, _)| keys_match(key, k)).map(|(_, v)| v.clone()))
+}
+
+/// Sets `k` to `v`. An existing variable with a matching key keeps its
+/// original spelling.
+pub(crate) fn set(k: &OsStr, v: &OsStr) {
+    with_vars(|vars| match vars.iter_mut().find(|(key, _)| keys_match(key, k)) {
+        Some((_, value)) => *value = v.to_os_string(),
+        None => vars.push((k.to_os_string(), v.to_os_string())),
+    })
+}
+
+pub(crate) fn remove(k: &OsStr) {
+    with_vars(|vars| vars.retain(|(key, _)| !keys_match(key, k)))
+}
+
+pub(crate) fn vars() -> Vec<(OsString, OsString)> {
+    with_vars(|vars| vars.clone())
+}
@@ -0,0 +1,31 @@
+use super::*;
+
+fn seed() {
+    set_env(vec![("Path".into(), "/bin".into()), ("HOME".into(), "/home".into())]);
+}
+
+#[test]
+fn keys_are_case_sensitive_by_default() {
+    seed();
+    set_env_case_insensitive(false);
+    assert_eq!(get(OsStr::new("PATH")), None);
+    assert_eq!(get(OsStr::new("Path")), Some("/bin".into()));
+
+    set(OsStr::new("PATH"), OsStr::new("/usr/bin"));
+    assert_eq!(vars().len(), 3);
+}
+
+#[test]
+fn case_insensitive_keys_match_any_case() {
+    seed();
+    set_env_case_insensitive(true);
+    assert_eq!(get(OsStr::new("PATH")), Some("/bin".into()));
+    assert_eq!(get(OsStr::new("home")), Some("/home".into()));
+
+    set(OsStr::new("PATH"), OsStr::new("/usr/bin"));
+    assert_eq!(vars()[0], ("Path".into(), "/usr/bin".into()));
+
+    remove(OsStr::new("path"));
+    assert_eq!(get(OsStr::new("Path")), None);
+    set_env_case_insensitive(false);
+}
@@ -1,138 +1,157 @@
+//! A minimal in-memory filesystem.
+//!
+//! BPF programs have no access to a real filesystem, so files live in a
+//! process-wide map from normalized path to contents. Directories are not
+//! stored explicitly; they only exist as prefixes of file paths.
+
+use crate::cell::{Cell, UnsafeCell};
+use crate::collections::BTreeMap;
 use crate::ffi::OsString;
 use crate::fmt;
-use crate::hash::{Hash, Hasher};
 use crate::io::{self, SeekFrom, IoSlice, IoSliceMut};
 use crate::path::{Path, PathBuf};
-use crate::sys::time::SystemTime;
-use crate::sys::{unsupported, Void};
+use crate::sys::path::normalize;
+use crate::sys::time::{self, SystemTime};
+use crate::sys::unsupported;
 
-pub struct File(Void);
+#[cfg(test)]
+mod tests;
 
-pub struct FileAttr(Void);
+struct Node {
+    data: Vec<u8>,
+    // Clock sysvar time of the last write, if the sysvar could be read.
+    modified: Option<SystemTime>,
+}
+
+impl Node {
+    fn touch(&mut self) {
+        self.modified =
+            time::clock().ok().map(|clock| SystemTime::from_unix_timestamp(clock.unix_timestamp));
+    }
+}
+
+struct Files {
+    map: UnsafeCell<Option<BTreeMap<PathBuf, Node>>>,
+}
+
+unsafe impl Sync for Files {} // no threads on BPF
 
-pub struct ReadDir(Void);
+static FILES: Files = Files { map: UnsafeCell::new(None) };
 
-pub struct DirEntry(Void);
+/// Runs `f` with exclusive access to the file map. `f` must not call back
+/// into this module.
+fn with_files<R>(f: impl FnOnce(&mut BTreeMap<PathBuf, Node>) -> R) -> R {
+    let map = unsafe { &mut *FILES.map.get() };
+    f(map.get_or_insert_with(BTreeMap::new))
+}
+
+fn not_found() -> io::Error {
+    io::Error::new_const(io::ErrorKind::NotFound, &"no such file in the virtual filesystem")
+}
+
+pub struct File {
+    path: PathBuf,
+    pos: Cell<u64>,
+    read: bool,
+    write: bool,
+    append: bool,
+}
+
+#[derive(Clone)]
+pub struct FileAttr {
+    size: u64,
+    file_type: FileType,
+    modified: Option<SystemTime>,
+}
+
+pub struct ReadDir {
+    entries: crate::vec::IntoIter<DirEntry>,
+}
+
+pub struct DirEntry {
+    path: PathBuf,
+    name: OsString,
+    file_type: FileType,
+}
 
 #[derive(Clone, Debug)]
-pub struct OpenOptions { }
+pub struct OpenOptions {
+    read: bool,
+    write: bool,
+    append: bool,
+    truncate: bool,
+    create: bool,
+    create_new: bool,
+}
 
-pub struct FilePermissions(Void);
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct FilePermissions {
+    readonly: bool,
+}
 
-pub struct FileType(Void);
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug)]
+pub struct FileType {
+    is_dir: bool,
+}
 
 #[derive(Debug)]
 pub struct DirBuilder { }
 
 impl FileAttr {
     pub fn size(&self) -> u64 {
-        match self.0 {}
+        self.size
     }
 
     pub fn perm(&self) -> FilePermissions {
-        match self.0 {}
+        FilePermissions { readonly: false }
     }
 
     pub fn file_type(&self) -> FileType {
-        match self.0 {}
+        self.file_type
     }
 
     pub fn modified(&self) -> io::Result<SystemTime> {
-        match self.0 {}
+        self.modified.ok_or_else(|| {
+            io::Error::new_const(io::ErrorKind::Other, &"modification time is not available")
+        })
     }
 
     pub fn accessed(&self) -> io::Result<SystemTime> {
-        match self.0 {}
+        unsupported()
     }
 
     pub fn created(&self) -> io::Result<SystemTime> {
-        match self.0 {}
-    }
-}
-
-impl Clone for FileAttr {
-    fn clone(&self) -> FileAttr {
-        match self.0 {}
+        unsupported()
     }
 }
 
 impl FilePermissions {
     pub fn readonly(&self) -> bool {
-        match self.0 {}
-    }
-
-    pub fn set_readonly(&mut self, _readonly: bool) {
-        match self.0 {}
+        self.readonly
     }
-}
-
-impl Clone for FilePermissions {
-    fn clone(&self) -> FilePermissions {
-        match self.0 {}
-    }
-}
-
-impl PartialEq for FilePermissions {
-    fn eq(&self, _other: &FilePermissions) -> bool {
-        match self.0 {}
-    }
-}
-
-impl Eq for FilePermissions {
-}
 
-impl fmt::Debug for FilePermissions {
-    fn fmt(&self, _f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        match self.0 {}
+    pub fn set_readonly(&mut self, readonly: bool) {
+        self.readonly = readonly;
     }
 }
 
 impl FileType {
     pub fn is_dir(&self) -> bool {
-        match self.0 {}
+        self.is_dir
     }
 
     pub fn is_file(&self) -> bool {
-        match self.0 {}
+        !self.is_dir
     }
 
     pub fn is_symlink(&self) -> bool {
-        match self.0 {}
-    }
-}
-
-impl Clone for FileType {
-    fn clone(&self) -> FileType {
-        match self.0 {}
-    }
-}
-
-impl Copy for FileType {}
-
-impl PartialEq for FileType {
-    fn eq(&self, _other: &FileType) -> bool {
-        match self.0 {}
-    }
-}
-
-impl Eq for FileType {
-}
-
-impl Hash for FileType {
-    fn hash<H: Hasher>(&self, _h: &mut H) {
-        match self.0 {}
-    }
-}
-
-impl fmt::Debug for FileType {
-    fn fmt(&self, _f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        match self.0 {}
+        false
     }
 }
 
 impl fmt::Debug for ReadDir {
-    fn fmt(&self, _f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        match self.0 {}
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list().entries(self.entries.as_slice().iter().map(|e| &e.path)).finish()
     }
 }
 
@@ -140,68 +159,125 @@ impl Iterator for ReadDir {
     type Item = io::Result<DirEntry>;
 
     fn next(&mut self) -> Option<io::Result<DirEntry>> {
-        match self.0 {}
+        self.entries.next().map(Ok)
     }
 }
 
 impl DirEntry {
     pub fn path(&self) -> PathBuf {
-        match self.0 {}
+        self.path.clone()
     }
 
     pub fn file_name(&self) -> OsString {
-        match self.0 {}
+        self.name.clone()
     }
 
     pub fn metadata(&self) -> io::Result<FileAttr> {
-        match self.0 {}
+        stat(&self.path)
     }
 
     pub fn file_type(&self) -> io::Result<FileType> {
-        match self.0 {}
+        Ok(self.file_type)
     }
 }
 
 impl OpenOptions {
     pub fn new() -> OpenOptions {
-        OpenOptions { }
+        OpenOptions {
+            read: false,
+            write: false,
+            append: false,
+            truncate: false,
+            create: false,
+            create_new: false,
+        }
     }
 
-    pub fn read(&mut self, _read: bool) { }
-    pub fn write(&mut self, _write: bool) { }
-    pub fn append(&mut self, _append: bool) { }
-    pub fn truncate(&mut self, _truncate: bool) { }
-    pub fn create(&mut self, _create: bool) { }
-    pub fn create_new(&mut self, _create_new: bool) { }
+    pub fn read(&mut self, read: bool) { self.read = read; }
+    pub fn write(&mut self, write: bool) { self.write = write; }
+    pub fn append(&mut self, append: bool) { self.append = append; }
+    pub fn truncate(&mut self, truncate: bool) { self.truncate = truncate; }
+    pub fn create(&mut self, create: bool) { self.create = create; }
+    pub fn create_new(&mut self, create_new: bool) { self.create_new = create_new; }
 }
 
 impl File {
-    pub fn open(_path: &Path, _opts: &OpenOptions) -> io::Result<File> {
-        unsupported()
+    pub fn open(path: &Path, opts: &OpenOptions) -> io::Result<File> {
+        let writable = opts.write || opts.append;
+        if !opts.read && !writable {
+            return Err(io::Error::new_const(io::ErrorKind::InvalidInput, &"invalid access mode"));
+        }
+        if (opts.truncate || opts.create || opts.create_new) && !writable {
+            return Err(io::Error::new_const(io::ErrorKind::InvalidInput, &"invalid creation mode"));
+        }
+        let path = normalize(path);
+        with_files(|files| {
+            match files.get_mut(&path) {
+                // Like O_EXCL: `create_new` never opens an existing file.
+                Some(_) if opts.create_new => {
+                    return Err(io::Error::new_const(io::ErrorKind::AlreadyExists,
+                                                    &"file already exists"));
+                }
+                Some(node) => {
+                    if opts.truncate {
+                        node.data.clear();
+                        node.touch();
+                    }
+                }
+                None if opts.create || opts.create_new => {
+                    let mut node = Node { data: Vec::new(), modified: None };
+                    node.touch();
+                    files.insert(path.clone(), node);
+                }
+                None => return Err(not_found()),
+            }
+            Ok(())
+        })?;
+        Ok(File {
+            path,
+            pos: Cell::new(0),
+            read: opts.read,
+            write: writable,
+            append: opts.append,
+        })
     }
 
     pub fn file_attr(&self) -> io::Result<FileAttr> {
-        match self.0 {}
+        stat(&self.path)
     }
 
     pub fn fsync(&self) -> io::Result<()> {
-        match self.0 {}
+        Ok(())
     }
 
     pub fn datasync(&self) -> io::Result<()> {
-        match self.0 {}
+        Ok(())
     }
 
-    pub fn truncate(&self, _size: u64) -> io::Result<()> {
-        match self.0 {}
+    pub fn truncate(&self, size: u64) -> io::Result<()> {
+        self.check_access(self.write)?;
+        with_files(|files| {
+            let node = files.get_mut(&self.path).ok_or_else(not_found)?;
+            node.data.resize(size as usize, 0);
+            node.touch();
+            Ok(())
+        })
     }
 
-    pub fn read(&self, _buf: &mut [u8]) -> io::Result<usize> {
-        match self.0 {}
+    pub fn read(&self, buf: &mut [u8]) -> io::Result<usize> {
+        self.check_access(self.read)?;
+        with_files(|files| {
+            let data = &files.get(&self.path).ok_or_else(not_found)?.data;
+            let start = (self.pos.get() as usize).min(data.len());
+            let n = buf.len().min(data.len() - start);
+            buf[..n].copy_from_slice(&data[start..start + n]);
+            self.pos.set((start + n) as u64);
+            Ok(n)
+        })
     }
 
-    pub fn read_vectored(&self, _bufs: &mut [IoSliceMut<'_>]) -> io::Result<usize> {
-        match self.0 {}
+    pub fn read_vectored(&self, bufs: &mut [IoSliceMut<'_>]) -> io::Result<usize> {
+        crate::io::default_read_vectored(|buf| self.read(buf), bufs)
     }
 
    #[inline]
@@ -209,12 +285,24 @@ pub fn is_read_vectored(&self) -> bool {
         false
     }
 
-    pub fn write(&self, _buf: &[u8]) -> io::Result<usize> {
-        match self.0 {}
+    pub fn write(&self, buf: &[u8]) -> io::Result<usize> {
+        self.check_access(self.write)?;
+        with_files(|files| {
+            let node = files.get_mut(&self.path).ok_or_else(not_found)?;
+            let start = if self.append { node.data.len() } else { self.pos.get() as usize };
+            let end = start + buf.len();
+            if end > node.data.len() {
+                node.data.resize(end, 0);
+            }
+            node.data[start..end].copy_from_slice(buf);
+            node.touch();
+            self.pos.set(end as u64);
+            Ok(buf.len())
+        })
     }
 
-    pub fn write_vectored(&self, _bufs: &[IoSlice<'_>]) -> io::Result<usize> {
-        match self.0 {}
+    pub fn write_vectored(&self, bufs: &[IoSlice<'_>]) -> io::Result<usize> {
+        crate::io::default_write_vectored(|buf| self.write(buf), bufs)
     }
 
     #[inline]
@@ -223,23 +311,53 @@ pub fn is_write_vectored(&self) -> bool {
     }
 
     pub fn flush(&self) -> io::Result<()> {
-        match self.0 {}
-    }
-
-    pub fn seek(&self, _pos: SeekFrom) -> io::Result<u64> {
-        match self.0 {}
+        Ok(())
+    }
+
+    pub fn seek(&self, pos: SeekFrom) -> io::Result<u64> {
+        let (base, offset) = match pos {
+            SeekFrom::Start(n) => {
+                self.pos.set(n);
+                return Ok(n);
+            }
+            SeekFrom::End(n) => {
+                let len = with_files(|files| {
+                    files.get(&self.path).map(|node| node.data.len() as u64).ok_or_else(not_found)
+                })?;
+                (len, n)
+            }
+            SeekFrom::Current(n) => (self.pos.get(), n),
+        };
+        let new_pos = if offset >= 0 {
+            base.checked_add(offset as u64)
+        } else {
+            base.checked_sub(offset.unsigned_abs())
+        };
+        match new_pos {
+            Some(n) => {
+                self.pos.set(n);
+                Ok(n)
+            }
+            None => Err(io::Error::new_const(io::ErrorKind::InvalidInput,
+                                             &"invalid seek to a negative or overflowing offset")),
+        }
     }
 
     pub fn duplicate(&self) -> io::Result<File> {
-        match self.0 {}
+        unsupported()
     }
 
     pub fn set_permissions(&self, _perm: FilePermissions) -> io::Result<()> {
-        match self.0 {}
+        unsupported()
     }
 
-    pub fn diverge(&self) -> ! {
-        match self.0 {}
+    fn check_access(&self, allowed: bool) -> io::Result<()> {
+        if allowed {
+            Ok(())
+        } else {
+            Err(io::Error::new_const(io::ErrorKind::PermissionDenied,
+                                     &"file not opened with the required access"))
+        }
     }
 }
 
@@ -254,27 +372,68 @@ pub fn mkdir(&self, _p: &Path) -> io::Result<()> {
 }
 
 impl fmt::Debug for File {
-    fn fmt(&self, _f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        match self.0 {}
-    }
-}
-
-pub fn readdir(_p: &Path) -> io::Result<ReadDir> {
-    unsupported()
-}
-
-pub fn unlink(_p: &Path) -> io::Result<()> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("File").field("path", &self.path).finish()
+    }
+}
+
+/// Lists the immediate children of `p`. Anything stored below a child is
+/// reported as a single directory entry for that child.
+///
+/// Entries are yielded sorted by name, whatever order they were created in,
+/// so that output depending on a directory listing is reproducible.
+pub fn readdir(p: &Path) -> io::Result<ReadDir> {
+    let dir = normalize(p);
+    let children = with_files(|files| {
+        let mut children = BTreeMap::new();
+        for path in files.keys() {
+            if *path == dir {
+                return Err(io::Error::new_const(io::ErrorKind::Other, &"not a directory"));
+            }
+            let mut rest = match path.strip_prefix(&dir) {
+                Ok(rest) => rest.components(),
+                Err(_) => continue,
+            };
+            if let Some(name) = rest.next() {
+                let is_dir = rest.next().is_some();
+                *children.entry(name.as_os_str().to_owned()).or_insert(false) |= is_dir;
+            }
+        }
+        Ok(children)
+    })?;
+    if children.is_empty() && dir.parent().is_some() {
+        return Err(not_found());
+    }
+    let entries: Vec<DirEntry> = children
+        .into_iter()
+        .map(|(name, is_dir)| DirEntry {
+            path: dir.join(&name),
+            name,
+            file_type: FileType { is_dir },
+        })
+        .collect();
+    Ok(ReadDir { entries: entries.into_iter() })
+}
+
+pub fn unlink(p: &Path) -> io::Result<()> {
+    let path = normalize(p);
+    with_files(|files| files.remove(&path).map(drop).ok_or_else(not_found))
+}
+
+/// Moves the contents of `old` to `new`, replacing any existing file there.
+pub fn rename(old: &Path, new: &Path) -> io::Result<()> {
+    let (old, new) = (normalize(old), normalize(new));
+    with_files(|files| {
+        let node = files.remove(&old).ok_or_else(not_found)?;
+        files.insert(new, node);
+        Ok(())
+    })
+}
+
+pub fn set_perm(_p: &Path, _perm: FilePermissions) -> io::Result<()> {
     unsupported()
 }
 
-pub fn rename(_old: &Path, _new: &Path) -> io::Result<()> {
-    unsupported()
-}
-
-pub fn set_perm(_p: &Path, perm: FilePermissions) -> io::Result<()> {
-    match perm.0 {}
-}
-
 pub fn rmdir(_p: &Path) -> io::Result<()> {
     unsupported()
 }
@@ -299,12 +458,27 @@ pub fn link(_src: &Path, _dst: &Path) -> io::Result<()> {
     unsupported()
 }
 
-pub fn stat(_p: &Path) -> io::Result<FileAttr> {
-    unsupported()
-}
-
-pub fn lstat(_p: &Path) -> io::Result<FileAttr> {
-    unsupported()
+/// Files report their contents' length and last write time. Any other path
+/// that prefixes a stored file is a directory.
+pub fn stat(p: &Path) -> io::Result<FileAttr> {
+    let path = normalize(p);
+    with_files(|files| {
+        if let Some(node) = files.get(&path) {
+            return Ok(FileAttr {
+                size: node.data.len() as u64,
+                file_type: FileType { is_dir: false },
+                modified: node.modified,
+            });
+        }
+        if path.parent().is_none() || files.keys().any(|k| k.starts_with(&path)) {
+            return Ok(FileAttr { size: 0, file_type: FileType { is_dir: true }, modified: None });
+        }
+        Err(not_found())
+    })
+}
+
+pub fn lstat(p: &Path) -> io::Result<FileAttr> {
+    stat(p)
 }
 
 pub fn canonicalize(_p: &Path) -> io::Result<PathBuf> {
@@ -0,0 +1,191 @@
+use super::*;
+use crate::ffi::OsStr;
+use crate::sys_common::os_str_bytes::OsStrExt;
+
+fn create_new(path: &str) -> io::Result<File> {
+    let mut opts = OpenOptions::new();
+    opts.write(true);
+    opts.create_new(true);
+    File::open(Path::new(path), &opts)
+}
+
+fn create(path: &str) -> io::Result<File> {
+    let mut opts = OpenOptions::new();
+    opts.write(true);
+    opts.create(true);
+    opts.truncate(true);
+    File::open(Path::new(path), &opts)
+}
+
+fn read_to_vec(path: &str) -> io::Result<Vec<u8>> {
+    let mut opts = OpenOptions::new();
+    opts.read(true);
+    let file = File::open(Path::new(path), &opts)?;
+    let mut buf = [0u8; 64];
+    let n = file.read(&mut buf)?;
+    Ok(buf[..n].to_vec())
+}
+
+#[test]
+fn create_new_fails_if_file_exists() {
+    create_new("/create_new/a").unwrap().write(b"first").unwrap();
+    let err = create_new("/create_new/a").unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::AlreadyExists);
+    assert_eq!(read_to_vec("/create_new/a").unwrap(), b"first");
+
+    // Paths are compared after normalization.
+    let err = create_new("/create_new/./b/../a").unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::AlreadyExists);
+}
+
+#[test]
+fn create_truncates_existing_file() {
+    create("/create/a").unwrap().write(b"longer contents").unwrap();
+    create("/create/a").unwrap().write(b"short").unwrap();
+    assert_eq!(read_to_vec("/create/a").unwrap(), b"short");
+}
+
+#[test]
+fn open_missing_file() {
+    let err = read_to_vec("/open/missing").unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::NotFound);
+}
+
+#[test]
+fn read_dir_lists_entries() {
+    create("/d/a").unwrap();
+    create("/d/b").unwrap();
+    create("/dd/c").unwrap();
+
+    let entries: Vec<_> = readdir(Path::new("/d")).unwrap().map(|e| e.unwrap()).collect();
+    assert_eq!(entries.len(), 2);
+    assert_eq!(entries[0].file_name(), "a");
+    assert_eq!(entries[0].path(), Path::new("/d/a"));
+    assert!(entries[0].file_type().unwrap().is_file());
+    assert_eq!(entries[1].file_name(), "b");
+}
+
+#[test]
+fn read_dir_lists_only_immediate_children() {
+    create("/nested/a").unwrap();
+    create("/nested/sub/b").unwrap();
+    create("/nested/sub/c").unwrap();
+
+    let entries: Vec<_> = readdir(Path::new("/nested")).unwrap().map(|e| e.unwrap()).collect();
+    let names: Vec<_> = entries.iter().map(|e| e.file_name()).collect();
+    assert_eq!(names, ["a", "sub"]);
+    assert!(entries[1].file_type().unwrap().is_dir());
+
+    let sub: Vec<_> =
+        readdir(Path::new("/nested/sub/")).unwrap().map(|e| e.unwrap().path()).collect();
+    assert_eq!(sub, [Path::new("/nested/sub/b"), Path::new("/nested/sub/c")]);
+}
+
+#[test]
+fn read_dir_yields_entries_sorted_by_name() {
+    for name in &["/sorted/m", "/sorted/b/x", "/sorted/z", "/sorted/a", "/sorted/B"] {
+        create(name).unwrap();
+    }
+
+    let names: Vec<_> =
+        readdir(Path::new("/sorted")).unwrap().map(|e| e.unwrap().file_name()).collect();
+    assert_eq!(names, ["B", "a", "b", "m", "z"]);
+}
+
+#[test]
+fn read_dir_missing_directory() {
+    let err = readdir(Path::new("/no/such/dir")).unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::NotFound);
+}
+
+#[test]
+fn metadata_reports_len_and_modified() {
+    unsafe { time::MOCK_CLOCK.unix_timestamp = 1_600_000_000 };
+    let file = create("/meta/a").unwrap();
+    assert_eq!(file.write(b"0123456789").unwrap(), 10);
+
+    let attr = stat(Path::new("/meta/a")).unwrap();
+    assert_eq!(attr.size(), 10);
+    assert!(attr.file_type().is_file());
+    assert_eq!(attr.modified().unwrap(), SystemTime::from_unix_timestamp(1_600_000_000));
+
+    unsafe { time::MOCK_CLOCK.unix_timestamp = 1_600_000_042 };
+    file.write(b"!").unwrap();
+    let attr = file.file_attr().unwrap();
+    assert_eq!(attr.size(), 11);
+    assert_eq!(attr.modified().unwrap(), SystemTime::from_unix_timestamp(1_600_000_042));
+
+    assert!(stat(Path::new("/meta")).unwrap().file_type().is_dir());
+    assert_eq!(stat(Path::new("/meta/b")).err().map(|e| e.kind()), Some(io::ErrorKind::NotFound));
+}
+
+#[test]
+fn remove_file() {
+    create("/remove/a").unwrap();
+    unlink(Path::new("/remove/a")).unwrap();
+    assert_eq!(read_to_vec("/remove/a").unwrap_err().kind(), io::ErrorKind::NotFound);
+
+    let err = unlink(Path::new("/remove/a")).unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::NotFound);
+}
+
+#[test]
+fn rename_over_existing_destination() {
+    create("/rename/a").unwrap().write(b"source").unwrap();
+    create("/rename/b").unwrap().write(b"destination").unwrap();
+    rename(Path::new("/rename/a"), Path::new("/rename/b")).unwrap();
+
+    assert_eq!(read_to_vec("/rename/b").unwrap(), b"source");
+    assert_eq!(read_to_vec("/rename/a").unwrap_err().kind(), io::ErrorKind::NotFound);
+}
+
+#[test]
+fn rename_missing_source() {
+    create("/rename_missing/b").unwrap().write(b"kept").unwrap();
+    let err = rename(Path::new("/rename_missing/a"), Path::new("/rename_missing/b")).unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::NotFound);
+    assert_eq!(read_to_vec("/rename_missing/b").unwrap(), b"kept");
+}
+
+#[test]
+fn non_utf8_file_names() {
+    let name = OsStr::from_bytes(b"\xfe\xff");
+    let path = Path::new("/non_utf8").join(name);
+    let mut opts = OpenOptions::new();
+    opts.write(true);
+    opts.create(true);
+    File::open(&path, &opts).unwrap().write(b"bytes").unwrap();
+
+    let entries: Vec<_> = readdir(Path::new("/non_utf8")).unwrap().map(|e| e.unwrap()).collect();
+    assert_eq!(entries.len(), 1);
+    assert_eq!(entries[0].file_name().as_bytes(), b"\xfe\xff");
+    assert_eq!(entries[0].path(), path);
+
+    // The same bytes reached through a different spelling name the same file.
+    let spelled = Path::new(OsStr::from_bytes(b"/non_utf8/./\xfe\xff"));
+    assert_eq!(stat(spelled).unwrap().size(), 5);
+}
+
+#[test]
+fn fs_write_then_read() {
+    crate::fs::write("/x", b"hi").unwrap();
+    assert_eq!(crate::fs::read("/x").unwrap(), b"hi");
+    assert_eq!(crate::fs::read_to_string("/x").unwrap(), "hi");
+
+    // `write` replaces the previous contents.
+    crate::fs::write("/x", b"!").unwrap();
+    assert_eq!(crate::fs::read("/x").unwrap(), b"!");
+}
+
+#[test]
+fn fs_read_to_string_rejects_invalid_utf8() {
+    crate::fs::write("/not-utf8", [0x66, 0x6f, 0xff]).unwrap();
+    let err = crate::fs::read_to_string("/not-utf8").unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    assert_eq!(crate::fs::read("/not-utf8").unwrap(), [0x66, 0x6f, 0xff]);
+}
+
+#[test]
+fn fs_read_missing_file() {
+    assert_eq!(crate::fs::read("/fs-read-missing").unwrap_err().kind(), io::ErrorKind::NotFound);
+}
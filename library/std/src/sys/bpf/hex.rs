@@ -0,0 +1,64 @@
+//! Hex encoding, for logging byte slices and for hex-encoded instruction
+//! data.
+//!
+//! Like `base58`, both directions work in a caller-provided buffer and never
+//! touch the heap.
+
+#[cfg(test)]
+mod tests;
+
+const DIGITS: &[u8; 16] = b"0123456789abcdef";
+
+/// Errors returned by `decode`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum HexError {
+    /// The input has an odd number of digits.
+    OddLength,
+    /// The input contains a character that is not a hex digit.
+    InvalidCharacter,
+    /// The output buffer is too small to hold the decoded bytes.
+    BufferTooSmall,
+}
+
+/// Encodes `bytes` as lowercase hex into `out`.
+///
+/// Returns the number of bytes written, or `Err` if `out` is too small to
+/// hold the encoding.
+pub fn encode(bytes: &[u8], out: &mut [u8]) -> Result<usize, ()> {
+    let len = bytes.len().checked_mul(2).ok_or(())?;
+    if len > out.len() {
+        return Err(());
+    }
+    for (&byte, pair) in bytes.iter().zip(out.chunks_exact_mut(2)) {
+        pair[0] = DIGITS[(byte >> 4) as usize];
+        pair[1] = DIGITS[(byte & 0xf) as usize];
+    }
+    Ok(len)
+}
+
+/// Decodes the hex string `input`, in either case, into `out`.
+///
+/// Returns the number of bytes written.
+pub fn decode(input: &str, out: &mut [u8]) -> Result<usize, HexError> {
+    let input = input.as_bytes();
+    if input.len() % 2 != 0 {
+        return Err(HexError::OddLength);
+    }
+    let len = input.len() / 2;
+    if len > out.len() {
+        return Err(HexError::BufferTooSmall);
+    }
+    for (pair, byte) in input.chunks_exact(2).zip(out.iter_mut()) {
+        *byte = (digit(pair[0])? << 4) | digit(pair[1])?;
+    }
+    Ok(len)
+}
+
+fn digit(c: u8) -> Result<u8, HexError> {
+    match c {
+        b'0'..=b'9' => Ok(c - b'0'),
+        b'a'..=b'f' => Ok(c - b'a' + 10),
+        b'A'..=b'F' => Ok(c - b'A' + 10),
+        _ => Err(HexError::InvalidCharacter),
+    }
+}
@@ -0,0 +1,42 @@
+use super::*;
+
+#[test]
+fn round_trip() {
+    let bytes = [0x00, 0x01, 0x7f, 0x80, 0xab, 0xff];
+    let mut hex = [0u8; 12];
+    assert_eq!(encode(&bytes, &mut hex), Ok(12));
+    assert_eq!(&hex, b"00017f80abff");
+
+    let mut decoded = [0u8; 6];
+    assert_eq!(decode(core::str::from_utf8(&hex).unwrap(), &mut decoded), Ok(6));
+    assert_eq!(decoded, bytes);
+
+    assert_eq!(encode(&[], &mut []), Ok(0));
+    assert_eq!(decode("", &mut []), Ok(0));
+}
+
+#[test]
+fn decode_accepts_both_cases() {
+    let mut out = [0u8; 4];
+    assert_eq!(decode("DEADbeef", &mut out), Ok(4));
+    assert_eq!(out, [0xde, 0xad, 0xbe, 0xef]);
+}
+
+#[test]
+fn decode_rejects_malformed_input() {
+    let mut out = [0u8; 4];
+    assert_eq!(decode("abc", &mut out), Err(HexError::OddLength));
+    assert_eq!(decode("0g", &mut out), Err(HexError::InvalidCharacter));
+    assert_eq!(decode("0x12", &mut out), Err(HexError::InvalidCharacter));
+}
+
+#[test]
+fn too_small_buffers() {
+    let mut out = [0u8; 3];
+    assert_eq!(encode(&[1, 2], &mut out), Err(()));
+    assert_eq!(decode("01020304", &mut out), Err(HexError::BufferTooSmall));
+
+    let mut out = [0u8; 4];
+    assert_eq!(encode(&[1, 2], &mut out), Ok(4));
+    assert_eq!(&out, b"0102");
+}
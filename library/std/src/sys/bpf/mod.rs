@@ -12,23 +12,41 @@
 //! remove all this and just omit parts of the standard library if we're
 //! compiling for BPF. That way it's a compile time error for something that's
 //! guaranteed to be a runtime error!
+//!
+//! `bpfel-unknown-unknown` keeps `atomic_cas`, so `Arc` and the rest of
+//! `alloc::sync` are built for it. The target also sets `singlethread`, which
+//! makes codegen lower every atomic operation to a plain load or store and
+//! `fence` to nothing; that is what keeps `Arc`'s reference counts working
+//! without a fallback in this module. Targets built on `bpf_base` turn
+//! `atomic_cas` off, and there `Arc` is not compiled at all.
 
+use crate::io;
 use crate::os::raw::c_char;
+use self::pubkey::Pubkey;
 
 pub mod alloc;
 pub mod args;
+pub mod array_vec;
+pub mod base58;
 //#[cfg(feature = "backtrace")]
 //pub mod backtrace;
 pub mod cmath;
+pub mod copy;
+#[cfg(feature = "bpf-curve-group-op")]
+pub mod ed25519;
+pub mod entrypoint;
 pub mod env;
 pub mod fs;
+pub mod hex;
 pub mod io;
 pub mod memchr;
 pub mod net;
 pub mod os;
+pub mod parse;
 pub mod path;
 pub mod pipe;
 pub mod process;
+pub mod pubkey;
 pub mod thread;
 pub mod time;
 pub mod stdio;
@@ -41,22 +59,618 @@
 
 pub use crate::sys_common::os_str_bytes as os_str;
 
+#[cfg(test)]
+mod tests;
+
 extern "C" {
     fn abort() -> !;
     #[allow(improper_ctypes)]
     fn custom_panic(info: &core::panic::PanicInfo<'_>);
     fn sol_log_(message: *const u8, length: u64);
+    fn sol_log_64_(arg1: u64, arg2: u64, arg3: u64, arg4: u64, arg5: u64);
+}
+
+#[cfg(feature = "bpf-remaining-compute-units")]
+extern "C" {
+    #[link_name = "sol_remaining_compute_units"]
+    fn sol_remaining_compute_units_() -> u64;
+}
+
+/// Returns the number of compute units the program has left.
+///
+/// Only available with the `bpf-remaining-compute-units` feature, as older
+/// runtimes do not provide the syscall and programs referencing it would
+/// fail to load there.
+#[cfg(feature = "bpf-remaining-compute-units")]
+pub fn sol_remaining_compute_units() -> u64 {
+    unsafe { sol_remaining_compute_units_() }
+}
+
+/// Logs `label` with the number of compute units left, as `label: N CU`.
+/// The difference between two checkpoints is what the code in between cost.
+#[cfg(feature = "bpf-remaining-compute-units")]
+pub fn log_cu_checkpoint(label: &str) {
+    log_cu_checkpoint_from(label, sol_remaining_compute_units);
+}
+
+// Split out so that tests can supply the remaining compute units.
+#[cfg(feature = "bpf-remaining-compute-units")]
+fn log_cu_checkpoint_from(label: &str, remaining_compute_units: impl FnOnce() -> u64) {
+    sol_log(&format!("{}: {} CU", label, remaining_compute_units()));
+}
+
+struct CuMarkers {
+    enabled: crate::cell::Cell<bool>,
+}
+
+unsafe impl Sync for CuMarkers {} // no threads on BPF
+
+static CU_MARKERS: CuMarkers = CuMarkers { enabled: crate::cell::Cell::new(false) };
+
+/// Makes the entrypoint log the compute units left when the program starts
+/// and when it returns, as `program entry: N CU` and `program exit: N CU`.
+/// Off by default, and only logged with the `bpf-remaining-compute-units`
+/// feature.
+pub fn set_cu_markers(enabled: bool) {
+    CU_MARKERS.enabled.set(enabled);
+}
+
+/// Logs a `set_cu_markers` marker named `label`, if they are enabled.
+pub fn log_cu_marker(label: &str) {
+    #[cfg(feature = "bpf-remaining-compute-units")]
+    log_cu_marker_from(label, sol_remaining_compute_units);
+    #[cfg(not(feature = "bpf-remaining-compute-units"))]
+    let _ = label;
+}
+
+// Split out so that tests can supply the remaining compute units.
+#[cfg(feature = "bpf-remaining-compute-units")]
+fn log_cu_marker_from(label: &str, remaining_compute_units: impl FnOnce() -> u64) {
+    if CU_MARKERS.enabled.get() {
+        log_cu_checkpoint_from(label, remaining_compute_units);
+    }
+}
+
+/// Runs `f` and logs `label` with the compute units it consumed, as
+/// `label: consumed N CU`, then returns its result. Without the
+/// `bpf-remaining-compute-units` feature, `f` is just run.
+pub fn profile_cu<F: FnOnce() -> R, R>(label: &str, f: F) -> R {
+    #[cfg(feature = "bpf-remaining-compute-units")]
+    return profile_cu_from(label, f, sol_remaining_compute_units);
+    #[cfg(not(feature = "bpf-remaining-compute-units"))]
+    {
+        let _ = label;
+        f()
+    }
+}
+
+// Split out so that tests can supply the remaining compute units.
+#[cfg(feature = "bpf-remaining-compute-units")]
+fn profile_cu_from<F: FnOnce() -> R, R>(
+    label: &str,
+    f: F,
+    mut remaining_compute_units: impl FnMut() -> u64,
+) -> R {
+    let before = remaining_compute_units();
+    let result = f();
+    let consumed = before.saturating_sub(remaining_compute_units());
+    sol_log(&format!("{}: consumed {} CU", label, consumed));
+    result
+}
+
+#[cfg(feature = "bpf-curve-group-op")]
+pub use self::ed25519::ed25519_verify;
+
+#[cfg(all(feature = "bpf-secp256k1-recover", test))]
+use self::tests::sol_secp256k1_recover;
+
+#[cfg(all(feature = "bpf-secp256k1-recover", not(test)))]
+extern "C" {
+    fn sol_secp256k1_recover(
+        hash: *const u8,
+        recovery_id: u64,
+        signature: *const u8,
+        result: *mut u8,
+    ) -> u64;
+}
+
+/// Recovers the uncompressed public key (without its `0x04` prefix) that
+/// made `signature` over `message_hash`.
+#[cfg(feature = "bpf-secp256k1-recover")]
+pub fn secp256k1_recover(
+    message_hash: &[u8; 32],
+    recovery_id: u8,
+    signature: &[u8; 64],
+) -> io::Result<[u8; 64]> {
+    let mut pubkey = [0u8; 64];
+    status_to_result(unsafe {
+        sol_secp256k1_recover(
+            message_hash.as_ptr(),
+            recovery_id as u64,
+            signature.as_ptr(),
+            pubkey.as_mut_ptr(),
+        )
+    })?;
+    Ok(pubkey)
+}
+
+/// Returns whether `signature` over `message_hash` was made by `pubkey`.
+///
+/// The runtime has no verify syscall, so this recovers the signer's key with
+/// each of the four recovery ids in turn and compares it with `pubkey`. That
+/// can cost up to four recoveries; callers that know the recovery id should
+/// use `secp256k1_recover` directly.
+#[cfg(feature = "bpf-secp256k1-recover")]
+pub fn secp256k1_verify(
+    message_hash: &[u8; 32],
+    signature: &[u8; 64],
+    pubkey: &[u8; 64],
+) -> bool {
+    (0..4).any(|recovery_id| {
+        secp256k1_recover(message_hash, recovery_id, signature).map_or(false, |key| key == *pubkey)
+    })
 }
 
 pub fn sol_log(message: &str) {
+    if !LOG_BUDGET.take() {
+        return;
+    }
+    write_log(message);
+}
+
+fn write_log(message: &str) {
+    #[cfg(test)]
+    if let Some(sink) = LOG_SINK.sink.get() {
+        sink(message);
+        return;
+    }
     unsafe {
         sol_log_(message.as_ptr(), message.len() as u64);
     }
 }
 
+struct LogBudget {
+    remaining: crate::cell::Cell<Option<u32>>,
+    exhausted: crate::cell::Cell<bool>,
+}
+
+unsafe impl Sync for LogBudget {} // no threads on BPF
+
+static LOG_BUDGET: LogBudget = LogBudget {
+    remaining: crate::cell::Cell::new(None),
+    exhausted: crate::cell::Cell::new(false),
+};
+
+impl LogBudget {
+    // Returns whether one more log fits in the budget, and writes the
+    // "log budget exhausted" marker the first time one does not.
+    fn take(&self) -> bool {
+        match self.remaining.get() {
+            None => true,
+            Some(0) => {
+                if !self.exhausted.replace(true) {
+                    write_log("log budget exhausted");
+                }
+                false
+            }
+            Some(n) => {
+                self.remaining.set(Some(n - 1));
+                true
+            }
+        }
+    }
+}
+
+/// Lets only the next `max_calls` calls to `sol_log` through, so that a
+/// program logging in a loop cannot spend its whole compute budget on
+/// logging. Later logs are dropped, after a single "log budget exhausted"
+/// line.
+pub fn set_log_budget(max_calls: u32) {
+    LOG_BUDGET.remaining.set(Some(max_calls));
+    LOG_BUDGET.exhausted.set(false);
+}
+
+/// Removes the limit set by `set_log_budget`.
+pub fn reset_log_budget() {
+    LOG_BUDGET.remaining.set(None);
+    LOG_BUDGET.exhausted.set(false);
+}
+
+#[cfg(test)]
+struct LogSink {
+    sink: crate::cell::Cell<Option<fn(&str)>>,
+}
+
+#[cfg(test)]
+unsafe impl Sync for LogSink {} // no threads on BPF
+
+#[cfg(test)]
+static LOG_SINK: LogSink = LogSink { sink: crate::cell::Cell::new(None) };
+
+/// Routes `sol_log` to `sink` instead of the syscall, so that host tests can
+/// see what would be logged. Does nothing outside of tests.
+#[cfg(test)]
+pub fn set_log_sink(sink: Option<fn(&str)>) {
+    LOG_SINK.sink.set(sink);
+}
+
+#[cfg(not(test))]
+pub fn set_log_sink(_sink: Option<fn(&str)>) {}
+
+/// Logs five integers as hex in a single log line.
+pub fn sol_log_64(arg1: u64, arg2: u64, arg3: u64, arg4: u64, arg5: u64) {
+    unsafe {
+        sol_log_64_(arg1, arg2, arg3, arg4, arg5);
+    }
+}
+
+/// Logs `pairs` as a single `k1=v1 k2=v2` line. Values that contain a
+/// space, `=` or `"`, or that are empty, are written in double quotes with
+/// `"` and `\` escaped by a backslash. No pairs log an empty line.
+pub fn sol_log_kv(pairs: &[(&str, &str)]) {
+    sol_log(&format_kv(pairs));
+}
+
+fn format_kv(pairs: &[(&str, &str)]) -> String {
+    let mut line = String::new();
+    for (i, (key, value)) in pairs.iter().enumerate() {
+        if i > 0 {
+            line.push(' ');
+        }
+        line.push_str(key);
+        line.push('=');
+        if value.is_empty() || value.contains(&[' ', '=', '"'][..]) {
+            line.push('"');
+            for c in value.chars() {
+                if c == '"' || c == '\\' {
+                    line.push('\\');
+                }
+                line.push(c);
+            }
+            line.push('"');
+        } else {
+            line.push_str(value);
+        }
+    }
+    line
+}
+
+/// Returns `a + b`, or `None` if it overflows.
+pub fn checked_add_u64(a: u64, b: u64) -> Option<u64> {
+    a.checked_add(b)
+}
+
+/// Returns `a - b`, or `None` if it underflows.
+pub fn checked_sub_u64(a: u64, b: u64) -> Option<u64> {
+    a.checked_sub(b)
+}
+
+/// Returns `a + b`. On overflow, logs both operands with `sol_log_64` and
+/// aborts the program.
+pub fn logged_add_u64(a: u64, b: u64) -> u64 {
+    checked_add_u64(a, b).unwrap_or_else(|| {
+        report_overflow("addition", a, b, sol_log_64);
+        abort_internal()
+    })
+}
+
+/// Returns `a - b`. On underflow, logs both operands with `sol_log_64` and
+/// aborts the program.
+pub fn logged_sub_u64(a: u64, b: u64) -> u64 {
+    checked_sub_u64(a, b).unwrap_or_else(|| {
+        report_overflow("subtraction", a, b, sol_log_64);
+        abort_internal()
+    })
+}
+
+// Split out so that tests can see the operands without aborting.
+fn report_overflow(
+    operation: &str,
+    a: u64,
+    b: u64,
+    log_64: impl FnOnce(u64, u64, u64, u64, u64),
+) {
+    log_64(a, b, 0, 0, 0);
+    sol_log(&format!("arithmetic overflow in u64 {}", operation));
+}
+
+/// Fills `out` with the `(pointer, length)` descriptors of `slices`, as
+/// taken by syscalls that work on several byte slices at once (hashing,
+/// `sol_log_data`, program address seeds). Entries of `out` past the last
+/// slice are left as they are.
+///
+/// Panics if `out` is shorter than `slices`.
+pub fn build_slice_descriptors<'a>(slices: &[&'a [u8]], out: &mut [(*const u8, u64)]) {
+    assert!(out.len() >= slices.len(), "too many slices for the descriptor buffer");
+    for (descriptor, slice) in out.iter_mut().zip(slices) {
+        *descriptor = (slice.as_ptr(), slice.len() as u64);
+    }
+}
+
+/// Checks that an account's data is `expected` bytes long. On a mismatch,
+/// logs both lengths with `sol_log_64` and returns an `InvalidData` error,
+/// which callers can map to a program error.
+pub fn expect_data_len(actual: usize, expected: usize) -> io::Result<()> {
+    check_data_len(actual, expected, sol_log_64)
+}
+
+// Split out so that tests can see the logged lengths.
+fn check_data_len(
+    actual: usize,
+    expected: usize,
+    log_64: impl FnOnce(u64, u64, u64, u64, u64),
+) -> io::Result<()> {
+    if actual == expected {
+        return Ok(());
+    }
+    log_64(actual as u64, expected as u64, 0, 0, 0);
+    Err(io::Error::new(
+        io::ErrorKind::InvalidData,
+        format!("account data is {} bytes long, expected {}", actual, expected),
+    ))
+}
+
+/// Longest message the runtime logs in one piece.
+pub const MAX_LOG_MESSAGE_LEN: usize = 10 * 1024;
+
+/// Logs `message`, split over as many log lines as needed to stay within
+/// `MAX_LOG_MESSAGE_LEN`.
+pub fn sol_log_chunked(message: &str) {
+    for chunk in log_chunks(message, MAX_LOG_MESSAGE_LEN) {
+        sol_log(chunk);
+    }
+}
+
+// Splits `message` into pieces of at most `limit` bytes, without splitting
+// any character.
+fn log_chunks(mut message: &str, limit: usize) -> impl Iterator<Item = &str> {
+    crate::iter::from_fn(move || {
+        if message.is_empty() {
+            return None;
+        }
+        let mut end = limit.min(message.len());
+        while !message.is_char_boundary(end) {
+            end -= 1;
+        }
+        // A limit smaller than a character still has to make progress.
+        if end == 0 {
+            end = message.chars().next().map_or(0, char::len_utf8);
+        }
+        let (chunk, rest) = message.split_at(end);
+        message = rest;
+        Some(chunk)
+    })
+}
+
+struct ProgramId {
+    id: crate::cell::Cell<Option<Pubkey>>,
+}
+
+unsafe impl Sync for ProgramId {} // no threads on BPF
+
+static PROGRAM_ID: ProgramId = ProgramId { id: crate::cell::Cell::new(None) };
+
+/// Records the id of the running program. Called by the entrypoint before
+/// any program code runs.
+pub fn set_program_id(id: &Pubkey) {
+    PROGRAM_ID.id.set(Some(*id));
+}
+
+/// Returns the id of the running program, or `None` if the entrypoint has
+/// not set it.
+pub fn program_id() -> Option<Pubkey> {
+    PROGRAM_ID.id.get()
+}
+
+/// What `panic` does once the panic has been reported.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PanicMode {
+    /// Abort the program. This is the default, and what production
+    /// programs should use. The runtime cannot end a program with a code
+    /// from inside a call, so a code the panic carries is dropped.
+    Abort,
+    /// Pass `PANIC_RETURN_CODE` to the given function instead of aborting.
+    /// There is no unwinding on BPF, so this is how a harness running
+    /// several tests in one program regains control after a panicking test.
+    /// Later panics are reported again.
+    ReturnCode(fn(u64) -> !),
+}
+
+/// Sentinel passed on by `panic` in `PanicMode::ReturnCode`.
+pub const PANIC_RETURN_CODE: u64 = 0xdead_0001;
+
+struct PanicState {
+    panicking: crate::cell::Cell<bool>,
+    mode: crate::cell::Cell<PanicMode>,
+}
+
+unsafe impl Sync for PanicState {} // no threads on BPF
+
+static PANIC_STATE: PanicState = PanicState {
+    panicking: crate::cell::Cell::new(false),
+    mode: crate::cell::Cell::new(PanicMode::Abort),
+};
+
+/// Sets what `panic` does after reporting a panic.
+pub fn set_panic_mode(mode: PanicMode) {
+    PANIC_STATE.mode.set(mode);
+}
+
+// Split out so that tests can panic without aborting. `code` is what a
+// `PanicMode::ReturnCode` resume function gets.
+fn end_panic(code: u64, abort: impl FnOnce() -> !) -> ! {
+    match PANIC_STATE.mode.get() {
+        PanicMode::Abort => abort(),
+        PanicMode::ReturnCode(resume) => {
+            sol_log("panic: returning to the harness");
+            PANIC_STATE.panicking.set(false);
+            resume(code)
+        }
+    }
+}
+
+/// Prefix of the panic messages that carry a program error code. A panic
+/// whose entire message is this prefix followed by a `u64` in decimal, as
+/// raised by `panic_with_error_code`, passes that code on instead of
+/// `PANIC_RETURN_CODE` in `PanicMode::ReturnCode`. With `PanicMode::Abort`
+/// the program still aborts, as the runtime has no way to end it with a
+/// code from inside a call.
+pub const PROGRAM_ERROR_PREFIX: &str = "program error: ";
+
+/// Panics with a message carrying `code`, see `PROGRAM_ERROR_PREFIX`.
+#[track_caller]
+pub fn panic_with_error_code(code: u64) -> ! {
+    panic!("{}{}", PROGRAM_ERROR_PREFIX, code)
+}
+
+// Holds the start of a formatted panic message, just long enough for one
+// that carries an error code: the prefix and the 20 digits of `u64::MAX`.
+struct ErrorCodeMessage {
+    buf: [u8; PROGRAM_ERROR_PREFIX.len() + 20],
+    len: usize,
+}
+
+impl crate::fmt::Write for ErrorCodeMessage {
+    fn write_str(&mut self, s: &str) -> crate::fmt::Result {
+        let end = self.len + s.len();
+        if end > self.buf.len() {
+            return Err(crate::fmt::Error);
+        }
+        self.buf[self.len..end].copy_from_slice(s.as_bytes());
+        self.len = end;
+        Ok(())
+    }
+}
+
+/// Returns the code carried by a panic message, see `PROGRAM_ERROR_PREFIX`.
+/// Longer messages are given up on without allocating.
+fn program_error_code(msg: &crate::fmt::Arguments<'_>) -> Option<u64> {
+    let mut message = ErrorCodeMessage { buf: [0; PROGRAM_ERROR_PREFIX.len() + 20], len: 0 };
+    crate::fmt::write(&mut message, *msg).ok()?;
+    let digits = core::str::from_utf8(&message.buf[..message.len])
+        .ok()?
+        .strip_prefix(PROGRAM_ERROR_PREFIX)?;
+    if digits.is_empty() || !digits.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    digits.parse().ok()
+}
+
+/// Runs `report` for the first panic and returns the code it finds for
+/// `end_panic`. A panic raised while reporting (e.g. an allocation failure
+/// while formatting the message) only passes a fixed message to `log` and
+/// returns `PANIC_RETURN_CODE`, so that it cannot recurse.
+fn report_panic(report: impl FnOnce() -> u64, log: impl FnOnce(&str)) -> u64 {
+    if PANIC_STATE.panicking.replace(true) {
+        log("panic during panic");
+        PANIC_RETURN_CODE
+    } else {
+        report()
+    }
+}
+
+/// Longest panic message handed to `custom_panic`. The runtime drops log
+/// messages that are too long entirely, so longer ones are cut down to this
+/// many bytes followed by `...`.
+pub const MAX_PANIC_MESSAGE_LEN: usize = MAX_LOG_MESSAGE_LEN;
+
+struct BoundedMessage {
+    buf: String,
+    limit: usize,
+    truncated: bool,
+}
+
+impl crate::fmt::Write for BoundedMessage {
+    fn write_str(&mut self, s: &str) -> crate::fmt::Result {
+        let room = self.limit - self.buf.len();
+        if s.len() <= room {
+            self.buf.push_str(s);
+            return Ok(());
+        }
+        let mut end = room;
+        while !s.is_char_boundary(end) {
+            end -= 1;
+        }
+        self.buf.push_str(&s[..end]);
+        self.truncated = true;
+        // Stop formatting, the rest would be thrown away.
+        Err(crate::fmt::Error)
+    }
+}
+
+// Counts the bytes a message formats to without storing any of them, and
+// stops formatting once there are more than `limit`.
+struct MessageLen {
+    len: usize,
+    limit: usize,
+}
+
+impl crate::fmt::Write for MessageLen {
+    fn write_str(&mut self, s: &str) -> crate::fmt::Result {
+        self.len += s.len();
+        if self.len > self.limit { Err(crate::fmt::Error) } else { Ok(()) }
+    }
+}
+
+/// Formats `msg` and returns it cut to `limit` bytes plus `...`, or `None`
+/// if it already fits. A message that fits is only measured, not stored.
+fn truncate_message(msg: &crate::fmt::Arguments<'_>, limit: usize) -> Option<String> {
+    if crate::fmt::write(&mut MessageLen { len: 0, limit }, *msg).is_ok() {
+        return None;
+    }
+    let buf = String::with_capacity(limit + "...".len());
+    let mut bounded = BoundedMessage { buf, limit, truncated: false };
+    let _ = crate::fmt::write(&mut bounded, *msg);
+    if bounded.truncated {
+        bounded.buf.push_str("...");
+        Some(bounded.buf)
+    } else {
+        None
+    }
+}
+
+// Files of std's stubs for what BPF does not provide. Their panics say
+// nothing beyond where they were raised, so the payload is not worth
+// formatting and logging in full.
+const STUB_PANIC_DIR: &str = "std/src/sys/bpf";
+const STUB_PANIC_FILES: &[&str] =
+    &["args.rs", "condvar.rs", "os.rs", "process.rs", "thread.rs", "time.rs"];
+
+/// Returns the terse message logged for a panic raised at `file:line` by
+/// one of std's stubs, as `unsupported in std stub os.rs:32`. Panics from
+/// anywhere else are reported in full.
+fn stub_panic_message(file: &str, line: u32) -> Option<String> {
+    let (dir, name) = file.rsplit_once('/')?;
+    if dir.ends_with(STUB_PANIC_DIR) && STUB_PANIC_FILES.contains(&name) {
+        Some(format!("unsupported in std stub {}:{}", name, line))
+    } else {
+        None
+    }
+}
+
 pub fn panic(info: &core::panic::PanicInfo<'_>) -> ! {
-    unsafe { custom_panic(info); }
-    unsafe { abort(); }
+    let code = report_panic(|| {
+        // Finding the code formats the payload, so it is only done here.
+        let code = info.message().and_then(program_error_code).unwrap_or(PANIC_RETURN_CODE);
+        let stub = info.location().and_then(|l| stub_panic_message(l.file(), l.line()));
+        if let Some(msg) = stub {
+            sol_log(&msg);
+            return code;
+        }
+        let truncated = info.message().and_then(|msg| truncate_message(msg, MAX_PANIC_MESSAGE_LEN));
+        match (truncated, info.location()) {
+            (Some(msg), Some(location)) => unsafe {
+                // `format_args!` temporaries only live until the end of the statement.
+                custom_panic(&core::panic::PanicInfo::internal_constructor(
+                    Some(&format_args!("{}", msg)),
+                    location,
+                ))
+            },
+            _ => unsafe { custom_panic(info) },
+        }
+        code
+    }, sol_log);
+    end_panic(code, abort_internal)
 }
 
 pub fn unsupported<T>() -> crate::io::Result<T> {
@@ -64,14 +678,72 @@ pub fn unsupported<T>() -> crate::io::Result<T> {
 }
 
 pub fn unsupported_err() -> crate::io::Error {
-    crate::io::Error::new(crate::io::ErrorKind::Other,
-                   "operation not supported on BPF yet")
+    crate::io::Error::new(crate::io::ErrorKind::Other, Unsupported)
+}
+
+// The payload of `unsupported_err`, which `is_unsupported` looks for since the
+// error kind is the catch-all `Other`.
+#[derive(Debug)]
+struct Unsupported;
+
+impl crate::fmt::Display for Unsupported {
+    fn fmt(&self, f: &mut crate::fmt::Formatter<'_>) -> crate::fmt::Result {
+        f.write_str("operation not supported on BPF yet")
+    }
 }
 
+impl crate::error::Error for Unsupported {}
+
+/// Returns whether `e` was created by `unsupported_err`.
+pub fn is_unsupported(e: &crate::io::Error) -> bool {
+    e.get_ref().map_or(false, |inner| inner.is::<Unsupported>())
+}
+
+/// Error code reported when a program runs out of compute units, matching
+/// the runtime's `InstructionError::ComputationalBudgetExceeded`.
+pub const COMPUTE_BUDGET_EXCEEDED: i32 = 37;
+
 pub fn decode_error_kind(_code: i32) -> crate::io::ErrorKind {
+    // This includes `COMPUTE_BUDGET_EXCEEDED`, which has no dedicated kind;
+    // `is_compute_budget_exceeded` detects it instead.
     crate::io::ErrorKind::Other
 }
 
+/// Describes an error code reported by the runtime. The codes follow the
+/// runtime's `InstructionError` variants.
+pub fn error_description(code: i32) -> Option<&'static str> {
+    Some(match code {
+        0 => "generic instruction error",
+        1 => "invalid program argument",
+        2 => "invalid instruction data",
+        3 => "invalid account data for instruction",
+        4 => "account data too small for instruction",
+        5 => "insufficient funds for instruction",
+        6 => "incorrect program id for instruction",
+        7 => "missing required signature for instruction",
+        COMPUTE_BUDGET_EXCEEDED => "computational budget exceeded",
+        _ => return None,
+    })
+}
+
+/// Converts the status returned by a syscall into a result: 0 is success,
+/// anything else becomes an error carrying the status as its raw OS error
+/// code.
+pub fn status_to_result(status: u64) -> io::Result<()> {
+    match status {
+        0 => Ok(()),
+        code if code <= i32::MAX as u64 => Err(io::Error::from_raw_os_error(code as i32)),
+        code => {
+            Err(io::Error::new(io::ErrorKind::Other, format!("syscall failed with status {}", code)))
+        }
+    }
+}
+
+/// Returns whether `e` was created from `COMPUTE_BUDGET_EXCEEDED`.
+pub fn is_compute_budget_exceeded(e: &io::Error) -> bool {
+    e.raw_os_error() == Some(COMPUTE_BUDGET_EXCEEDED)
+}
+
 // This enum is used as the storage for a bunch of types which can't actually
 // exist.
 #[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Debug, Hash)]
@@ -90,11 +762,123 @@ pub fn abort_internal() -> ! {
     unsafe { abort() }
 }
 
+/// Call depth the runtime allows before the program traps.
+pub const DEFAULT_MAX_FRAME_DEPTH: usize = 64;
+
+struct FrameDepth {
+    depth: crate::cell::Cell<usize>,
+    limit: crate::cell::Cell<usize>,
+}
+
+unsafe impl Sync for FrameDepth {} // no threads on BPF
+
+static FRAME_DEPTH: FrameDepth = FrameDepth {
+    depth: crate::cell::Cell::new(0),
+    limit: crate::cell::Cell::new(DEFAULT_MAX_FRAME_DEPTH),
+};
+
+/// Sets how many nested `enter_frame` calls are allowed.
+pub fn set_max_frame_depth(limit: usize) {
+    FRAME_DEPTH.limit.set(limit);
+}
+
+/// Records entering a recursive call, and aborts with "stack depth exceeded"
+/// if that nests deeper than the limit. The runtime would otherwise trap
+/// without saying why. Every call must be paired with `leave_frame`.
+pub fn enter_frame() {
+    enter_frame_or(abort_internal)
+}
+
+// Split out so that tests can exceed the limit without aborting.
+fn enter_frame_or(abort: impl FnOnce() -> !) {
+    let depth = FRAME_DEPTH.depth.get() + 1;
+    if depth > FRAME_DEPTH.limit.get() {
+        sol_log("stack depth exceeded");
+        abort();
+    }
+    FRAME_DEPTH.depth.set(depth);
+}
+
+/// Records leaving a call recorded with `enter_frame`.
+pub fn leave_frame() {
+    FRAME_DEPTH.depth.set(FRAME_DEPTH.depth.get().saturating_sub(1));
+}
+
+struct DeterministicHashing {
+    // `None` until `set_deterministic_hashing` is called.
+    enabled: crate::cell::Cell<Option<bool>>,
+}
+
+unsafe impl Sync for DeterministicHashing {} // no threads on BPF
+
+static DETERMINISTIC_HASHING: DeterministicHashing =
+    DeterministicHashing { enabled: crate::cell::Cell::new(None) };
+
+/// With `true`, makes `hashmap_random_keys` return the fixed `(0, 0)` seed, so
+/// that the iteration order of hash maps never depends on the Clock sysvar.
+/// With `false`, seeds the keys from the Clock sysvar instead. Until this is
+/// called, the keys are the fixed `(1, 2)`.
+pub fn set_deterministic_hashing(enabled: bool) {
+    DETERMINISTIC_HASHING.enabled.set(Some(enabled));
+}
+
 // We don't have randomness yet, but I totally used a random number generator to
 // generate these numbers.
 //
-// More seriously though this is just for DOS protection in hash maps. It's ok
-// if we don't do that on BPF just yet.
+// More seriously though this is just for DOS protection in hash maps. A program
+// that wants some can seed the keys from the Clock sysvar with
+// `set_deterministic_hashing(false)`. Everyone can read it, so this is weak
+// protection at best, but it at least varies between slots.
 pub fn hashmap_random_keys() -> (u64, u64) {
-    (1, 2)
+    match DETERMINISTIC_HASHING.enabled.get() {
+        None => (1, 2),
+        Some(true) => (0, 0),
+        Some(false) => match time::clock() {
+            Ok(clock) => {
+                let mut state = clock.slot ^ (clock.unix_timestamp as u64).rotate_left(32);
+                (splitmix64(&mut state), splitmix64(&mut state))
+            }
+            Err(_) => (1, 2),
+        },
+    }
+}
+
+/// Derives hash map keys from `seed` alone, the same way for every call, as
+/// opposed to `hashmap_random_keys`.
+pub fn fixed_random_keys(seed: u64) -> (u64, u64) {
+    let mut state = seed;
+    (splitmix64(&mut state), splitmix64(&mut state))
+}
+
+/// Returns a hasher builder keyed by `fixed_random_keys(seed)`, for a map
+/// that has to iterate in the same order on every run without making all
+/// maps deterministic with `set_deterministic_hashing`:
+/// `HashMap::with_hasher(seeded_random_state(seed))`.
+pub fn seeded_random_state(seed: u64) -> crate::collections::hash_map::RandomState {
+    crate::collections::hash_map::RandomState::with_keys(fixed_random_keys(seed))
+}
+
+// Advances a splitmix64 generator and returns its next output.
+fn splitmix64(state: &mut u64) -> u64 {
+    *state = state.wrapping_add(0x9e3779b97f4a7c15);
+    let mut z = *state;
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+    z ^ (z >> 31)
+}
+
+/// Fills `buf` with bytes from a splitmix64 stream seeded by `seed`.
+///
+/// **This is not a source of randomness.** The output is fully determined by
+/// `seed`, and any seed a program can see (such as the Clock slot) is also
+/// visible to everyone else, so the bytes are predictable to an attacker. It
+/// only exists so that crates which insist on `getrandom`-style entropy can
+/// run on BPF instead of panicking; never use it for keys, nonces or anything
+/// else security relevant.
+#[cfg(feature = "bpf-deterministic-random")]
+pub fn fill_bytes_deterministic(buf: &mut [u8], seed: u64) {
+    let mut state = seed;
+    for chunk in buf.chunks_mut(8) {
+        chunk.copy_from_slice(&splitmix64(&mut state).to_le_bytes()[..chunk.len()]);
+    }
 }
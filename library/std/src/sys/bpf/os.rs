@@ -7,12 +7,15 @@
 use crate::str;
 use crate::sys::{unsupported, Void};
 
+#[cfg(test)]
+mod tests;
+
 pub fn errno() -> i32 {
     0
 }
 
-pub fn error_string(_errno: i32) -> String {
-    "operation successful".to_string()
+pub fn error_string(errno: i32) -> String {
+    crate::sys::error_description(errno).unwrap_or("unknown error").to_string()
 }
 
 pub fn getcwd() -> io::Result<PathBuf> {
@@ -61,29 +64,36 @@ pub fn current_exe() -> io::Result<PathBuf> {
     unsupported()
 }
 
-pub struct Env(Void);
+pub struct Env {
+    iter: crate::vec::IntoIter<(OsString, OsString)>,
+}
 
 impl Iterator for Env {
     type Item = (OsString, OsString);
     fn next(&mut self) -> Option<(OsString, OsString)> {
-        match self.0 {}
+        self.iter.next()
+    }
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.iter.size_hint()
     }
 }
 
 pub fn env() -> Env {
-    panic!();
+    Env { iter: crate::sys::env::vars().into_iter() }
 }
 
-pub fn getenv(_k: &OsStr) -> io::Result<Option<OsString>> {
-    unsupported()
+pub fn getenv(k: &OsStr) -> io::Result<Option<OsString>> {
+    Ok(crate::sys::env::get(k))
 }
 
-pub fn setenv(_k: &OsStr, _v: &OsStr) -> io::Result<()> {
-    unsupported()
+pub fn setenv(k: &OsStr, v: &OsStr) -> io::Result<()> {
+    crate::sys::env::set(k, v);
+    Ok(())
 }
 
-pub fn unsetenv(_k: &OsStr) -> io::Result<()> {
-    unsupported()
+pub fn unsetenv(k: &OsStr) -> io::Result<()> {
+    crate::sys::env::remove(k);
+    Ok(())
 }
 
 pub fn temp_dir() -> PathBuf {
@@ -94,10 +104,63 @@ pub fn home_dir() -> Option<PathBuf> {
     None
 }
 
-pub fn exit(_code: i32) -> ! {
+/// A program can only return a status from its entrypoint; leaving from
+/// anywhere else always aborts, which the runtime reports as a failure. The
+/// requested code is logged so that it still shows up in the program log.
+pub fn exit(code: i32) -> ! {
+    crate::sys::sol_log(&format!("exit with code {}", code));
     intrinsics::abort()
 }
 
 pub fn getpid() -> u32 {
     0
 }
+
+#[cfg(all(feature = "bpf-get-sysvar", not(test)))]
+extern "C" {
+    fn sol_get_sysvar(sysvar_id: *const u8, result: *mut u8, offset: u64, length: u64) -> u64;
+}
+
+/// Address of the Clock sysvar, `SysvarC1ock11111111111111111111111111111111`.
+#[cfg(feature = "bpf-get-sysvar")]
+const CLOCK_SYSVAR_ID: [u8; 32] = [
+    0x06, 0xa7, 0xd5, 0x17, 0x18, 0xc7, 0x74, 0xc9, 0x28, 0x56, 0x63, 0x98, 0x69, 0x1d, 0x5e, 0xb6,
+    0x8b, 0x5e, 0xb8, 0xa3, 0x9b, 0x4b, 0x6d, 0x5c, 0x73, 0x55, 0x5b, 0x21, 0x00, 0x00, 0x00, 0x00,
+];
+
+/// Offset of `slot` in the Clock sysvar, see `time::Clock`.
+#[cfg(feature = "bpf-get-sysvar")]
+const CLOCK_SLOT_OFFSET: u64 = 0;
+
+/// Returns the current slot. Unlike `time::clock`, this only copies the
+/// slot out of the Clock sysvar instead of the whole struct.
+#[cfg(feature = "bpf-get-sysvar")]
+pub fn current_slot() -> io::Result<u64> {
+    let mut slot = [0u8; 8];
+    crate::sys::status_to_result(unsafe {
+        sol_get_sysvar(CLOCK_SYSVAR_ID.as_ptr(), slot.as_mut_ptr(), CLOCK_SLOT_OFFSET, 8)
+    })?;
+    Ok(u64::from_le_bytes(slot))
+}
+
+// Serves the Clock sysvar from `time::MOCK_CLOCK`, and fails for any other
+// sysvar or a range outside of it, like the runtime does.
+#[cfg(all(feature = "bpf-get-sysvar", test))]
+unsafe fn sol_get_sysvar(sysvar_id: *const u8, result: *mut u8, offset: u64, length: u64) -> u64 {
+    use crate::sys::time::{Clock, MOCK_CLOCK};
+
+    if crate::slice::from_raw_parts(sysvar_id, 32) != CLOCK_SYSVAR_ID {
+        return 1;
+    }
+    let clock = crate::slice::from_raw_parts(
+        crate::ptr::addr_of!(MOCK_CLOCK) as *const u8,
+        crate::mem::size_of::<Clock>(),
+    );
+    match clock.get(offset as usize..).and_then(|rest| rest.get(..length as usize)) {
+        Some(bytes) => {
+            crate::ptr::copy_nonoverlapping(bytes.as_ptr(), result, bytes.len());
+            0
+        }
+        None => 1,
+    }
+}
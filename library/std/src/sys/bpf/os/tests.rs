@@ -0,0 +1,11 @@
+use super::*;
+
+#[test]
+#[cfg(feature = "bpf-get-sysvar")]
+fn current_slot_reads_the_clock_sysvar() {
+    unsafe {
+        crate::sys::time::MOCK_CLOCK.slot = 123_456_789;
+        crate::sys::time::MOCK_CLOCK.unix_timestamp = 1_700_000_000;
+    }
+    assert_eq!(current_slot().unwrap(), 123_456_789);
+}
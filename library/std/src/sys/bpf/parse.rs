@@ -0,0 +1,36 @@
+//! Readers for fixed-size integers in instruction and account data.
+//!
+//! Instruction data has no alignment guarantees, so values are copied out
+//! into a byte array before being converted instead of being read in place.
+//! All readers return `None` if the value does not fit in `data`.
+
+#[cfg(test)]
+mod tests;
+
+// Copies the `N` bytes at `offset`, if they are in bounds.
+fn read_array<const N: usize>(data: &[u8], offset: usize) -> Option<[u8; N]> {
+    let end = offset.checked_add(N)?;
+    let mut bytes = [0u8; N];
+    bytes.copy_from_slice(data.get(offset..end)?);
+    Some(bytes)
+}
+
+pub fn read_u16_le(data: &[u8], offset: usize) -> Option<u16> {
+    read_array(data, offset).map(u16::from_le_bytes)
+}
+
+pub fn read_u32_le(data: &[u8], offset: usize) -> Option<u32> {
+    read_array(data, offset).map(u32::from_le_bytes)
+}
+
+pub fn read_u64_le(data: &[u8], offset: usize) -> Option<u64> {
+    read_array(data, offset).map(u64::from_le_bytes)
+}
+
+pub fn read_u128_le(data: &[u8], offset: usize) -> Option<u128> {
+    read_array(data, offset).map(u128::from_le_bytes)
+}
+
+pub fn read_i64_le(data: &[u8], offset: usize) -> Option<i64> {
+    read_array(data, offset).map(i64::from_le_bytes)
+}
@@ -0,0 +1,63 @@
+use super::*;
+
+const DATA: [u8; 10] = [0x01, 0x02, 0x03, 0x04, 0x05, 0x06, 0x07, 0x08, 0x09, 0x0a];
+
+#[test]
+fn in_bounds_reads() {
+    assert_eq!(read_u16_le(&DATA, 0), Some(0x0201));
+    assert_eq!(read_u32_le(&DATA, 1), Some(0x05040302));
+    // Offset 1 is not aligned for a u64.
+    assert_eq!(read_u64_le(&DATA, 1), Some(0x0908070605040302));
+}
+
+#[test]
+fn reads_ending_at_the_end_of_data() {
+    assert_eq!(read_u16_le(&DATA, 8), Some(0x0a09));
+    assert_eq!(read_u32_le(&DATA, 6), Some(0x0a090807));
+    assert_eq!(read_u64_le(&DATA, 2), Some(0x0a09080706050403));
+}
+
+#[test]
+fn out_of_bounds_reads_return_none() {
+    assert_eq!(read_u16_le(&DATA, 9), None);
+    assert_eq!(read_u32_le(&DATA, 7), None);
+    assert_eq!(read_u64_le(&DATA, 3), None);
+    assert_eq!(read_u64_le(&DATA, 100), None);
+    assert_eq!(read_u64_le(&DATA, usize::MAX), None);
+    assert_eq!(read_u16_le(&[], 0), None);
+}
+
+
+// A token amount too large for a u64 at an unaligned offset, followed by -2
+// as an i64 at the end.
+fn wide_data() -> [u8; 25] {
+    let mut data = [0u8; 25];
+    data[1..17].copy_from_slice(&(u64::MAX as u128 + 1).to_le_bytes());
+    data[17..25].copy_from_slice(&(-2i64).to_le_bytes());
+    data
+}
+
+#[test]
+fn u128_and_i64_reads() {
+    let data = wide_data();
+    assert_eq!(read_u128_le(&data, 1), Some(1 << 64));
+    assert_eq!(read_i64_le(&data, 17), Some(-2));
+    // Just the upper half of the u128.
+    assert_eq!(read_i64_le(&data, 9), Some(1));
+}
+
+#[test]
+fn u128_and_i64_reads_ending_at_the_end_of_data() {
+    let data = wide_data();
+    assert_eq!(read_u128_le(&data, 9), Some(1 | (-2i64 as u64 as u128) << 64));
+    assert_eq!(read_i64_le(&data, 17), Some(-2));
+}
+
+#[test]
+fn out_of_bounds_u128_and_i64_reads_return_none() {
+    let data = wide_data();
+    assert_eq!(read_u128_le(&data, 10), None);
+    assert_eq!(read_i64_le(&data, 18), None);
+    assert_eq!(read_u128_le(&data, usize::MAX), None);
+    assert_eq!(read_u128_le(&DATA, 0), None);
+}
@@ -1,6 +1,9 @@
-use crate::path::Prefix;
+use crate::path::{Component, Path, PathBuf, Prefix};
 use crate::ffi::OsStr;
 
+#[cfg(test)]
+mod tests;
+
 #[inline]
 pub fn is_sep_byte(b: u8) -> bool {
     b == b'/'
@@ -17,3 +20,29 @@ pub fn parse_prefix(_: &OsStr) -> Option<Prefix<'_>> {
 
 pub const MAIN_SEP_STR: &str = "/";
 pub const MAIN_SEP: char = '/';
+
+/// Lexically normalizes `path` into the canonical form used as a key by the
+/// virtual filesystem.
+///
+/// `.` components, repeated separators and trailing separators are dropped,
+/// and `..` removes the preceding normal component. As on Unix, `..` at the
+/// root stays at the root, while leading `..` components of a relative path
+/// are kept. No filesystem access (e.g. symlink resolution) takes place.
+pub fn normalize(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::Prefix(_) | Component::RootDir => normalized.push(component),
+            Component::CurDir => {}
+            Component::ParentDir => match normalized.components().next_back() {
+                Some(Component::Normal(_)) => {
+                    normalized.pop();
+                }
+                Some(Component::RootDir) => {}
+                _ => normalized.push(".."),
+            },
+            Component::Normal(name) => normalized.push(name),
+        }
+    }
+    normalized
+}
@@ -0,0 +1,74 @@
+use super::*;
+use crate::ffi::OsString;
+use crate::sys_common::os_str_bytes::{OsStrExt, OsStringExt};
+
+fn normalized(path: &str) -> PathBuf {
+    normalize(Path::new(path))
+}
+
+#[test]
+fn separators() {
+    assert!(is_sep_byte(b'/'));
+    assert!(!is_sep_byte(b'\\'));
+    assert!(is_verbatim_sep(b'/'));
+    assert!(parse_prefix(OsStr::new("/a/b")).is_none());
+}
+
+#[test]
+fn collapse_dots() {
+    assert_eq!(normalized("/a/./b/../c"), Path::new("/a/c"));
+    assert_eq!(normalized("./a/b/.."), Path::new("a"));
+    assert_eq!(normalized("a/../../b"), Path::new("../b"));
+    assert_eq!(normalized("/../a"), Path::new("/a"));
+    assert_eq!(normalized("/a/b/../../.."), Path::new("/"));
+    assert_eq!(normalized("//a//b"), Path::new("/a/b"));
+}
+
+#[test]
+fn trailing_slashes() {
+    assert_eq!(normalized("/a/b/"), Path::new("/a/b"));
+    assert_eq!(normalized("/a/b/."), Path::new("/a/b"));
+    assert_eq!(normalized("a/"), Path::new("a"));
+    assert_eq!(normalized("/"), Path::new("/"));
+}
+
+#[test]
+fn absolute_and_relative() {
+    assert!(Path::new("/a/b").is_absolute());
+    assert!(normalized("/a/../b").is_absolute());
+    assert!(Path::new("a/b").is_relative());
+    assert!(normalized("./a").is_relative());
+    assert!(normalized("../a").is_relative());
+}
+
+#[test]
+fn os_str_bytes_round_trip() {
+    let bytes = b"a\xffb\x80\x00c";
+    let os_str = OsStr::from_bytes(bytes);
+    assert_eq!(os_str.as_bytes(), bytes);
+    assert!(os_str.to_str().is_none());
+
+    let os_string = OsString::from_vec(bytes.to_vec());
+    assert_eq!(os_string.as_os_str(), os_str);
+    assert_eq!(os_string.into_vec(), bytes);
+}
+
+#[test]
+fn push_and_join_bytes() {
+    let mut path = PathBuf::from("/dir");
+    path.push(OsStr::from_bytes(b"\xfe\xff"));
+    assert_eq!(path.as_os_str().as_bytes(), b"/dir/\xfe\xff");
+
+    let joined = path.join("file");
+    assert_eq!(joined.as_os_str().as_bytes(), b"/dir/\xfe\xff/file");
+    assert_eq!(joined.file_name().unwrap().as_bytes(), b"file");
+
+    // Joining an absolute path replaces the whole key.
+    assert_eq!(path.join("/other").as_os_str().as_bytes(), b"/other");
+}
+
+#[test]
+fn normalize_preserves_non_utf8_bytes() {
+    let path = Path::new(OsStr::from_bytes(b"/a/./\x80/../\xc3\x28/"));
+    assert_eq!(normalize(path).as_os_str().as_bytes(), b"/a/\xc3\x28");
+}
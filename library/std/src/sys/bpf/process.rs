@@ -10,6 +10,9 @@
 
 pub use crate::ffi::OsString as EnvKey;
 
+#[cfg(test)]
+mod tests;
+
 ////////////////////////////////////////////////////////////////////////////////
 // Command
 ////////////////////////////////////////////////////////////////////////////////
@@ -91,8 +94,8 @@ fn from(pipe: AnonPipe) -> Stdio {
 }
 
 impl From<File> for Stdio {
-    fn from(file: File) -> Stdio {
-        file.diverge()
+    fn from(_file: File) -> Stdio {
+        panic!("unsupported")
     }
 }
 
@@ -158,18 +161,26 @@ pub fn code(self) -> Option<NonZeroI32> {
     }
 }
 
+/// The status the program entrypoint returns to the runtime: 0 is success,
+/// anything else is a program error code.
 #[derive(PartialEq, Eq, Clone, Copy, Debug)]
-pub struct ExitCode(bool);
+pub struct ExitCode(u8);
 
 impl ExitCode {
-    pub const SUCCESS: ExitCode = ExitCode(false);
-    pub const FAILURE: ExitCode = ExitCode(true);
+    pub const SUCCESS: ExitCode = ExitCode(0);
+    pub const FAILURE: ExitCode = ExitCode(1);
 
     pub fn as_i32(&self) -> i32 {
         self.0 as i32
     }
 }
 
+impl From<u8> for ExitCode {
+    fn from(code: u8) -> Self {
+        ExitCode(code)
+    }
+}
+
 pub struct Process(Void);
 
 impl Process {
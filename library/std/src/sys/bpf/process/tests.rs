@@ -0,0 +1,9 @@
+use super::*;
+
+#[test]
+fn exit_code_round_trip() {
+    assert_eq!(ExitCode::SUCCESS.as_i32(), 0);
+    assert_ne!(ExitCode::FAILURE.as_i32(), 0);
+    assert_eq!(ExitCode::from(0), ExitCode::SUCCESS);
+    assert_eq!(ExitCode::from(42).as_i32(), 42);
+}
@@ -0,0 +1,39 @@
+//! The address of an account or program.
+
+use crate::fmt;
+use crate::sys::base58::base58_encode;
+
+#[cfg(test)]
+mod tests;
+
+/// A 32-byte account or program address. Displayed in base58, the way
+/// Solana tools print addresses.
+#[repr(transparent)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Pubkey([u8; 32]);
+
+impl Pubkey {
+    pub const fn from_bytes(bytes: [u8; 32]) -> Pubkey {
+        Pubkey(bytes)
+    }
+
+    pub const fn to_bytes(self) -> [u8; 32] {
+        self.0
+    }
+}
+
+impl AsRef<[u8]> for Pubkey {
+    fn as_ref(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+impl fmt::Display for Pubkey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // 32 bytes never take more than 44 base58 digits.
+        let mut out = [0u8; 44];
+        let len = base58_encode(&self.0, &mut out).map_err(|_| fmt::Error)?;
+        // The base58 alphabet is ASCII.
+        f.pad(unsafe { crate::str::from_utf8_unchecked(&out[..len]) })
+    }
+}
@@ -0,0 +1,21 @@
+use super::*;
+
+#[test]
+fn bytes_round_trip() {
+    let mut bytes = [0u8; 32];
+    bytes[31] = 7;
+    let key = Pubkey::from_bytes(bytes);
+    assert_eq!(key.to_bytes(), bytes);
+    assert_eq!(key.as_ref(), &bytes[..]);
+    assert_eq!(key, Pubkey::from_bytes(bytes));
+    assert_ne!(key, Pubkey::from_bytes([0; 32]));
+}
+
+#[test]
+fn displays_as_base58() {
+    assert_eq!(Pubkey::from_bytes([0; 32]).to_string(), "11111111111111111111111111111111");
+    assert_eq!(
+        Pubkey::from_bytes([0xff; 32]).to_string(),
+        "JEKNVnkbo3jma5nREBBJCDoXFVeKkD56V3xKrvRmWxFG"
+    );
+}
@@ -1,16 +1,182 @@
+use crate::cell::UnsafeCell;
 use crate::io;
 
+#[cfg(test)]
+mod tests;
+
 pub struct Stdin;
 pub struct Stdout;
 pub struct Stderr;
 
+/// Input returned by `Stdin`, and how much of it has been consumed. There
+/// is no standard input on BPF, so it is empty unless seeded with
+/// `set_stdin`.
+struct SeededInput {
+    data: Vec<u8>,
+    pos: usize,
+}
+
+struct StdinBuffer(UnsafeCell<SeededInput>);
+
+unsafe impl Sync for StdinBuffer {} // no threads on BPF
+
+static STDIN_BUFFER: StdinBuffer =
+    StdinBuffer(UnsafeCell::new(SeededInput { data: Vec::new(), pos: 0 }));
+
+/// Replaces the input read from `Stdin` with `data`.
+pub fn set_stdin(data: Vec<u8>) {
+    unsafe { *STDIN_BUFFER.0.get() = SeededInput { data, pos: 0 } };
+}
+
 impl Stdin {
+    /// Reads the seeded input up to and including the next `\n`, or to its
+    /// end, and appends it to `buf`. Returns the number of bytes read, which
+    /// is 0 once the input is exhausted. Nothing is consumed if the line is
+    /// not valid UTF-8.
+    pub fn read_line(&mut self, buf: &mut String) -> io::Result<usize> {
+        let len = {
+            let available = io::BufRead::fill_buf(self)?;
+            let len = match available.iter().position(|&b| b == b'\n') {
+                Some(i) => i + 1,
+                None => available.len(),
+            };
+            let line = core::str::from_utf8(&available[..len]).map_err(|_| {
+                io::Error::new_const(
+                    io::ErrorKind::InvalidData,
+                    &"stream did not contain valid UTF-8",
+                )
+            })?;
+            buf.push_str(line);
+            len
+        };
+        io::BufRead::consume(self, len);
+        Ok(len)
+    }
 }
 
 impl io::Read for Stdin {
-    fn read(&mut self, _buf: &mut [u8]) -> io::Result<usize> {
-        Ok(0)
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = {
+            let available = io::BufRead::fill_buf(self)?;
+            let n = buf.len().min(available.len());
+            buf[..n].copy_from_slice(&available[..n]);
+            n
+        };
+        io::BufRead::consume(self, n);
+        Ok(n)
+    }
+}
+
+impl io::BufRead for Stdin {
+    fn fill_buf(&mut self) -> io::Result<&[u8]> {
+        let input = unsafe { &*STDIN_BUFFER.0.get() };
+        Ok(&input.data[input.pos..])
+    }
+
+    fn consume(&mut self, amt: usize) {
+        let input = unsafe { &mut *STDIN_BUFFER.0.get() };
+        input.pos = input.data.len().min(input.pos + amt);
+    }
+}
+
+/// Holds output until a full line is available, since every `sol_log` call
+/// becomes a separate entry in the program log.
+///
+/// At most `N` bytes are held, inline so that buffering does not grow the
+/// (non-freeing) heap; a longer line is logged in pieces.
+struct LineBuffer<const N: usize> {
+    buf: [u8; N],
+    len: usize,
+}
+
+impl<const N: usize> LineBuffer<N> {
+    const fn new() -> LineBuffer<N> {
+        LineBuffer { buf: [0; N], len: 0 }
+    }
+
+    /// Appends as much of `data` as fits and passes each completed line,
+    /// without its newline, to `log`. Returns the number of bytes taken.
+    fn write(&mut self, data: &[u8], log: &mut dyn FnMut(&[u8])) -> usize {
+        if self.len == N {
+            self.flush_full(log);
+        }
+        let n = data.len().min(N - self.len);
+        self.buf[self.len..self.len + n].copy_from_slice(&data[..n]);
+        self.len += n;
+        let mut start = 0;
+        while let Some(i) = self.buf[start..self.len].iter().position(|&b| b == b'\n') {
+            log(&self.buf[start..start + i]);
+            start += i + 1;
+        }
+        self.consume(start);
+        n
+    }
+
+    /// Passes any incomplete trailing line to `log`.
+    fn flush(&mut self, log: &mut dyn FnMut(&[u8])) {
+        if self.len > 0 {
+            log(&self.buf[..self.len]);
+            self.len = 0;
+        }
+    }
+
+    // Logs a full buffer up to the last character boundary, so that a
+    // character cut off by the capacity is logged whole with the next piece.
+    fn flush_full(&mut self, log: &mut dyn FnMut(&[u8])) {
+        let end = match utf8_prefix_len(&self.buf[..self.len]) {
+            0 => self.len,
+            end => end,
+        };
+        log(&self.buf[..end]);
+        self.consume(end);
+    }
+
+    fn consume(&mut self, n: usize) {
+        self.buf.copy_within(n..self.len, 0);
+        self.len -= n;
     }
+
+    #[cfg(test)]
+    fn pending(&self) -> &[u8] {
+        &self.buf[..self.len]
+    }
+}
+
+// Returns the length of `bytes` without a UTF-8 sequence that is cut off at
+// its end.
+fn utf8_prefix_len(bytes: &[u8]) -> usize {
+    let start = match bytes.iter().rev().take(4).position(|&b| b & 0xc0 != 0x80) {
+        Some(i) => bytes.len() - 1 - i,
+        None => return bytes.len(),
+    };
+    let width = match bytes[start] {
+        0x00..=0x7f => 1,
+        0xc0..=0xdf => 2,
+        0xe0..=0xef => 3,
+        _ => 4,
+    };
+    if start + width > bytes.len() { start } else { bytes.len() }
+}
+
+struct StdoutBuffer(UnsafeCell<LineBuffer<{ crate::sys::MAX_LOG_MESSAGE_LEN }>>);
+
+unsafe impl Sync for StdoutBuffer {} // no threads on BPF
+
+static STDOUT_BUFFER: StdoutBuffer = StdoutBuffer(UnsafeCell::new(LineBuffer::new()));
+
+// Output is not necessarily UTF-8, which the log requires.
+#[cfg(not(test))]
+fn log(line: &[u8]) {
+    crate::sys::sol_log(&String::from_utf8_lossy(line));
+}
+
+// Tests run without the runtime, so log entries are collected instead.
+#[cfg(test)]
+pub(crate) static mut LOGGED: Vec<Vec<u8>> = Vec::new();
+
+#[cfg(test)]
+fn log(line: &[u8]) {
+    unsafe { LOGGED.push(line.to_vec()) };
 }
 
 impl Stdout {
@@ -18,13 +184,32 @@ impl Stdout {
 
 impl io::Write for Stdout {
     fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
-        unsafe {
-            crate::sys::sol_log(core::str::from_utf8_unchecked(buf));
-        }
-        Ok(buf.len())
+        Ok(unsafe { (*STDOUT_BUFFER.0.get()).write(buf, &mut log) })
+    }
+
+    fn write_vectored(&mut self, bufs: &[io::IoSlice<'_>]) -> io::Result<usize> {
+        io::default_write_vectored(|buf| self.write(buf), bufs)
     }
 
     fn flush(&mut self) -> io::Result<()> {
+        unsafe { (*STDOUT_BUFFER.0.get()).flush(&mut log) };
+        Ok(())
+    }
+
+    // The buffer takes at most its capacity per call, so larger inputs are
+    // logged over several rounds.
+    fn write_all(&mut self, mut buf: &[u8]) -> io::Result<()> {
+        while !buf.is_empty() {
+            let n = self.write(buf)?;
+            buf = &buf[n..];
+        }
+        Ok(())
+    }
+
+    fn write_all_vectored(&mut self, bufs: &mut [io::IoSlice<'_>]) -> io::Result<()> {
+        for buf in bufs.iter() {
+            self.write_all(buf)?;
+        }
         Ok(())
     }
 }
@@ -34,9 +219,7 @@ impl Stderr {
 
 impl io::Write for Stderr {
     fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
-        unsafe {
-            crate::sys::sol_log(core::str::from_utf8_unchecked(buf));
-        }
+        crate::sys::sol_log(&String::from_utf8_lossy(buf));
         Ok(buf.len())
     }
 
@@ -45,10 +228,25 @@ fn flush(&mut self) -> io::Result<()> {
     }
 }
 
+/// Logs any buffered stdout output that has not been terminated by a newline.
+pub fn cleanup() {
+    let _ = io::Write::flush(&mut Stdout);
+}
+
 pub fn is_ebadf(_err: &io::Error) -> bool {
     true
 }
 
+/// Returns `default` in place of an error from an operation the runtime does
+/// not provide, the way other platforms tolerate a closed descriptor, so
+/// that printing does not fail over a missing syscall.
+pub fn handle_unsupported<T>(r: io::Result<T>, default: T) -> io::Result<T> {
+    match r {
+        Err(ref e) if crate::sys::is_unsupported(e) => Ok(default),
+        r => r,
+    }
+}
+
 pub fn panic_output() -> Option<impl io::Write> {
     None::<Box<dyn io::Write>>
 }
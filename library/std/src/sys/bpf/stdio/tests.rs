@@ -0,0 +1,135 @@
+use super::*;
+
+fn capture(f: impl FnOnce(&mut dyn FnMut(&[u8]))) -> Vec<String> {
+    let mut lines = Vec::new();
+    f(&mut |line: &[u8]| lines.push(String::from_utf8(line.to_vec()).unwrap()));
+    lines
+}
+
+#[test]
+fn line_buffer_emits_complete_lines() {
+    let mut buf = LineBuffer::<64>::new();
+    assert_eq!(capture(|log| { buf.write(b"hello ", log); }), Vec::<String>::new());
+    assert_eq!(
+        capture(|log| assert_eq!(buf.write(b"world\nfoo\n\nbar", log), 14)),
+        ["hello world", "foo", ""]
+    );
+    assert_eq!(buf.pending(), b"bar");
+}
+
+#[test]
+fn line_buffer_flush_emits_partial_line() {
+    let mut buf = LineBuffer::<64>::new();
+    assert!(capture(|log| { buf.write(b"no trailing newline", log); }).is_empty());
+    assert_eq!(capture(|log| buf.flush(log)), ["no trailing newline"]);
+    assert!(capture(|log| buf.flush(log)).is_empty());
+}
+
+#[test]
+fn cleanup_logs_unflushed_output() {
+    let logged_before = unsafe { LOGGED.len() };
+    let mut stdout = Stdout;
+    io::Write::write_all(&mut stdout, b"line\npartial").unwrap();
+    assert_eq!(unsafe { &LOGGED[logged_before..] }, &[b"line".to_vec()]);
+
+    cleanup();
+    assert_eq!(unsafe { &LOGGED[logged_before..] }, &[b"line".to_vec(), b"partial".to_vec()]);
+}
+
+#[test]
+fn line_buffer_takes_at_most_its_capacity() {
+    let mut buf = LineBuffer::<4>::new();
+    assert_eq!(capture(|log| assert_eq!(buf.write(b"ab\ncdefgh", log), 4)), ["ab"]);
+    assert_eq!(buf.pending(), b"c");
+    // A full buffer is logged to make room.
+    assert!(capture(|log| assert_eq!(buf.write(b"defgh", log), 3)).is_empty());
+    assert_eq!(capture(|log| assert_eq!(buf.write(b"gh", log), 2)), ["cdef"]);
+}
+
+#[test]
+fn line_buffer_does_not_split_characters() {
+    let e_acute = "é".as_bytes();
+    let mut buf = LineBuffer::<4>::new();
+    assert_eq!(buf.write(b"abc", &mut |_| unreachable!()), 3);
+    assert_eq!(buf.write(e_acute, &mut |_| unreachable!()), 1);
+    assert_eq!(capture(|log| assert_eq!(buf.write(&e_acute[1..], log), 1)), ["abc"]);
+    assert_eq!(capture(|log| buf.flush(log)), ["é"]);
+}
+
+#[test]
+fn utf8_prefix_len_drops_a_cut_off_character() {
+    let euro = "a€".as_bytes();
+    assert_eq!(utf8_prefix_len(euro), 4);
+    assert_eq!(utf8_prefix_len(&euro[..3]), 1);
+    assert_eq!(utf8_prefix_len(b"ab"), 2);
+}
+
+#[test]
+fn write_all_logs_input_larger_than_the_buffer() {
+    let logged_before = unsafe { LOGGED.len() };
+    let len = 3 * crate::sys::MAX_LOG_MESSAGE_LEN;
+    let data: Vec<u8> = (0..len).map(|i| b'a' + (i % 26) as u8).collect();
+    let mut stdout = Stdout;
+    io::Write::write_all(&mut stdout, &data).unwrap();
+    io::Write::flush(&mut stdout).unwrap();
+
+    let logged = unsafe { &LOGGED[logged_before..] };
+    assert_eq!(logged.len(), 3);
+    assert!(logged.iter().all(|entry| entry.len() == crate::sys::MAX_LOG_MESSAGE_LEN));
+    assert_eq!(logged.concat(), data);
+}
+
+#[test]
+fn stdin_fill_buf_peeks_without_consuming() {
+    use crate::io::{BufRead, Read};
+
+    set_stdin(b"hello world".to_vec());
+    let mut stdin = Stdin;
+    assert_eq!(stdin.fill_buf().unwrap(), b"hello world");
+    assert_eq!(stdin.fill_buf().unwrap(), b"hello world");
+
+    stdin.consume(6);
+    let mut rest = Vec::new();
+    stdin.read_to_end(&mut rest).unwrap();
+    assert_eq!(rest, b"world");
+    assert_eq!(stdin.fill_buf().unwrap(), b"");
+}
+
+#[test]
+fn stdin_read_line_reads_one_line_at_a_time() {
+    set_stdin(b"first\nlast".to_vec());
+    let mut stdin = Stdin;
+    let mut line = String::from("> ");
+    assert_eq!(stdin.read_line(&mut line).unwrap(), 6);
+    assert_eq!(line, "> first\n");
+
+    line.clear();
+    assert_eq!(stdin.read_line(&mut line).unwrap(), 4);
+    assert_eq!(line, "last");
+
+    line.clear();
+    assert_eq!(stdin.read_line(&mut line).unwrap(), 0);
+    assert_eq!(line, "");
+
+    set_stdin(b"\xff\n".to_vec());
+    assert_eq!(stdin.read_line(&mut line).unwrap_err().kind(), io::ErrorKind::InvalidData);
+    assert_eq!(line, "");
+}
+
+#[test]
+fn unsupported_writes_are_swallowed() {
+    struct NoSyscall;
+    impl io::Write for NoSyscall {
+        fn write(&mut self, _buf: &[u8]) -> io::Result<usize> {
+            crate::sys::unsupported()
+        }
+        fn flush(&mut self) -> io::Result<()> {
+            Err(io::Error::new_const(io::ErrorKind::Other, &"flush failed"))
+        }
+    }
+
+    let mut out = NoSyscall;
+    assert_eq!(handle_unsupported(io::Write::write(&mut out, b"hello"), 5).unwrap(), 5);
+    let err = handle_unsupported(io::Write::flush(&mut out), ()).unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::Other);
+}
@@ -0,0 +1,548 @@
+use super::*;
+
+#[test]
+fn compute_budget_exceeded_error() {
+    let err = io::Error::from_raw_os_error(COMPUTE_BUDGET_EXCEEDED);
+    assert!(is_compute_budget_exceeded(&err));
+    assert_eq!(err.kind(), io::ErrorKind::Other);
+
+    assert!(!is_compute_budget_exceeded(&io::Error::from_raw_os_error(1)));
+    assert!(!is_compute_budget_exceeded(&io::Error::new(io::ErrorKind::Other, "other")));
+}
+
+#[test]
+fn unsupported_err_is_recognized() {
+    let err = unsupported_err();
+    assert_eq!(err.kind(), io::ErrorKind::Other);
+    assert_eq!(err.to_string(), "operation not supported on BPF yet");
+    assert!(is_unsupported(&err));
+
+    let lookalike = io::Error::new(io::ErrorKind::Other, "operation not supported on BPF yet");
+    assert!(!is_unsupported(&lookalike));
+    assert!(!is_unsupported(&io::Error::from_raw_os_error(1)));
+}
+
+#[test]
+fn known_error_codes_are_described() {
+    let err = io::Error::from_raw_os_error(COMPUTE_BUDGET_EXCEEDED);
+    assert_eq!(err.to_string(), "computational budget exceeded (os error 37)");
+    assert_eq!(error_description(7), Some("missing required signature for instruction"));
+    assert_eq!(error_description(-1), None);
+    assert_eq!(io::Error::from_raw_os_error(1000).to_string(), "unknown error (os error 1000)");
+}
+
+#[test]
+fn syscall_status_to_result() {
+    assert!(status_to_result(0).is_ok());
+
+    let err = status_to_result(COMPUTE_BUDGET_EXCEEDED as u64).unwrap_err();
+    assert_eq!(err.raw_os_error(), Some(COMPUTE_BUDGET_EXCEEDED));
+    assert_eq!(err.kind(), io::ErrorKind::Other);
+    assert!(is_compute_budget_exceeded(&err));
+
+    let err = status_to_result(u64::MAX).unwrap_err();
+    assert_eq!(err.raw_os_error(), None);
+    assert_eq!(err.kind(), io::ErrorKind::Other);
+}
+
+#[test]
+fn program_id_round_trip() {
+    assert_eq!(program_id(), None);
+    let id = Pubkey::from_bytes([7u8; 32]);
+    set_program_id(&id);
+    assert_eq!(program_id(), Some(id));
+}
+
+#[test]
+fn nested_panic_skips_reporting() {
+    let mut logged = Vec::new();
+    let mut aborts = 0;
+    // The outer report panics again; the nested panic logs and aborts, so
+    // the outer panic never reaches its own abort.
+    let code = report_panic(
+        || {
+            let code = report_panic(|| unreachable!("nested panic must not be reported"), |msg| {
+                logged.push(msg.to_string())
+            });
+            assert_eq!(code, PANIC_RETURN_CODE);
+            aborts += 1;
+            6001
+        },
+        |_| unreachable!("first panic must be reported"),
+    );
+    assert_eq!(code, 6001);
+    assert_eq!(logged, ["panic during panic"]);
+    assert_eq!(aborts, 1);
+}
+
+#[test]
+fn return_code_panic_mode_lets_the_next_test_run() {
+    static mut CAPTURED: Vec<String> = Vec::new();
+    fn sink(message: &str) {
+        unsafe { CAPTURED.push(message.to_string()) };
+    }
+    fn resume(code: u64) -> ! {
+        crate::panic::panic_any(code)
+    }
+    fn failing_test(name: &'static str) {
+        let code = report_panic(
+            || {
+                sol_log(name);
+                PANIC_RETURN_CODE
+            },
+            sol_log,
+        );
+        end_panic(code, || unreachable!("must not abort in ReturnCode mode"));
+    }
+
+    PANIC_STATE.panicking.set(false);
+    set_panic_mode(PanicMode::ReturnCode(resume));
+    set_log_sink(Some(sink));
+    for &name in &["first", "second"] {
+        let payload = crate::panic::catch_unwind(|| failing_test(name)).unwrap_err();
+        assert_eq!(payload.downcast_ref::<u64>(), Some(&PANIC_RETURN_CODE));
+    }
+    set_log_sink(None);
+    set_panic_mode(PanicMode::Abort);
+    assert_eq!(
+        unsafe { &CAPTURED },
+        &[
+            "first",
+            "panic: returning to the harness",
+            "second",
+            "panic: returning to the harness"
+        ]
+    );
+}
+
+#[test]
+fn panic_messages_can_carry_an_error_code() {
+    assert_eq!(program_error_code(&format_args!("program error: {}", 6001)), Some(6001));
+    assert_eq!(
+        program_error_code(&format_args!("{}{}", PROGRAM_ERROR_PREFIX, u64::MAX)),
+        Some(u64::MAX)
+    );
+    assert_eq!(program_error_code(&format_args!("program error: 18446744073709551616")), None);
+    assert_eq!(program_error_code(&format_args!("program error: +1")), None);
+    assert_eq!(program_error_code(&format_args!("program error: ")), None);
+    assert_eq!(program_error_code(&format_args!("program error: 1 extra")), None);
+    assert_eq!(program_error_code(&format_args!("error 6001")), None);
+
+    fn resume(code: u64) -> ! {
+        crate::panic::panic_any(code)
+    }
+    set_log_sink(Some(|_| {}));
+    set_panic_mode(PanicMode::ReturnCode(resume));
+    let payload = crate::panic::catch_unwind(|| {
+        end_panic(6001, || unreachable!("must not abort in ReturnCode mode"))
+    })
+    .unwrap_err();
+    set_panic_mode(PanicMode::Abort);
+    set_log_sink(None);
+    assert_eq!(payload.downcast_ref::<u64>(), Some(&6001));
+}
+
+#[test]
+#[cfg(feature = "bpf-deterministic-random")]
+fn fill_bytes_deterministic_is_seeded() {
+    let mut a = [0u8; 37];
+    let mut b = [0u8; 37];
+    fill_bytes_deterministic(&mut a, 42);
+    fill_bytes_deterministic(&mut b, 42);
+    assert_eq!(a, b);
+    assert!(a.iter().any(|&byte| byte != 0));
+
+    // splitmix64's first output for seed 0.
+    let mut first = [0u8; 8];
+    fill_bytes_deterministic(&mut first, 0);
+    assert_eq!(u64::from_le_bytes(first), 0xe220a8397b1dcdaf);
+
+    fill_bytes_deterministic(&mut b, 43);
+    assert_ne!(a, b);
+}
+
+#[test]
+#[cfg(feature = "bpf-remaining-compute-units")]
+fn remaining_compute_units_links() {
+    let syscall: fn() -> u64 = sol_remaining_compute_units;
+    assert!(syscall() > 0);
+}
+
+// Needs a VM that meters compute units.
+#[test]
+#[cfg(feature = "bpf-remaining-compute-units")]
+fn remaining_compute_units_decrease_with_work() {
+    let before = sol_remaining_compute_units();
+    let mut acc = 0u64;
+    for i in 0..100 {
+        acc = crate::hint::black_box(acc.wrapping_mul(31).wrapping_add(i));
+    }
+    let after = sol_remaining_compute_units();
+    assert!(after < before, "{} compute units left before, {} after", before, after);
+}
+
+#[test]
+fn long_panic_message_is_truncated() {
+    let long = "x".repeat(MAX_PANIC_MESSAGE_LEN * 2);
+    let msg = truncate_message(&format_args!("panicked: {}", long), MAX_PANIC_MESSAGE_LEN).unwrap();
+    assert_eq!(msg.len(), MAX_PANIC_MESSAGE_LEN + "...".len());
+    assert!(msg.starts_with("panicked: xxx"));
+    assert!(msg.ends_with("x..."));
+
+    // Multi-byte characters are not split.
+    let msg = truncate_message(&format_args!("{}", "é".repeat(8)), 5).unwrap();
+    assert_eq!(msg, "éé...");
+
+    assert_eq!(truncate_message(&format_args!("short {}", 1), MAX_PANIC_MESSAGE_LEN), None);
+    assert_eq!(truncate_message(&format_args!("{}{}", "xx", "xxx"), 5), None);
+    assert_eq!(truncate_message(&format_args!("{}{}", "xxx", "xxx"), 5).unwrap(), "xxxxx...");
+}
+
+#[test]
+fn long_log_messages_are_chunked() {
+    let message = "é".repeat(MAX_LOG_MESSAGE_LEN);
+    let chunks: Vec<_> = log_chunks(&message, MAX_LOG_MESSAGE_LEN).collect();
+    assert_eq!(chunks.len(), 2);
+    assert!(chunks.iter().all(|chunk| chunk.len() == MAX_LOG_MESSAGE_LEN));
+    assert_eq!(chunks.concat(), message);
+
+    // Chunks end before a character that would not fit.
+    let chunks: Vec<_> = log_chunks("aé€", 2).collect();
+    assert_eq!(chunks, ["a", "é", "€"]);
+
+    assert_eq!(log_chunks("", 2).count(), 0);
+}
+
+#[test]
+fn deterministic_hashing_ignores_the_clock() {
+    let keys_at = |slot, unix_timestamp| {
+        unsafe {
+            time::MOCK_CLOCK.slot = slot;
+            time::MOCK_CLOCK.unix_timestamp = unix_timestamp;
+        }
+        hashmap_random_keys()
+    };
+
+    // Until a program asks for either, the keys are the fixed ones.
+    assert_eq!(keys_at(1, 1_600_000_000), (1, 2));
+    assert_eq!(keys_at(2, 1_600_000_400), (1, 2));
+
+    set_deterministic_hashing(false);
+    assert_ne!(keys_at(1, 1_600_000_000), keys_at(2, 1_600_000_400));
+
+    set_deterministic_hashing(true);
+    assert_eq!(keys_at(1, 1_600_000_000), (0, 0));
+    assert_eq!(keys_at(2, 1_600_000_400), (0, 0));
+    DETERMINISTIC_HASHING.enabled.set(None);
+}
+
+#[test]
+fn maps_with_the_same_seed_iterate_identically() {
+    use crate::collections::HashMap;
+
+    assert_eq!(fixed_random_keys(42), fixed_random_keys(42));
+    assert_ne!(fixed_random_keys(42), fixed_random_keys(43));
+
+    let build = |seed| {
+        let mut map = HashMap::with_hasher(seeded_random_state(seed));
+        map.extend((0..64).map(|i| (i, i * i)));
+        map.into_iter().collect::<Vec<_>>()
+    };
+    assert_eq!(build(7), build(7));
+}
+
+#[test]
+fn log_sink_captures_sol_log() {
+    static mut CAPTURED: Vec<String> = Vec::new();
+    fn sink(message: &str) {
+        unsafe { CAPTURED.push(message.to_string()) };
+    }
+
+    set_log_sink(Some(sink));
+    sol_log("hello from the sink");
+    set_log_sink(None);
+    assert_eq!(unsafe { &CAPTURED }, &["hello from the sink"]);
+}
+
+#[test]
+fn kv_logging_quotes_values_when_needed() {
+    assert_eq!(format_kv(&[]), "");
+    assert_eq!(format_kv(&[("slot", "42"), ("owner", "system")]), "slot=42 owner=system");
+    assert_eq!(
+        format_kv(&[("msg", "transfer failed"), ("expr", "a=\"b\""), ("memo", "")]),
+        r#"msg="transfer failed" expr="a=\"b\"" memo="""#
+    );
+}
+
+#[test]
+fn log_budget_drops_logs_past_the_limit() {
+    static mut CAPTURED: Vec<String> = Vec::new();
+    fn sink(message: &str) {
+        unsafe { CAPTURED.push(message.to_string()) };
+    }
+
+    set_log_sink(Some(sink));
+    set_log_budget(2);
+    for i in 0..4 {
+        sol_log(&format!("iteration {}", i));
+    }
+    reset_log_budget();
+    sol_log("after reset");
+    set_log_sink(None);
+    assert_eq!(
+        unsafe { &CAPTURED },
+        &["iteration 0", "iteration 1", "log budget exhausted", "after reset"]
+    );
+}
+
+#[cfg(feature = "bpf-secp256k1-recover")]
+const MOCK_SECP256K1_HASH: [u8; 32] = [0x11; 32];
+#[cfg(feature = "bpf-secp256k1-recover")]
+const MOCK_SECP256K1_SIGNATURE: [u8; 64] = [0x22; 64];
+#[cfg(feature = "bpf-secp256k1-recover")]
+const MOCK_SECP256K1_PUBKEY: [u8; 64] = [0x33; 64];
+// The runtime's status codes for a recovery id past 3 and for a signature
+// that recovers no key.
+#[cfg(feature = "bpf-secp256k1-recover")]
+const INVALID_RECOVERY_ID: u64 = 2;
+#[cfg(feature = "bpf-secp256k1-recover")]
+const INVALID_SIGNATURE: u64 = 3;
+
+#[cfg(feature = "bpf-secp256k1-recover")]
+thread_local! {
+    // The recovery ids `sol_secp256k1_recover` was called with on this thread.
+    static SECP256K1_RECOVERY_IDS: crate::cell::RefCell<Vec<u64>> =
+        crate::cell::RefCell::new(Vec::new());
+}
+
+#[cfg(feature = "bpf-secp256k1-recover")]
+fn take_secp256k1_recovery_ids() -> Vec<u64> {
+    SECP256K1_RECOVERY_IDS.with(|ids| ids.take())
+}
+
+// Recovers `MOCK_SECP256K1_PUBKEY` from `MOCK_SECP256K1_SIGNATURE` over
+// `MOCK_SECP256K1_HASH` with recovery id 2, and an unrelated key from
+// anything else, like a real recovery from a tampered signature would.
+// Recovery id 0 recovers no key at all.
+#[cfg(feature = "bpf-secp256k1-recover")]
+pub(super) unsafe fn sol_secp256k1_recover(
+    hash: *const u8,
+    recovery_id: u64,
+    signature: *const u8,
+    result: *mut u8,
+) -> u64 {
+    SECP256K1_RECOVERY_IDS.with(|ids| ids.borrow_mut().push(recovery_id));
+    match recovery_id {
+        0 => return INVALID_SIGNATURE,
+        1..=3 => {}
+        _ => return INVALID_RECOVERY_ID,
+    }
+    let hash = crate::slice::from_raw_parts(hash, 32);
+    let signature = crate::slice::from_raw_parts(signature, 64);
+    let result = crate::slice::from_raw_parts_mut(result, 64);
+    if hash == MOCK_SECP256K1_HASH && signature == MOCK_SECP256K1_SIGNATURE && recovery_id == 2 {
+        result.copy_from_slice(&MOCK_SECP256K1_PUBKEY);
+    } else {
+        for (i, byte) in result.iter_mut().enumerate() {
+            *byte = !(signature[i] ^ hash[i % 32]) ^ recovery_id as u8;
+        }
+    }
+    0
+}
+
+#[test]
+#[cfg(feature = "bpf-secp256k1-recover")]
+fn secp256k1_recover_reports_the_syscall_status() {
+    let hash = MOCK_SECP256K1_HASH;
+    let signature = MOCK_SECP256K1_SIGNATURE;
+    assert_eq!(secp256k1_recover(&hash, 2, &signature).unwrap(), MOCK_SECP256K1_PUBKEY);
+
+    let error = secp256k1_recover(&hash, 0, &signature).unwrap_err();
+    assert_eq!(error.raw_os_error(), Some(INVALID_SIGNATURE as i32));
+    let error = secp256k1_recover(&hash, 4, &signature).unwrap_err();
+    assert_eq!(error.raw_os_error(), Some(INVALID_RECOVERY_ID as i32));
+    assert_eq!(take_secp256k1_recovery_ids(), [2, 0, 4]);
+}
+
+#[test]
+#[cfg(feature = "bpf-secp256k1-recover")]
+fn secp256k1_verify_checks_the_recovered_key() {
+    let hash = MOCK_SECP256K1_HASH;
+    let signature = MOCK_SECP256K1_SIGNATURE;
+    let pubkey = MOCK_SECP256K1_PUBKEY;
+    take_secp256k1_recovery_ids();
+
+    // A failed recovery moves on to the next id, and a match ends the search.
+    assert!(secp256k1_verify(&hash, &signature, &pubkey));
+    assert_eq!(take_secp256k1_recovery_ids(), [0, 1, 2]);
+
+    let mut tampered = signature;
+    tampered[10] ^= 1;
+    assert!(!secp256k1_verify(&hash, &tampered, &pubkey));
+    assert_eq!(take_secp256k1_recovery_ids(), [0, 1, 2, 3]);
+    assert!(!secp256k1_verify(&[0x12; 32], &signature, &pubkey));
+    assert_eq!(take_secp256k1_recovery_ids(), [0, 1, 2, 3]);
+}
+
+#[test]
+#[cfg(feature = "bpf-remaining-compute-units")]
+fn cu_checkpoint_logs_label_and_remaining_units() {
+    static mut CAPTURED: Vec<String> = Vec::new();
+    fn sink(message: &str) {
+        unsafe { CAPTURED.push(message.to_string()) };
+    }
+
+    set_log_sink(Some(sink));
+    log_cu_checkpoint_from("before invoke", || 199_850);
+    log_cu_checkpoint_from("after invoke", || 12_345);
+    set_log_sink(None);
+    assert_eq!(unsafe { &CAPTURED }, &["before invoke: 199850 CU", "after invoke: 12345 CU"]);
+}
+
+#[test]
+#[cfg(feature = "bpf-remaining-compute-units")]
+fn cu_markers_are_only_logged_when_enabled() {
+    static mut CAPTURED: Vec<String> = Vec::new();
+    fn sink(message: &str) {
+        unsafe { CAPTURED.push(message.to_string()) };
+    }
+
+    set_log_sink(Some(sink));
+    log_cu_marker_from("program entry", || 200_000);
+    log_cu_marker_from("program exit", || 150_000);
+    assert!(unsafe { CAPTURED.is_empty() });
+
+    set_cu_markers(true);
+    log_cu_marker_from("program entry", || 200_000);
+    log_cu_marker_from("program exit", || 150_000);
+    set_cu_markers(false);
+    set_log_sink(None);
+    assert_eq!(unsafe { &CAPTURED }, &["program entry: 200000 CU", "program exit: 150000 CU"]);
+}
+
+#[test]
+#[cfg(feature = "bpf-remaining-compute-units")]
+fn profile_cu_logs_the_delta_and_returns_the_result() {
+    static mut CAPTURED: Vec<String> = Vec::new();
+    fn sink(message: &str) {
+        unsafe { CAPTURED.push(message.to_string()) };
+    }
+
+    let remaining = crate::cell::Cell::new(200_000);
+    set_log_sink(Some(sink));
+    let work = || {
+        remaining.set(198_766);
+        42
+    };
+    let result = profile_cu_from("hash", work, || remaining.get());
+    set_log_sink(None);
+    assert_eq!(result, 42);
+    assert_eq!(unsafe { &CAPTURED }, &["hash: consumed 1234 CU"]);
+}
+
+#[test]
+fn arc_clone_and_drop_round_trip() {
+    let arc = crate::sync::Arc::new([0u8; 16]);
+    let weak = crate::sync::Arc::downgrade(&arc);
+    for _ in 0..1000 {
+        let clones: Vec<_> = (0..8).map(|_| arc.clone()).collect();
+        assert_eq!(crate::sync::Arc::strong_count(&arc), 9);
+        drop(clones);
+    }
+    assert_eq!(crate::sync::Arc::strong_count(&arc), 1);
+    drop(arc);
+    assert!(weak.upgrade().is_none());
+}
+
+#[test]
+fn logged_arithmetic_returns_the_result() {
+    assert_eq!(checked_add_u64(u64::MAX - 1, 1), Some(u64::MAX));
+    assert_eq!(checked_add_u64(u64::MAX, 1), None);
+    assert_eq!(checked_sub_u64(0, 1), None);
+    assert_eq!(logged_add_u64(40, 2), 42);
+    assert_eq!(logged_sub_u64(44, 2), 42);
+}
+
+#[test]
+fn overflow_logs_the_operands() {
+    static mut CAPTURED: Vec<String> = Vec::new();
+    fn sink(message: &str) {
+        unsafe { CAPTURED.push(message.to_string()) };
+    }
+
+    let mut operands = None;
+    set_log_sink(Some(sink));
+    report_overflow("addition", u64::MAX, 7, |a, b, c, d, e| operands = Some([a, b, c, d, e]));
+    set_log_sink(None);
+    assert_eq!(operands, Some([u64::MAX, 7, 0, 0, 0]));
+    assert_eq!(unsafe { &CAPTURED }, &["arithmetic overflow in u64 addition"]);
+}
+
+#[test]
+fn data_len_mismatch_logs_both_lengths() {
+    assert!(check_data_len(165, 165, |_, _, _, _, _| unreachable!()).is_ok());
+
+    let mut logged = None;
+    let err = check_data_len(82, 165, |a, b, c, d, e| logged = Some([a, b, c, d, e])).unwrap_err();
+    assert_eq!(logged, Some([82, 165, 0, 0, 0]));
+    assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    assert_eq!(err.to_string(), "account data is 82 bytes long, expected 165");
+}
+
+#[test]
+fn frame_depth_is_limited() {
+    static mut CAPTURED: Vec<String> = Vec::new();
+    fn sink(message: &str) {
+        unsafe { CAPTURED.push(message.to_string()) };
+    }
+    fn recurse(depth: usize) {
+        enter_frame_or(|| panic!("aborted"));
+        if depth > 1 {
+            recurse(depth - 1);
+        }
+        leave_frame();
+    }
+
+    set_max_frame_depth(8);
+    recurse(8);
+    recurse(8);
+    assert!(unsafe { CAPTURED.is_empty() });
+
+    set_log_sink(Some(sink));
+    assert!(crate::panic::catch_unwind(|| recurse(9)).is_err());
+    set_log_sink(None);
+    assert_eq!(unsafe { &CAPTURED }, &["stack depth exceeded"]);
+    set_max_frame_depth(DEFAULT_MAX_FRAME_DEPTH);
+}
+
+#[test]
+fn slice_descriptors_point_at_the_slices() {
+    let seed = b"vault";
+    let empty: &[u8] = &[];
+    let bump = [254u8];
+    let mut descriptors = [(crate::ptr::null(), u64::MAX); 4];
+    build_slice_descriptors(&[seed, empty, &bump], &mut descriptors);
+    assert_eq!(descriptors[0], (seed.as_ptr(), 5));
+    assert_eq!(descriptors[1], (empty.as_ptr(), 0));
+    assert_eq!(descriptors[2], (bump.as_ptr(), 1));
+    assert_eq!(descriptors[3], (crate::ptr::null(), u64::MAX));
+
+    build_slice_descriptors(&[], &mut []);
+}
+
+#[test]
+fn panics_from_std_stubs_are_terse() {
+    assert_eq!(
+        stub_panic_message("library/std/src/sys/bpf/os.rs", 32).as_deref(),
+        Some("unsupported in std stub os.rs:32")
+    );
+    assert_eq!(
+        stub_panic_message("/rustc/0123abcd/library/std/src/sys/bpf/time.rs", 86).as_deref(),
+        Some("unsupported in std stub time.rs:86")
+    );
+
+    // User code and the rest of std are reported in full.
+    assert_eq!(stub_panic_message("src/os.rs", 32), None);
+    assert_eq!(stub_panic_message("library/std/src/sys/bpf/fs.rs", 10), None);
+    assert_eq!(stub_panic_message("lib.rs", 1), None);
+}
@@ -1,7 +1,32 @@
-#![cfg(target_thread_local)]
+//! BPF programs run on a single thread, which exits when the program
+//! completes. Destructors are kept in a plain static list until then.
+
 #![unstable(feature = "thread_local_internals", issue = "none")]
 
-pub unsafe fn register_dtor(t: *mut u8, dtor: unsafe extern "C" fn(*mut u8)) {
-    use crate::sys_common::thread_local_dtor::register_dtor_fallback;
-    register_dtor_fallback(t, dtor);
+use crate::cell::UnsafeCell;
+
+#[cfg(test)]
+mod tests;
+
+type Dtor = unsafe extern "C" fn(*mut u8);
+
+struct Dtors {
+    list: UnsafeCell<Vec<(*mut u8, Dtor)>>,
+}
+
+unsafe impl Sync for Dtors {} // no threads on BPF
+
+static DTORS: Dtors = Dtors { list: UnsafeCell::new(Vec::new()) };
+
+pub unsafe fn register_dtor(t: *mut u8, dtor: Dtor) {
+    (*DTORS.list.get()).push((t, dtor));
+}
+
+/// Runs the registered destructors, most recently registered first.
+/// Destructors registered while this runs are run as well.
+pub unsafe fn run_dtors() {
+    // Pop one at a time, as destructors may register new ones.
+    while let Some((t, dtor)) = (*DTORS.list.get()).pop() {
+        dtor(t);
+    }
 }
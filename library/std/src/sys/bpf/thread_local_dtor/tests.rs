@@ -0,0 +1,17 @@
+use super::*;
+
+static mut ORDER: Vec<usize> = Vec::new();
+
+unsafe extern "C" fn record(t: *mut u8) {
+    ORDER.push(t as usize);
+}
+
+#[test]
+fn dtors_run_in_reverse_registration_order() {
+    unsafe {
+        register_dtor(1 as *mut u8, record);
+        register_dtor(2 as *mut u8, record);
+        run_dtors();
+        assert_eq!(ORDER, [2, 1]);
+    }
+}
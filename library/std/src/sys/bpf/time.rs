@@ -1,6 +1,50 @@
+use crate::io;
+use crate::sys::status_to_result;
 use crate::time::Duration;
 // use crate::sys::{TimeSysCall, TimeClock};
 
+#[cfg(test)]
+mod tests;
+
+#[cfg(not(test))]
+extern "C" {
+    fn sol_get_clock_sysvar(addr: *mut u8) -> u64;
+}
+
+/// The Clock sysvar, laid out as the runtime writes it.
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct Clock {
+    pub slot: u64,
+    pub epoch_start_timestamp: i64,
+    pub epoch: u64,
+    pub leader_schedule_epoch: u64,
+    pub unix_timestamp: i64,
+}
+
+/// Reads the Clock sysvar.
+pub fn clock() -> io::Result<Clock> {
+    let mut clock = Clock::default();
+    status_to_result(unsafe { sol_get_clock_sysvar(&mut clock as *mut Clock as *mut u8) })?;
+    Ok(clock)
+}
+
+// Tests run without the runtime, so the syscall reads a settable mock instead.
+#[cfg(test)]
+pub static mut MOCK_CLOCK: Clock = Clock {
+    slot: 0,
+    epoch_start_timestamp: 0,
+    epoch: 0,
+    leader_schedule_epoch: 0,
+    unix_timestamp: 0,
+};
+
+#[cfg(test)]
+unsafe fn sol_get_clock_sysvar(addr: *mut u8) -> u64 {
+    *(addr as *mut Clock) = MOCK_CLOCK;
+    0
+}
+
 #[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Debug, Hash)]
 pub struct Instant(Duration);
 
@@ -22,6 +66,8 @@ pub fn actually_monotonic() -> bool {
         true
     }
 
+    /// Returns `None` if `other` is later; `Instant::duration_since`
+    /// saturates that to zero rather than panicking.
     pub fn checked_sub_instant(&self, other: &Instant) -> Option<Duration> {
         self.0.checked_sub(other.0)
     }
@@ -40,6 +86,12 @@ pub fn now() -> SystemTime {
         panic!();
     }
 
+    /// Converts a Clock sysvar `unix_timestamp`. Timestamps before the
+    /// epoch are clamped to it.
+    pub fn from_unix_timestamp(timestamp: i64) -> SystemTime {
+        SystemTime(Duration::from_secs(timestamp.max(0) as u64))
+    }
+
     pub fn sub_time(&self, other: &SystemTime)
                     -> Result<Duration, Duration> {
         self.0.checked_sub(other.0).ok_or_else(|| other.0 - self.0)
@@ -0,0 +1,9 @@
+use super::*;
+
+#[test]
+fn checked_instant_subtraction_rejects_a_later_instant() {
+    let earlier = Instant::zero();
+    let later = earlier.checked_add_duration(&Duration::from_secs(5)).unwrap();
+    assert_eq!(later.checked_sub_instant(&earlier), Some(Duration::from_secs(5)));
+    assert_eq!(earlier.checked_sub_instant(&later), None);
+}
@@ -3,7 +3,6 @@
 
 #[cfg(not(target_arch = "bpf"))]
 use crate::sync::Once;
-#[cfg(not(target_arch = "bpf"))]
 use crate::sys;
 #[cfg(not(target_arch = "bpf"))]
 use crate::sys_common::thread_info;
@@ -45,6 +44,24 @@ pub fn cleanup() {
     });
 }
 
+// Runtime initialization for BPF, which has no `lang_start`. The program
+// entrypoint calls this before running any program code.
+#[cfg(target_arch = "bpf")]
+pub fn init() {
+    sys::log_cu_marker("program entry");
+}
+
+// Runtime cleanup for BPF, which has no `lang_start`. The program entrypoint
+// calls this on its successful-return path so that thread-local destructors
+// run and buffered output is logged.
+#[cfg(target_arch = "bpf")]
+pub fn cleanup() {
+    sys::log_cu_marker("program exit");
+    // SAFETY: the program is done, nothing uses its thread locals anymore.
+    unsafe { sys::thread_local_dtor::run_dtors() };
+    crate::io::cleanup();
+}
+
 // Prints to the "panic output", depending on the platform this may be:
 // - the standard error output
 // - some dedicated platform specific output
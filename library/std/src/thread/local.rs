@@ -513,13 +513,32 @@ pub unsafe fn get(&self, init: fn() -> T) -> Option<&'static T> {
             let value = unsafe {
                 match self.inner.get() {
                     Some(ref value) => value,
-                    None => self.inner.initialize(init),
+                    None => {
+                        // The only thread exits when the program completes,
+                        // at which point the value is dropped.
+                        #[cfg(target_arch = "bpf")]
+                        if crate::mem::needs_drop::<T>() {
+                            crate::sys::thread_local_dtor::register_dtor(
+                                self as *const _ as *mut u8,
+                                destroy_value::<T>,
+                            );
+                        }
+                        self.inner.initialize(init)
+                    }
                 }
             };
 
             Some(value)
         }
     }
+
+    #[cfg(target_arch = "bpf")]
+    unsafe extern "C" fn destroy_value<T>(ptr: *mut u8) {
+        // SAFETY: `ptr` was registered by `get` above and points to a
+        // `Key<T>`. Destructors only run once the program has completed, so
+        // no references to the value are in use anymore.
+        unsafe { drop((*(ptr as *mut Key<T>)).inner.take()) }
+    }
 }
 
 #[doc(hidden)]
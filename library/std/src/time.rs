@@ -263,7 +263,8 @@ pub fn now() -> Instant {
     ///
     /// # Panics
     ///
-    /// This function will panic if `earlier` is later than `self`.
+    /// This function will panic if `earlier` is later than `self`, except on
+    /// BPF, where it returns zero.
     ///
     /// # Examples
     ///
@@ -278,6 +279,11 @@ pub fn now() -> Instant {
     /// ```
     #[stable(feature = "time2", since = "1.8.0")]
     pub fn duration_since(&self, earlier: Instant) -> Duration {
+        // The BPF clock sysvar can appear to go backwards, which should not
+        // make `Instant` subtraction panic.
+        if cfg!(target_arch = "bpf") {
+            return self.saturating_duration_since(earlier);
+        }
         self.0.checked_sub_instant(&earlier.0).expect("supplied instant is later than self")
     }
 
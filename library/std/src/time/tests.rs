@@ -63,11 +63,20 @@ fn instant_math_is_associative() {
 
 #[test]
 #[should_panic]
+#[cfg(not(target_arch = "bpf"))]
 fn instant_duration_since_panic() {
     let a = Instant::now();
     (a - Duration::SECOND).duration_since(a);
 }
 
+#[test]
+#[cfg(target_arch = "bpf")]
+fn instant_duration_since_saturates() {
+    let a = Instant::now();
+    assert_eq!(a.duration_since(a + Duration::SECOND), Duration::ZERO);
+    assert_eq!(a - (a + Duration::SECOND), Duration::ZERO);
+}
+
 #[test]
 fn instant_checked_duration_since_nopanic() {
     let now = Instant::now();
@@ -3,9 +3,12 @@
 #[cfg(not(target_arch = "bpf"))]
 use std::env;
 use std::path::PathBuf;
+use std::time::Duration;
 
+#[cfg(not(target_arch = "bpf"))]
+use super::console::DEFAULT_SLOWEST_COUNT;
 use super::helpers::isatty;
-use super::options::{ColorConfig, Options, OutputFormat, RunIgnored};
+use super::options::{BenchFormat, ColorConfig, LogfileFormat, Options, OutputFormat, RunIgnored};
 use super::time::TestTimeOptions;
 
 #[derive(Debug)]
@@ -25,26 +28,60 @@ pub struct TestOpts {
     pub test_threads: Option<usize>,
     pub skip: Vec<String>,
     pub time_options: Option<TestTimeOptions>,
+    pub slowest: Option<usize>,
+    pub time_threshold: Option<Duration>,
+    pub capture_max_bytes: Option<usize>,
+    pub logfile_format: LogfileFormat,
+    pub no_capture_on_success: bool,
+    pub report_cu: bool,
+    pub list_failures_to: Option<PathBuf>,
+    pub test_cu_limit: Option<u64>,
+    pub tee_output: bool,
+    pub bpf_test_markers: bool,
+    pub junit_output: Option<PathBuf>,
+    pub run_id: Option<String>,
+    pub test_cu_budget: Option<u64>,
+    pub bench_format: BenchFormat,
+    pub list_output: Option<PathBuf>,
+    pub error_on_ignored: bool,
+    pub show_cu: bool,
     pub options: Options,
 }
 
 impl TestOpts {
     pub fn use_color(&self) -> bool {
+        self.resolve_color(isatty::stdout_isatty(), no_color_requested())
+    }
+
+    // Resolves the color configuration. A non-empty `NO_COLOR` environment
+    // variable (see https://no-color.org) disables automatic coloring, but an
+    // explicit `--color=always` still wins.
+    pub(crate) fn resolve_color(&self, stdout_isatty: bool, no_color: bool) -> bool {
         match self.color {
-            ColorConfig::AutoColor => !self.nocapture && isatty::stdout_isatty(),
+            ColorConfig::AutoColor => !self.nocapture && stdout_isatty && !no_color,
             ColorConfig::AlwaysColor => true,
             ColorConfig::NeverColor => false,
         }
     }
 }
 
+#[cfg(not(target_arch = "bpf"))]
+fn no_color_requested() -> bool {
+    env::var_os("NO_COLOR").map_or(false, |val| !val.is_empty())
+}
+
+#[cfg(target_arch = "bpf")]
+fn no_color_requested() -> bool {
+    false
+}
+
 /// Result of parsing the options.
 pub type OptRes = Result<TestOpts, String>;
 /// Result of parsing the option part.
 #[cfg(not(target_arch = "bpf"))]
 type OptPartRes<T> = Result<T, String>;
 
-fn optgroups() -> getopts::Options {
+pub(crate) fn optgroups() -> getopts::Options {
     let mut opts = getopts::Options::new();
     opts.optflag("", "include-ignored", "Run ignored and not ignored tests")
         .optflag("", "ignored", "Run only ignored tests")
@@ -55,6 +92,22 @@ fn optgroups() -> getopts::Options {
         .optflag("", "list", "List all tests and benchmarks")
         .optflag("h", "help", "Display this message")
         .optopt("", "logfile", "Write logs to the specified file", "PATH")
+        .optopt(
+            "",
+            "logfile-format",
+            "Configure the format of the logfile:
+            text = one line per test (default);
+            json = one JSON object per line and test",
+            "text|json",
+        )
+        .optopt(
+            "",
+            "bench-format",
+            "Configure the format of the benchmark results:
+            text = as part of the regular output (default);
+            csv  = a name,median_ns,deviation_ns table instead of the regular output",
+            "text|csv",
+        )
         .optflag(
             "",
             "nocapture",
@@ -65,7 +118,7 @@ fn optgroups() -> getopts::Options {
             "",
             "test-threads",
             "Number of threads used for running tests \
-             in parallel",
+             in parallel (0 uses the number of CPUs, at most 64)",
             "n_threads",
         )
         .optmulti(
@@ -98,8 +151,10 @@ fn optgroups() -> getopts::Options {
             pretty = Print verbose output;
             terse  = Display one character per test;
             json   = Output a json document;
-            junit  = Output a JUnit document",
-            "pretty|terse|json|junit",
+            json-summary = Output a single json object once all tests have run;
+            junit  = Output a JUnit document;
+            tap    = Output a TAP stream",
+            "pretty|terse|json|json-summary|junit|tap",
         )
         .optflag("", "show-output", "Show captured stdout of successful tests")
         .optopt(
@@ -127,6 +182,94 @@ fn optgroups() -> getopts::Options {
             Not available for --format=terse",
             "plain|colored",
         )
+        .optflagopt(
+            "",
+            "slowest",
+            "Show the N slowest tests at the end of the run (10 if N is
+            omitted). Implies --report-time",
+            "N",
+        )
+        .optopt(
+            "",
+            "time-threshold",
+            "Fail any test that runs for longer than the given number of
+            milliseconds. Implies --report-time",
+            "MILLIS",
+        )
+        .optopt(
+            "",
+            "capture-max-bytes",
+            "Truncate the captured output of each test to the given number of bytes",
+            "BYTES",
+        )
+        .optopt(
+            "",
+            "list-failures-to",
+            "Write the names of the failed tests to the given file, one per line",
+            "PATH",
+        )
+        .optopt("", "run-id", "Add the given id to the JSON suite events, to tell runs apart", "ID")
+        .optopt(
+            "",
+            "junit-output",
+            "Also write a JUnit report to the given file, whatever the --format",
+            "PATH",
+        )
+        .optopt(
+            "",
+            "list-output",
+            "Write the output of --list to the given file instead of stdout",
+            "PATH",
+        )
+        .optflag(
+            "",
+            "tee-output",
+            "Print the captured output of each test as soon as it finishes, while still \
+             capturing it for the report at the end of the run. Only with --format=pretty",
+        )
+        .optflag(
+            "",
+            "error-on-ignored",
+            "Fail the run if any test is ignored, and list the ignored tests. Tests ignored \
+             because they are for another target or need more than --test-cu-budget don't count",
+        )
+        .optflag(
+            "",
+            "no-capture-on-success",
+            "Discard the captured output of tests that pass instead of keeping it until the end \
+             of the run",
+        )
+        .optopt(
+            "",
+            "test-cu-limit",
+            "Fail any test that consumes more than the given number of compute units. \
+             Only measured on BPF",
+            "UNITS",
+        )
+        .optopt(
+            "",
+            "test-cu-budget",
+            "The compute budget the tests run with. Tests that need more compute units than \
+             that are ignored",
+            "UNITS",
+        )
+        .optflag(
+            "",
+            "bpf-test-markers",
+            "Log `TEST_START <name>` and `TEST_END <name> <result>` around each test, so that \
+             the program log can be split up by test. Only has an effect on BPF",
+        )
+        .optflag(
+            "",
+            "report-cu",
+            "Report the compute units consumed by the whole suite. Only measured on BPF.",
+        )
+        .optflag(
+            "",
+            "show-cu",
+            "Show the compute units consumed by each passing test in the pretty and terse \
+             output. Only measured on BPF.",
+        )
         .optflag(
             "",
             "ensure-time",
@@ -161,6 +304,9 @@ fn usage(binary: &str, options: &getopts::Options) {
 This can be overridden with the --nocapture flag or setting RUST_TEST_NOCAPTURE
 environment variable to a value other than "0". Logging is not captured by default.
 
+Without --format or --quiet, the output format is taken from the RUST_TEST_FORMAT
+environment variable (pretty if it is unset or not a valid format).
+
 Test Attributes:
 
     `#[test]`        - Indicates a function is a test to be run. This function
@@ -230,7 +376,23 @@ fn parse_opts_impl(matches: getopts::Matches) -> OptRes {
     // Unstable flags
     let force_run_in_process = unstable_optflag!(matches, allow_unstable, "force-run-in-process");
     let exclude_should_panic = unstable_optflag!(matches, allow_unstable, "exclude-should-panic");
-    let time_options = get_time_options(&matches, allow_unstable)?;
+    let slowest = get_slowest(&matches, allow_unstable)?;
+    let time_threshold = get_time_threshold(&matches, allow_unstable)?;
+    let capture_max_bytes = get_capture_max_bytes(&matches, allow_unstable)?;
+    let no_capture_on_success = unstable_optflag!(matches, allow_unstable, "no-capture-on-success");
+    let tee_output = unstable_optflag!(matches, allow_unstable, "tee-output");
+    let error_on_ignored = unstable_optflag!(matches, allow_unstable, "error-on-ignored");
+    let report_cu = unstable_optflag!(matches, allow_unstable, "report-cu");
+    let show_cu = unstable_optflag!(matches, allow_unstable, "show-cu");
+    let bpf_test_markers = unstable_optflag!(matches, allow_unstable, "bpf-test-markers");
+    let test_cu_limit = get_compute_units(&matches, allow_unstable, "test-cu-limit")?;
+    let test_cu_budget = get_compute_units(&matches, allow_unstable, "test-cu-budget")?;
+    let list_failures_to = get_list_failures_to(&matches, allow_unstable)?;
+    let junit_output = get_junit_output(&matches, allow_unstable)?;
+    let list_output = get_list_output(&matches, allow_unstable)?;
+    let run_id = get_run_id(&matches, allow_unstable)?;
+    let measure_time = slowest.is_some() || time_threshold.is_some();
+    let time_options = get_time_options(&matches, allow_unstable, measure_time)?;
 
     let include_ignored = matches.opt_present("include-ignored");
     let quiet = matches.opt_present("quiet");
@@ -242,12 +404,17 @@ fn parse_opts_impl(matches: getopts::Matches) -> OptRes {
     let run_tests = !bench_benchmarks || matches.opt_present("test");
 
     let logfile = get_log_file(&matches)?;
+    let logfile_format = get_logfile_format(&matches, allow_unstable)?;
+    let bench_format = get_bench_format(&matches, allow_unstable)?;
     let run_ignored = get_run_ignored(&matches, include_ignored)?;
     let filters = matches.free.clone();
     let nocapture = get_nocapture(&matches)?;
     let test_threads = get_test_threads(&matches)?;
     let color = get_color_config(&matches)?;
     let format = get_format(&matches, quiet, allow_unstable)?;
+    if tee_output && !matches!(format, OutputFormat::Pretty) {
+        return Err("The \"tee-output\" flag is only supported with --format=pretty".into());
+    }
 
     let options = Options::new().display_output(matches.opt_present("show-output"));
 
@@ -267,6 +434,23 @@ fn parse_opts_impl(matches: getopts::Matches) -> OptRes {
         test_threads,
         skip,
         time_options,
+        slowest,
+        time_threshold,
+        capture_max_bytes,
+        logfile_format,
+        no_capture_on_success,
+        report_cu,
+        list_failures_to,
+        test_cu_limit,
+        tee_output,
+        bpf_test_markers,
+        junit_output,
+        run_id,
+        test_cu_budget,
+        bench_format,
+        list_output,
+        error_on_ignored,
+        show_cu,
         options,
     };
 
@@ -291,6 +475,23 @@ fn parse_opts_impl(_matches: getopts::Matches) -> OptRes {
         test_threads: Some(1),
         skip: Vec::new(),
         time_options: None,
+        slowest: None,
+        time_threshold: None,
+        capture_max_bytes: None,
+        logfile_format: LogfileFormat::Text,
+        no_capture_on_success: false,
+        report_cu: false,
+        list_failures_to: None,
+        test_cu_limit: None,
+        tee_output: false,
+        bpf_test_markers: false,
+        junit_output: None,
+        run_id: None,
+        test_cu_budget: None,
+        bench_format: BenchFormat::Text,
+        list_output: None,
+        error_on_ignored: false,
+        show_cu: false,
         options: Options::new(),
     };
 
@@ -313,6 +514,7 @@ fn is_nightly() -> bool {
 fn get_time_options(
     matches: &getopts::Matches,
     allow_unstable: bool,
+    measure_time: bool,
 ) -> OptPartRes<Option<TestTimeOptions>> {
     let report_time = unstable_optflag!(matches, allow_unstable, "report-time");
     let colored_opt_str = matches.opt_str("report-time");
@@ -321,7 +523,8 @@ fn get_time_options(
 
     // If `ensure-test-time` option is provided, time output is enforced,
     // so user won't be confused if any of tests will silently fail.
-    let options = if report_time || ensure_test_time {
+    // Options relying on measured test durations (e.g. `slowest`) enable it as well.
+    let options = if report_time || ensure_test_time || measure_time {
         if ensure_test_time && !report_time {
             report_time_colored = true;
         }
@@ -333,15 +536,116 @@ fn get_time_options(
     Ok(options)
 }
 
+#[cfg(not(target_arch = "bpf"))]
+fn get_slowest(matches: &getopts::Matches, allow_unstable: bool) -> OptPartRes<Option<usize>> {
+    if !unstable_optflag!(matches, allow_unstable, "slowest") {
+        return Ok(None);
+    }
+
+    let slowest = match matches.opt_str("slowest") {
+        Some(n_str) => match n_str.parse::<usize>() {
+            Ok(n) => n,
+            Err(e) => {
+                return Err(format!("argument for --slowest must be a number (error: {})", e));
+            }
+        },
+        None => DEFAULT_SLOWEST_COUNT,
+    };
+
+    Ok(Some(slowest))
+}
+
+#[cfg(not(target_arch = "bpf"))]
+fn get_time_threshold(
+    matches: &getopts::Matches,
+    allow_unstable: bool,
+) -> OptPartRes<Option<Duration>> {
+    let time_threshold = match matches.opt_str("time-threshold") {
+        Some(_) if !allow_unstable => {
+            return Err("The \"time-threshold\" option is only accepted on the nightly compiler \
+                        with -Z unstable-options"
+                .into());
+        }
+        Some(ms_str) => match ms_str.parse::<u64>() {
+            Ok(ms) => Some(Duration::from_millis(ms)),
+            Err(e) => {
+                return Err(format!(
+                    "argument for --time-threshold must be a number of milliseconds \
+                     (error: {})",
+                    e
+                ));
+            }
+        },
+        None => None,
+    };
+
+    Ok(time_threshold)
+}
+
+#[cfg(not(target_arch = "bpf"))]
+fn get_compute_units(
+    matches: &getopts::Matches,
+    allow_unstable: bool,
+    name: &str,
+) -> OptPartRes<Option<u64>> {
+    let compute_units = match matches.opt_str(name) {
+        Some(_) if !allow_unstable => {
+            return Err(format!(
+                "The \"{}\" option is only accepted on the nightly compiler \
+                 with -Z unstable-options",
+                name
+            ));
+        }
+        Some(n_str) => match n_str.parse::<u64>() {
+            Ok(n) => Some(n),
+            Err(e) => {
+                return Err(format!(
+                    "argument for --{} must be a number of compute units (error: {})",
+                    name, e
+                ));
+            }
+        },
+        None => None,
+    };
+
+    Ok(compute_units)
+}
+
+#[cfg(not(target_arch = "bpf"))]
+fn get_capture_max_bytes(
+    matches: &getopts::Matches,
+    allow_unstable: bool,
+) -> OptPartRes<Option<usize>> {
+    let capture_max_bytes = match matches.opt_str("capture-max-bytes") {
+        Some(_) if !allow_unstable => {
+            return Err("The \"capture-max-bytes\" option is only accepted on the nightly \
+                        compiler with -Z unstable-options"
+                .into());
+        }
+        Some(n_str) => match n_str.parse::<usize>() {
+            Ok(n) => Some(n),
+            Err(e) => {
+                return Err(format!(
+                    "argument for --capture-max-bytes must be a number of bytes \
+                     (error: {})",
+                    e
+                ));
+            }
+        },
+        None => None,
+    };
+
+    Ok(capture_max_bytes)
+}
+
 #[cfg(not(target_arch = "bpf"))]
 fn get_test_threads(matches: &getopts::Matches) -> OptPartRes<Option<usize>> {
     let test_threads = match matches.opt_str("test-threads") {
         Some(n_str) => match n_str.parse::<usize>() {
-            Ok(0) => return Err("argument for --test-threads must not be 0".to_string()),
             Ok(n) => Some(n),
             Err(e) => {
                 return Err(format!(
-                    "argument for --test-threads must be a number > 0 \
+                    "argument for --test-threads must be a number \
                      (error: {})",
                     e
                 ));
@@ -359,26 +663,74 @@ fn get_format(
     quiet: bool,
     allow_unstable: bool,
 ) -> OptPartRes<OutputFormat> {
-    let format = match matches.opt_str("format").as_deref() {
-        None if quiet => OutputFormat::Terse,
-        Some("pretty") | None => OutputFormat::Pretty,
-        Some("terse") => OutputFormat::Terse,
-        Some("json") => {
+    let format = matches.opt_str("format");
+    let env_format = env::var("RUST_TEST_FORMAT").ok();
+    resolve_format(format.as_deref(), quiet, env_format.as_deref(), allow_unstable)
+}
+
+// Resolves the output format from `--format`, `--quiet` and the value of `RUST_TEST_FORMAT`.
+// Without an explicit `--format` or `--quiet`, the environment variable is used. An unusable
+// value there is reported but never fatal, so a stale variable cannot break every test
+// invocation.
+#[cfg(not(target_arch = "bpf"))]
+pub(crate) fn resolve_format(
+    format: Option<&str>,
+    quiet: bool,
+    env_format: Option<&str>,
+    allow_unstable: bool,
+) -> OptPartRes<OutputFormat> {
+    if let Some(format) = format {
+        return parse_format(format, allow_unstable);
+    }
+    if quiet {
+        return Ok(OutputFormat::Terse);
+    }
+
+    if let Some(format) = env_format {
+        match parse_format(format, allow_unstable) {
+            Ok(format) => return Ok(format),
+            Err(e) => eprintln!("warning: ignoring RUST_TEST_FORMAT: {}", e),
+        }
+    }
+
+    Ok(OutputFormat::Pretty)
+}
+
+#[cfg(not(target_arch = "bpf"))]
+fn parse_format(format: &str, allow_unstable: bool) -> OptPartRes<OutputFormat> {
+    let format = match format {
+        "pretty" => OutputFormat::Pretty,
+        "terse" => OutputFormat::Terse,
+        "json" => {
             if !allow_unstable {
                 return Err("The \"json\" format is only accepted on the nightly compiler".into());
             }
             OutputFormat::Json
         }
-        Some("junit") => {
+        "json-summary" => {
+            if !allow_unstable {
+                return Err(
+                    "The \"json-summary\" format is only accepted on the nightly compiler".into()
+                );
+            }
+            OutputFormat::JsonSummary
+        }
+        "junit" => {
             if !allow_unstable {
                 return Err("The \"junit\" format is only accepted on the nightly compiler".into());
             }
             OutputFormat::Junit
         }
-        Some(v) => {
+        "tap" => {
+            if !allow_unstable {
+                return Err("The \"tap\" format is only accepted on the nightly compiler".into());
+            }
+            OutputFormat::Tap
+        }
+        v => {
             return Err(format!(
-                "argument for --format must be pretty, terse, json or junit (was \
-                 {})",
+                "argument for --format must be pretty, terse, json, json-summary, junit or \
+                 tap (was {})",
                 v
             ));
         }
@@ -387,6 +739,48 @@ fn get_format(
     Ok(format)
 }
 
+#[cfg(not(target_arch = "bpf"))]
+fn get_bench_format(matches: &getopts::Matches, allow_unstable: bool) -> OptPartRes<BenchFormat> {
+    let format = match matches.opt_str("bench-format").as_deref() {
+        Some("text") | None => BenchFormat::Text,
+        Some("csv") => {
+            if !allow_unstable {
+                return Err(
+                    "The \"csv\" bench format is only accepted on the nightly compiler".into()
+                );
+            }
+            BenchFormat::Csv
+        }
+        Some(v) => {
+            return Err(format!("argument for --bench-format must be text or csv (was {})", v));
+        }
+    };
+
+    Ok(format)
+}
+
+#[cfg(not(target_arch = "bpf"))]
+fn get_logfile_format(
+    matches: &getopts::Matches,
+    allow_unstable: bool,
+) -> OptPartRes<LogfileFormat> {
+    let format = match matches.opt_str("logfile-format").as_deref() {
+        Some("text") | None => LogfileFormat::Text,
+        Some("json") => {
+            if !allow_unstable {
+                return Err("The \"json\" logfile format is only accepted on the nightly compiler"
+                    .into());
+            }
+            LogfileFormat::Json
+        }
+        Some(v) => {
+            return Err(format!("argument for --logfile-format must be text or json (was {})", v));
+        }
+    };
+
+    Ok(format)
+}
+
 #[cfg(not(target_arch = "bpf"))]
 fn get_color_config(matches: &getopts::Matches) -> OptPartRes<ColorConfig> {
     let color = match matches.opt_str("color").as_deref() {
@@ -455,6 +849,63 @@ fn get_allow_unstable(matches: &getopts::Matches) -> OptPartRes<bool> {
     Ok(allow_unstable)
 }
 
+#[cfg(not(target_arch = "bpf"))]
+fn get_list_failures_to(
+    matches: &getopts::Matches,
+    allow_unstable: bool,
+) -> OptPartRes<Option<PathBuf>> {
+    let path = matches.opt_str("list-failures-to");
+    if path.is_some() && !allow_unstable {
+        return Err("The \"list-failures-to\" option is only accepted on the nightly compiler \
+                    with -Z unstable-options"
+            .into());
+    }
+
+    Ok(path.map(PathBuf::from))
+}
+
+#[cfg(not(target_arch = "bpf"))]
+fn get_run_id(matches: &getopts::Matches, allow_unstable: bool) -> OptPartRes<Option<String>> {
+    let run_id = matches.opt_str("run-id");
+    if run_id.is_some() && !allow_unstable {
+        return Err("The \"run-id\" option is only accepted on the nightly compiler \
+                    with -Z unstable-options"
+            .into());
+    }
+
+    Ok(run_id)
+}
+
+#[cfg(not(target_arch = "bpf"))]
+fn get_junit_output(
+    matches: &getopts::Matches,
+    allow_unstable: bool,
+) -> OptPartRes<Option<PathBuf>> {
+    let path = matches.opt_str("junit-output");
+    if path.is_some() && !allow_unstable {
+        return Err("The \"junit-output\" option is only accepted on the nightly compiler \
+                    with -Z unstable-options"
+            .into());
+    }
+
+    Ok(path.map(PathBuf::from))
+}
+
+#[cfg(not(target_arch = "bpf"))]
+fn get_list_output(
+    matches: &getopts::Matches,
+    allow_unstable: bool,
+) -> OptPartRes<Option<PathBuf>> {
+    let path = matches.opt_str("list-output");
+    if path.is_some() && !allow_unstable {
+        return Err("The \"list-output\" option is only accepted on the nightly compiler \
+                    with -Z unstable-options"
+            .into());
+    }
+
+    Ok(path.map(PathBuf::from))
+}
+
 #[cfg(not(target_arch = "bpf"))]
 fn get_log_file(matches: &getopts::Matches) -> OptPartRes<Option<PathBuf>> {
     let logfile = matches.opt_str("logfile").map(|s| PathBuf::from(&s));
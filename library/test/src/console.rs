@@ -1,18 +1,26 @@
 //! Module providing interface for running tests in the console.
 
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
 use std::fs::File;
 use std::io;
 use std::io::prelude::Write;
-use std::time::Instant;
+use std::path::Path;
+#[cfg(not(target_arch = "bpf"))]
+use std::panic::{self, UnwindSafe};
+use std::time::{Duration, Instant};
 
 use super::{
     bench::fmt_bench_samples,
     cli::TestOpts,
     event::{CompletedTest, TestEvent},
-    filter_tests,
-    formatters::{JsonFormatter, JunitFormatter, OutputFormatter, PrettyFormatter, TerseFormatter},
-    helpers::{concurrency::get_concurrency, metrics::MetricMap},
-    options::{Options, OutputFormat},
+    filter_ignore_reason, filter_tests,
+    formatters::{
+        json_log_line, BenchCsvFormatter, JsonFormatter, JsonSummaryFormatter, JunitFormatter,
+        OutputFormatter, PrettyFormatter, TapFormatter, TeeFormatter, TerseFormatter,
+    },
+    helpers::{concurrency::resolve_test_threads, metrics::MetricMap, program_log},
+    options::{BenchFormat, LogfileFormat, Options, OutputFormat},
     run_tests,
     test_result::TestResult,
     time::{TestExecTime, TestSuiteExecTime},
@@ -41,6 +49,60 @@ fn flush(&mut self) -> io::Result<()> {
     }
 }
 
+// Acquires the terminal produced by `term_factory`, falling back to raw stdout
+// when no terminal is available or the factory panics (the `term` crate is known
+// to misbehave in some minimal/sandboxed environments).
+#[cfg(not(target_arch = "bpf"))]
+pub(crate) fn output_location_from<F>(term_factory: F) -> OutputLocation<io::Stdout>
+where
+    F: FnOnce() -> Option<Box<term::StdoutTerminal>> + UnwindSafe,
+{
+    match panic::catch_unwind(term_factory) {
+        Ok(Some(t)) => OutputLocation::Pretty(t),
+        Ok(None) | Err(_) => OutputLocation::Raw(io::stdout()),
+    }
+}
+
+#[cfg(not(target_arch = "bpf"))]
+fn stdout_output_location() -> OutputLocation<io::Stdout> {
+    output_location_from(term::stdout)
+}
+
+#[cfg(target_arch = "bpf")]
+fn stdout_output_location() -> OutputLocation<io::Stdout> {
+    OutputLocation::Raw(io::stdout())
+}
+
+/// Number of tests reported by `--slowest` when no count is given.
+pub const DEFAULT_SLOWEST_COUNT: usize = 10;
+
+/// Keeps track of the `limit` slowest tests seen so far.
+pub struct SlowestTests {
+    limit: usize,
+    // Min-heap, so the fastest of the currently tracked tests is evicted first.
+    heap: BinaryHeap<Reverse<(Duration, String)>>,
+}
+
+impl SlowestTests {
+    pub fn new(limit: usize) -> SlowestTests {
+        SlowestTests { limit, heap: BinaryHeap::with_capacity(limit + 1) }
+    }
+
+    pub fn record(&mut self, test: &TestDesc, exec_time: &TestExecTime) {
+        self.heap.push(Reverse((exec_time.0, test.name.as_slice().to_owned())));
+        if self.heap.len() > self.limit {
+            self.heap.pop();
+        }
+    }
+
+    /// Returns the tracked tests, slowest first.
+    pub fn sorted(&self) -> Vec<(&str, TestExecTime)> {
+        let mut tests: Vec<_> = self.heap.iter().map(|Reverse(entry)| entry).collect();
+        tests.sort_by(|a, b| b.cmp(a));
+        tests.into_iter().map(|(time, name)| (name.as_str(), TestExecTime(*time))).collect()
+    }
+}
+
 pub struct ConsoleTestState {
     pub log_out: Option<File>,
     pub total: usize,
@@ -55,6 +117,23 @@ pub struct ConsoleTestState {
     pub failures: Vec<(TestDesc, Vec<u8>)>,
     pub not_failures: Vec<(TestDesc, Vec<u8>)>,
     pub time_failures: Vec<(TestDesc, Vec<u8>)>,
+    pub slowest: Option<SlowestTests>,
+    pub time_threshold: Option<Duration>,
+    pub capture_max_bytes: Option<usize>,
+    pub no_capture_on_success: bool,
+    pub tee_output: bool,
+    // Where the `--bpf-test-markers` are logged to.
+    pub test_markers: Option<fn(&str)>,
+    pub report_cu: bool,
+    pub compute_units: Option<u64>,
+    pub show_cu: bool,
+    // Compute units consumed by the test whose result is being written, with `--show-cu`.
+    pub test_compute_units: Option<u64>,
+    pub test_cu_limit: Option<u64>,
+    pub logfile_format: LogfileFormat,
+    pub ignored_tests: Vec<TestDesc>,
+    // Set when `--error-on-ignored` found ignored tests, which fails the run.
+    pub forbidden_ignores: bool,
     pub options: Options,
 }
 
@@ -79,10 +158,29 @@ pub fn new(opts: &TestOpts) -> io::Result<ConsoleTestState> {
             failures: Vec::new(),
             not_failures: Vec::new(),
             time_failures: Vec::new(),
+            slowest: opts.slowest.map(SlowestTests::new),
+            time_threshold: opts.time_threshold,
+            capture_max_bytes: opts.capture_max_bytes,
+            no_capture_on_success: opts.no_capture_on_success,
+            tee_output: opts.tee_output,
+            test_markers: opts.bpf_test_markers.then(|| program_log::log as fn(&str)),
+            report_cu: opts.report_cu,
+            compute_units: None,
+            show_cu: opts.show_cu,
+            test_compute_units: None,
+            test_cu_limit: opts.test_cu_limit,
+            logfile_format: opts.logfile_format,
+            ignored_tests: Vec::new(),
+            forbidden_ignores: false,
             options: opts.options,
         })
     }
 
+    // Whether the run passed: no test failed, and none was ignored against `--error-on-ignored`.
+    pub fn succeeded(&self) -> bool {
+        self.failed == 0 && !self.forbidden_ignores
+    }
+
     pub fn write_log<F, S>(&mut self, msg: F) -> io::Result<()>
     where
         S: AsRef<str>,
@@ -104,6 +202,9 @@ pub fn write_log_result(
         result: &TestResult,
         exec_time: Option<&TestExecTime>,
     ) -> io::Result<()> {
+        if self.logfile_format == LogfileFormat::Json {
+            return self.write_log(|| json_log_line(test, result, exec_time) + "\n");
+        }
         self.write_log(|| {
             format!(
                 "{} {}",
@@ -130,13 +231,21 @@ fn current_test_count(&self) -> usize {
     }
 }
 
-// List the tests to console, and optionally to logfile. Filters are honored.
+// List the tests to console, or to the `--list-output` file, and optionally to logfile. Filters
+// are honored.
 pub fn list_tests_console(opts: &TestOpts, tests: Vec<TestDescAndFn>) -> io::Result<()> {
-    let mut output = match term::stdout() {
-        None => OutputLocation::Raw(io::stdout()),
-        Some(t) => OutputLocation::Pretty(t),
-    };
+    match opts.list_output {
+        // Created up front, so that an unusable path fails before any test is looked at.
+        Some(ref path) => list_tests(opts, tests, &mut OutputLocation::Raw(File::create(path)?)),
+        None => list_tests(opts, tests, &mut stdout_output_location()),
+    }
+}
 
+pub(crate) fn list_tests<T: Write>(
+    opts: &TestOpts,
+    tests: Vec<TestDescAndFn>,
+    output: &mut OutputLocation<T>,
+) -> io::Result<()> {
     let quiet = opts.format == OutputFormat::Terse;
     let mut st = ConsoleTestState::new(opts)?;
 
@@ -184,13 +293,20 @@ fn plural(count: u32, s: &str) -> String {
 // Updates `ConsoleTestState` depending on result of the test execution.
 fn handle_test_result(st: &mut ConsoleTestState, completed_test: CompletedTest) {
     let test = completed_test.desc;
-    let stdout = completed_test.stdout;
+    let mut stdout = completed_test.stdout;
     match completed_test.result {
         TestResult::TrOk => {
             st.passed += 1;
+            if st.no_capture_on_success {
+                // Nobody is going to look at it, so don't hold on to it until the end of the run.
+                stdout = Vec::new();
+            }
             st.not_failures.push((test, stdout));
         }
-        TestResult::TrIgnored => st.ignored += 1,
+        TestResult::TrIgnored => {
+            st.ignored += 1;
+            st.ignored_tests.push(test);
+        }
         TestResult::TrAllowedFail => st.allowed_fail += 1,
         TestResult::TrBench(bs) => {
             st.metrics.insert_metric(
@@ -206,7 +322,6 @@ fn handle_test_result(st: &mut ConsoleTestState, completed_test: CompletedTest)
         }
         TestResult::TrFailedMsg(msg) => {
             st.failed += 1;
-            let mut stdout = stdout;
             stdout.extend_from_slice(format!("note: {}", msg).as_bytes());
             st.failures.push((test, stdout));
         }
@@ -217,9 +332,53 @@ fn handle_test_result(st: &mut ConsoleTestState, completed_test: CompletedTest)
     }
 }
 
+pub(crate) const OUTPUT_TRUNCATED_MARKER: &str = "\n... (output truncated)\n";
+
+// Caps the captured output of a single test at `max_bytes`, as set with `--capture-max-bytes`.
+// Returns whether anything was cut off.
+fn truncate_captured_output(stdout: &mut Vec<u8>, max_bytes: usize) -> bool {
+    if stdout.len() <= max_bytes {
+        return false;
+    }
+    stdout.truncate(max_bytes);
+    stdout.extend_from_slice(OUTPUT_TRUNCATED_MARKER.as_bytes());
+    true
+}
+
+// Turns a passing test that ran for longer than `--time-threshold` into a time failure.
+fn apply_time_threshold(st: &ConsoleTestState, completed_test: &mut CompletedTest) {
+    if let (Some(threshold), Some(exec_time)) = (st.time_threshold, &completed_test.exec_time) {
+        if completed_test.result == TestResult::TrOk && exec_time.0 > threshold {
+            completed_test.result = TestResult::TrTimedFail;
+        }
+    }
+}
+
+// Turns a passing test that consumed more than `--test-cu-limit` compute units into a time
+// failure, as running out of compute units is the BPF equivalent of a timeout.
+fn apply_test_cu_limit(st: &ConsoleTestState, completed_test: &mut CompletedTest) {
+    if let (Some(limit), Some(compute_units)) = (st.test_cu_limit, completed_test.compute_units) {
+        if completed_test.result == TestResult::TrOk && compute_units > limit {
+            completed_test.result = TestResult::TrTimedFail;
+        }
+    }
+}
+
+// Names `result` in the `TEST_END` marker of `--bpf-test-markers`.
+fn marker_result(result: &TestResult) -> &'static str {
+    match *result {
+        TestResult::TrOk => "ok",
+        TestResult::TrFailed | TestResult::TrFailedMsg(_) => "failed",
+        TestResult::TrIgnored => "ignored",
+        TestResult::TrAllowedFail => "allowed_fail",
+        TestResult::TrBench(_) => "bench",
+        TestResult::TrTimedFail => "timed_out",
+    }
+}
+
 // Handler for events that occur during test execution.
 // It is provided as a callback to the `run_tests` function.
-fn on_test_event(
+pub(crate) fn on_test_event(
     event: &TestEvent,
     st: &mut ConsoleTestState,
     out: &mut dyn OutputFormatter,
@@ -232,16 +391,49 @@ fn on_test_event(
         TestEvent::TeFilteredOut(filtered_out) => {
             st.filtered_out = filtered_out;
         }
-        TestEvent::TeWait(ref test) => out.write_test_start(test)?,
+        TestEvent::TeWait(ref test) => {
+            if let Some(log) = st.test_markers {
+                log(&format!("TEST_START {}", test.name));
+            }
+            out.write_test_start(test)?
+        }
         TestEvent::TeTimeout(ref test) => out.write_timeout(test)?,
-        TestEvent::TeResult(completed_test) => {
+        TestEvent::TeResult(mut completed_test) => {
+            apply_time_threshold(st, &mut completed_test);
+            apply_test_cu_limit(st, &mut completed_test);
+            if let Some(max_bytes) = st.capture_max_bytes {
+                if truncate_captured_output(&mut completed_test.stdout, max_bytes) {
+                    out.write_warning(&format!(
+                        "output of {} was truncated to {} bytes",
+                        completed_test.desc.name, max_bytes
+                    ))?;
+                }
+            }
+
             let test = &completed_test.desc;
             let result = &completed_test.result;
             let exec_time = &completed_test.exec_time;
             let stdout = &completed_test.stdout;
 
+            if let (Some(slowest), Some(exec_time)) = (st.slowest.as_mut(), exec_time) {
+                slowest.record(test, exec_time);
+            }
+            if let (true, Some(compute_units)) = (st.report_cu, completed_test.compute_units) {
+                *st.compute_units.get_or_insert(0) += compute_units;
+            }
+
+            // Only this thread writes to `out`, so the output of tests running in parallel
+            // cannot interleave.
+            if st.tee_output && !stdout.is_empty() {
+                out.write_tee_output(test, stdout)?;
+            }
+
+            st.test_compute_units = completed_test.compute_units.filter(|_| st.show_cu);
             st.write_log_result(test, result, exec_time.as_ref())?;
             out.write_result(test, result, exec_time.as_ref(), &*stdout, st)?;
+            if let Some(log) = st.test_markers {
+                log(&format!("TEST_END {} {}", test.name, marker_result(result)));
+            }
             handle_test_result(st, completed_test);
         }
     }
@@ -252,23 +444,15 @@ fn on_test_event(
 /// A simple console test runner.
 /// Runs provided tests reporting process and results to the stdout.
 pub fn run_tests_console(opts: &TestOpts, tests: Vec<TestDescAndFn>) -> io::Result<bool> {
-    #[cfg(not(target_arch = "bpf"))]
-    let output = match term::stdout() {
-        None => OutputLocation::Raw(io::stdout()),
-        Some(t) => OutputLocation::Pretty(t),
-    };
-    #[cfg(target_arch = "bpf")]
-    let output = OutputLocation::Raw(io::stdout());
+    let output = stdout_output_location();
 
-    let max_name_len = tests
-        .iter()
-        .max_by_key(|t| len_if_padded(*t))
-        .map(|t| t.desc.name.as_slice().len())
-        .unwrap_or(0);
+    let max_name_len = max_name_len(&tests);
 
-    let is_multithreaded = opts.test_threads.unwrap_or_else(get_concurrency) > 1;
+    let is_multithreaded = resolve_test_threads(opts.test_threads) > 1;
 
     let mut out: Box<dyn OutputFormatter> = match opts.format {
+        // The CSV table takes the place of the regular output, whatever its format.
+        _ if opts.bench_format == BenchFormat::Csv => Box::new(BenchCsvFormatter::new(output)),
         OutputFormat::Pretty => Box::new(PrettyFormatter::new(
             output,
             opts.use_color(),
@@ -279,9 +463,15 @@ pub fn run_tests_console(opts: &TestOpts, tests: Vec<TestDescAndFn>) -> io::Resu
         OutputFormat::Terse => {
             Box::new(TerseFormatter::new(output, opts.use_color(), max_name_len, is_multithreaded))
         }
-        OutputFormat::Json => Box::new(JsonFormatter::new(output)),
+        OutputFormat::Json => Box::new(JsonFormatter::new(output).with_run_id(opts.run_id.clone())),
+        OutputFormat::JsonSummary => Box::new(JsonSummaryFormatter::new(output)),
         OutputFormat::Junit => Box::new(JunitFormatter::new(output)),
+        OutputFormat::Tap => Box::new(TapFormatter::new(output)),
     };
+    if let Some(ref path) = opts.junit_output {
+        let junit = JunitFormatter::new(OutputLocation::Raw(File::create(path)?));
+        out = Box::new(TeeFormatter::new(out, Box::new(junit)));
+    }
     let mut st = ConsoleTestState::new(opts)?;
 
     // Prevent the usage of `Instant` in some cases:
@@ -295,7 +485,57 @@ pub fn run_tests_console(opts: &TestOpts, tests: Vec<TestDescAndFn>) -> io::Resu
 
     assert!(st.current_test_count() == st.total);
 
-    out.write_run_finish(&st)
+    if let Some(ref path) = opts.list_failures_to {
+        write_failure_list(path, &st)?;
+    }
+
+    report_forbidden_ignores(opts, &mut st, &mut *out)?;
+    let success = out.write_run_finish(&st)?;
+    // Not every formatter flushes after its summary, and nothing else is left to push it out.
+    io::stdout().flush()?;
+    Ok(success)
+}
+
+// With `--error-on-ignored`, warns about the ignored tests and, if there are any, marks the run
+// as failed so that the summary reports it.
+// Tests that `filter_tests` ignored for being meant for another target or over the CU budget are
+// not held against the run.
+pub(crate) fn report_forbidden_ignores(
+    opts: &TestOpts,
+    st: &mut ConsoleTestState,
+    out: &mut dyn OutputFormatter,
+) -> io::Result<()> {
+    if !opts.error_on_ignored {
+        return Ok(());
+    }
+    let names: Vec<_> = st
+        .ignored_tests
+        .iter()
+        .filter(|desc| filter_ignore_reason(opts, desc).is_none())
+        .map(|desc| desc.name.as_slice())
+        .collect();
+    if names.is_empty() {
+        return Ok(());
+    }
+    out.write_warning(&format!("ignored tests are not allowed: {}", names.join(", ")))?;
+    st.forbidden_ignores = true;
+    Ok(())
+}
+
+// Writes the names of the failed tests to `path`, one per line, as requested with
+// `--list-failures-to`. The file is created even if nothing failed.
+pub(crate) fn write_failure_list(path: &Path, st: &ConsoleTestState) -> io::Result<()> {
+    let mut file = File::create(path)?;
+    for (test, _) in st.failures.iter().chain(&st.time_failures) {
+        writeln!(file, "{}", test.name)?;
+    }
+    Ok(())
+}
+
+// Returns the column width that names are padded to: the length of the longest name among the
+// tests that pad their names (benchmarks), or 0 if none do.
+pub(crate) fn max_name_len(tests: &[TestDescAndFn]) -> usize {
+    tests.iter().map(len_if_padded).max().unwrap_or(0)
 }
 
 // Calculates padding for given test description.
@@ -0,0 +1,70 @@
+use std::io::{self, prelude::Write};
+
+use super::OutputFormatter;
+use crate::{
+    console::{ConsoleTestState, OutputLocation},
+    test_result::TestResult,
+    time,
+    types::TestDesc,
+};
+
+// Writes one `name,median_ns,deviation_ns` row per benchmark, for `--bench-format csv`. Everything
+// else is left out so that the output can be loaded as is.
+pub(crate) struct BenchCsvFormatter<T> {
+    out: OutputLocation<T>,
+}
+
+impl<T: Write> BenchCsvFormatter<T> {
+    pub fn new(out: OutputLocation<T>) -> Self {
+        Self { out }
+    }
+
+    #[cfg(test)]
+    pub fn output_location(&self) -> &OutputLocation<T> {
+        &self.out
+    }
+}
+
+// Quotes `field` if it contains a character with a meaning in CSV.
+fn csv_field(field: &str) -> String {
+    if field.contains(&[',', '"', '\n', '\r'][..]) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+impl<T: Write> OutputFormatter for BenchCsvFormatter<T> {
+    fn write_run_start(&mut self, _test_count: usize) -> io::Result<()> {
+        self.out.write_all(b"name,median_ns,deviation_ns\n")
+    }
+
+    fn write_test_start(&mut self, _desc: &TestDesc) -> io::Result<()> {
+        Ok(())
+    }
+
+    fn write_timeout(&mut self, _desc: &TestDesc) -> io::Result<()> {
+        Ok(())
+    }
+
+    fn write_result(
+        &mut self,
+        desc: &TestDesc,
+        result: &TestResult,
+        _exec_time: Option<&time::TestExecTime>,
+        _stdout: &[u8],
+        _state: &ConsoleTestState,
+    ) -> io::Result<()> {
+        if let TestResult::TrBench(ref bs) = *result {
+            let median = bs.ns_iter_summ.median as usize;
+            let deviation = (bs.ns_iter_summ.max - bs.ns_iter_summ.min) as usize;
+            writeln!(self.out, "{},{},{}", csv_field(desc.name.as_slice()), median, deviation)?;
+        }
+        Ok(())
+    }
+
+    fn write_run_finish(&mut self, state: &ConsoleTestState) -> io::Result<bool> {
+        self.out.flush()?;
+        Ok(state.succeeded())
+    }
+}
@@ -8,13 +8,40 @@
     types::TestDesc,
 };
 
+/// Version of the event format, reported in the suite `started` event. Only bumped for changes
+/// that break existing consumers, such as renamed or removed fields.
+pub(crate) const JSON_SCHEMA_VERSION: u32 = 1;
+
+// Writes one JSON object per line. With a single test thread, each test is started and
+// reported before the next one is launched, so events follow the (sorted) test order.
+// With more threads, results are reported in the order the tests complete.
 pub(crate) struct JsonFormatter<T> {
     out: OutputLocation<T>,
+    run_id: Option<String>,
 }
 
 impl<T: Write> JsonFormatter<T> {
     pub fn new(out: OutputLocation<T>) -> Self {
-        Self { out }
+        Self { out, run_id: None }
+    }
+
+    /// Adds `run_id` to the suite events, as requested with `--run-id`.
+    pub fn with_run_id(mut self, run_id: Option<String>) -> Self {
+        self.run_id = run_id;
+        self
+    }
+
+    // Renders the `run_id` field of the suite events, if there is one.
+    fn run_id_field(&self) -> String {
+        match self.run_id {
+            Some(ref run_id) => format!(r#", "run_id": "{}""#, EscapedString(run_id)),
+            None => String::new(),
+        }
+    }
+
+    #[cfg(test)]
+    pub fn output_location(&self) -> &OutputLocation<T> {
+        &self.out
     }
 
     fn writeln_message(&mut self, s: &str) -> io::Result<()> {
@@ -62,8 +89,10 @@ fn write_event(
 impl<T: Write> OutputFormatter for JsonFormatter<T> {
     fn write_run_start(&mut self, test_count: usize) -> io::Result<()> {
         self.writeln_message(&*format!(
-            r#"{{ "type": "suite", "event": "started", "test_count": {} }}"#,
-            test_count
+            r#"{{ "type": "suite", "event": "started", "schema_version": {}, "test_count": {}{} }}"#,
+            JSON_SCHEMA_VERSION,
+            test_count,
+            self.run_id_field()
         ))
     }
 
@@ -94,7 +123,15 @@ fn write_result(
             }
 
             TestResult::TrFailed => {
-                self.write_event("test", desc.name.as_slice(), "failed", exec_time, stdout, None)
+                let location = stdout.as_deref().and_then(location_fields);
+                self.write_event(
+                    "test",
+                    desc.name.as_slice(),
+                    "failed",
+                    exec_time,
+                    stdout,
+                    location.as_deref(),
+                )
             }
 
             TestResult::TrTimedFail => self.write_event(
@@ -106,17 +143,36 @@ fn write_result(
                 Some(r#""reason": "time limit exceeded""#),
             ),
 
-            TestResult::TrFailedMsg(ref m) => self.write_event(
-                "test",
-                desc.name.as_slice(),
-                "failed",
-                exec_time,
-                stdout,
-                Some(&*format!(r#""message": "{}""#, EscapedString(m))),
-            ),
+            TestResult::TrFailedMsg(ref m) => {
+                let mut extra = format!(r#""message": "{}""#, EscapedString(m));
+                if let Some(location) = stdout.as_deref().and_then(location_fields) {
+                    extra.push_str(", ");
+                    extra.push_str(&location);
+                }
+                self.write_event(
+                    "test",
+                    desc.name.as_slice(),
+                    "failed",
+                    exec_time,
+                    stdout,
+                    Some(&extra),
+                )
+            }
 
             TestResult::TrIgnored => {
-                self.write_event("test", desc.name.as_slice(), "ignored", exec_time, stdout, None)
+                #[cfg(not(bootstrap))]
+                let reason =
+                    desc.ignore_message.map(|msg| format!(r#""reason": "{}""#, EscapedString(msg)));
+                #[cfg(bootstrap)]
+                let reason: Option<String> = None;
+                self.write_event(
+                    "test",
+                    desc.name.as_slice(),
+                    "ignored",
+                    exec_time,
+                    stdout,
+                    reason.as_deref(),
+                )
             }
 
             TestResult::TrAllowedFail => self.write_event(
@@ -161,6 +217,13 @@ fn write_timeout(&mut self, desc: &TestDesc) -> io::Result<()> {
         ))
     }
 
+    fn write_warning(&mut self, msg: &str) -> io::Result<()> {
+        self.writeln_message(&*format!(
+            r#"{{ "type": "warning", "message": "{}" }}"#,
+            EscapedString(msg)
+        ))
+    }
+
     fn write_run_finish(&mut self, state: &ConsoleTestState) -> io::Result<bool> {
         self.write_message(&*format!(
             "{{ \"type\": \"suite\", \
@@ -171,7 +234,7 @@ fn write_run_finish(&mut self, state: &ConsoleTestState) -> io::Result<bool> {
              \"ignored\": {}, \
              \"measured\": {}, \
              \"filtered_out\": {}",
-            if state.failed == 0 { "ok" } else { "failed" },
+            if state.succeeded() { "ok" } else { "failed" },
             state.passed,
             state.failed + state.allowed_fail,
             state.allowed_fail,
@@ -185,15 +248,72 @@ fn write_run_finish(&mut self, state: &ConsoleTestState) -> io::Result<bool> {
             self.write_message(&time_str)?;
         }
 
+        let run_id = self.run_id_field();
+        self.write_message(&run_id)?;
         self.writeln_message(" }")?;
 
-        Ok(state.failed == 0)
+        Ok(state.succeeded())
+    }
+}
+
+/// Renders a test result as a single JSON object, as written to the logfile
+/// with `--logfile-format json`.
+pub(crate) fn json_log_line(
+    desc: &TestDesc,
+    result: &TestResult,
+    exec_time: Option<&time::TestExecTime>,
+) -> String {
+    let name = EscapedString(desc.name.as_slice());
+    let mut line = match *result {
+        TestResult::TrBench(ref bs) => format!(
+            r#"{{ "type": "bench", "name": "{}", "median": {}, "deviation": {}"#,
+            name,
+            bs.ns_iter_summ.median as usize,
+            (bs.ns_iter_summ.max - bs.ns_iter_summ.min) as usize
+        ),
+        _ => {
+            let event = match *result {
+                TestResult::TrOk => "ok",
+                TestResult::TrIgnored => "ignored",
+                TestResult::TrAllowedFail => "allowed_failure",
+                _ => "failed",
+            };
+            format!(r#"{{ "type": "test", "name": "{}", "event": "{}""#, name, event)
+        }
+    };
+    match *result {
+        TestResult::TrFailedMsg(ref m) => {
+            line.push_str(&format!(r#", "message": "{}""#, EscapedString(m)))
+        }
+        TestResult::TrTimedFail => line.push_str(r#", "reason": "time limit exceeded""#),
+        _ => {}
     }
+    if let Some(exec_time) = exec_time {
+        line.push_str(&format!(r#", "exec_time": {}"#, exec_time.0.as_secs_f64()));
+    }
+    line.push_str(" }");
+    line
+}
+
+/// Finds the location of the first panic in `output`, as printed by the
+/// default panic hook (`thread 'a' panicked at 'msg', file:line:column`), and
+/// renders it as `"file"` and `"line"` fields. The message can span several
+/// lines, so the location ends the first line after `panicked at` that has one.
+fn location_fields(output: &str) -> Option<String> {
+    let (_, panic) = output.split_once("' panicked at '")?;
+    panic.lines().find_map(|line| {
+        let (_, location) = line.rsplit_once("', ")?;
+        let mut parts = location.rsplitn(3, ':');
+        let _column: u32 = parts.next()?.parse().ok()?;
+        let line: u32 = parts.next()?.parse().ok()?;
+        let file = parts.next().filter(|file| !file.is_empty())?;
+        Some(format!(r#""file": "{}", "line": {}"#, EscapedString(file), line))
+    })
 }
 
 /// A formatting utility used to print strings with characters in need of escaping.
 /// Base code taken form `libserialize::json::escape_str`
-struct EscapedString<S: AsRef<str>>(S);
+pub(super) struct EscapedString<S: AsRef<str>>(pub(super) S);
 
 impl<S: AsRef<str>> std::fmt::Display for EscapedString<S> {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> ::std::fmt::Result {
@@ -0,0 +1,93 @@
+use std::{io, io::prelude::Write};
+
+use super::{json::EscapedString, json_log_line, OutputFormatter};
+use crate::{
+    console::{ConsoleTestState, OutputLocation},
+    test_result::TestResult,
+    time,
+    types::TestDesc,
+};
+
+// Unlike `JsonFormatter`, which streams one object per event, this buffers every result and
+// writes a single `{ "tests": [...], "summary": {...} }` object once the run is finished.
+pub(crate) struct JsonSummaryFormatter<T> {
+    out: OutputLocation<T>,
+    tests: Vec<String>,
+    warnings: Vec<String>,
+}
+
+impl<T: Write> JsonSummaryFormatter<T> {
+    pub fn new(out: OutputLocation<T>) -> Self {
+        Self { out, tests: Vec::new(), warnings: Vec::new() }
+    }
+
+    #[cfg(test)]
+    pub fn output_location(&self) -> &OutputLocation<T> {
+        &self.out
+    }
+}
+
+impl<T: Write> OutputFormatter for JsonSummaryFormatter<T> {
+    fn write_run_start(&mut self, _test_count: usize) -> io::Result<()> {
+        // Nothing is written until the run is finished.
+        Ok(())
+    }
+
+    fn write_test_start(&mut self, _desc: &TestDesc) -> io::Result<()> {
+        Ok(())
+    }
+
+    fn write_timeout(&mut self, _desc: &TestDesc) -> io::Result<()> {
+        Ok(())
+    }
+
+    fn write_warning(&mut self, msg: &str) -> io::Result<()> {
+        self.warnings.push(format!(r#""{}""#, EscapedString(msg)));
+        Ok(())
+    }
+
+    fn write_result(
+        &mut self,
+        desc: &TestDesc,
+        result: &TestResult,
+        exec_time: Option<&time::TestExecTime>,
+        _stdout: &[u8],
+        _state: &ConsoleTestState,
+    ) -> io::Result<()> {
+        self.tests.push(json_log_line(desc, result, exec_time));
+        Ok(())
+    }
+
+    fn write_run_finish(&mut self, state: &ConsoleTestState) -> io::Result<bool> {
+        let mut summary = format!(
+            "{{ \"result\": \"{}\", \
+             \"passed\": {}, \
+             \"failed\": {}, \
+             \"allowed_fail\": {}, \
+             \"ignored\": {}, \
+             \"measured\": {}, \
+             \"filtered_out\": {}",
+            if state.succeeded() { "ok" } else { "failed" },
+            state.passed,
+            state.failed + state.allowed_fail,
+            state.allowed_fail,
+            state.ignored,
+            state.measured,
+            state.filtered_out,
+        );
+        if let Some(ref exec_time) = state.exec_time {
+            summary.push_str(&format!(", \"exec_time\": {}", exec_time.0.as_secs_f64()));
+        }
+        summary.push_str(" }");
+
+        let mut document =
+            format!(r#"{{ "tests": [{}], "summary": {}"#, self.tests.join(", "), summary);
+        if !self.warnings.is_empty() {
+            document.push_str(&format!(r#", "warnings": [{}]"#, self.warnings.join(", ")));
+        }
+        document.push_str(" }\n");
+        self.out.write_all(document.as_bytes())?;
+
+        Ok(state.succeeded())
+    }
+}
@@ -19,6 +19,11 @@ pub fn new(out: OutputLocation<T>) -> Self {
         Self { out, results: Vec::new() }
     }
 
+    #[cfg(test)]
+    pub fn output_location(&self) -> &OutputLocation<T> {
+        &self.out
+    }
+
     fn write_message(&mut self, s: &str) -> io::Result<()> {
         assert!(!s.contains('\n'));
 
@@ -42,6 +47,12 @@ fn write_timeout(&mut self, _desc: &TestDesc) -> io::Result<()> {
         Ok(())
     }
 
+    fn write_warning(&mut self, msg: &str) -> io::Result<()> {
+        // "--" may not appear inside an XML comment.
+        let msg = msg.replace("--", "- -").replace('\n', " ");
+        self.write_message(&*format!("<!-- warning: {} -->", msg))
+    }
+
     fn write_result(
         &mut self,
         desc: &TestDesc,
@@ -52,7 +63,8 @@ fn write_result(
     ) -> io::Result<()> {
         // Because the testsuit node holds some of the information as attributes, we can't write it
         // until all of the tests has ran. Instead of writting every result as they come in, we add
-        // them to a Vec and write them all at once when run is complete.
+        // them to a Vec and write them all at once when run is complete. Tests that were not timed
+        // are reported as taking no time.
         let duration = exec_time.map(|t| t.0.clone()).unwrap_or_default();
         self.results.push((desc.clone(), result.clone(), duration));
         Ok(())
@@ -60,14 +72,19 @@ fn write_result(
     fn write_run_finish(&mut self, state: &ConsoleTestState) -> io::Result<bool> {
         self.write_message("<testsuites>")?;
 
+        let time: Duration = self.results.iter().map(|(_, _, duration)| *duration).sum();
         self.write_message(&*format!(
             "<testsuite name=\"test\" package=\"test\" id=\"0\" \
              errors=\"0\" \
              failures=\"{}\" \
              tests=\"{}\" \
              skipped=\"{}\" \
+             time=\"{}\" \
              >",
-            state.failed, state.total, state.ignored
+            state.failed,
+            state.total,
+            state.ignored,
+            time.as_secs_f64()
         ))?;
         for (desc, result, duration) in std::mem::replace(&mut self.results, Vec::new()) {
             let (class_name, test_name) = parse_class_name(&desc);
@@ -79,7 +96,7 @@ fn write_run_finish(&mut self, state: &ConsoleTestState) -> io::Result<bool> {
                          name=\"{}\" time=\"{}\">",
                         class_name,
                         test_name,
-                        duration.as_secs()
+                        duration.as_secs_f64()
                     ))?;
                     self.write_message("<failure type=\"assert\"/>")?;
                     self.write_message("</testcase>")?;
@@ -91,7 +108,7 @@ fn write_run_finish(&mut self, state: &ConsoleTestState) -> io::Result<bool> {
                          name=\"{}\" time=\"{}\">",
                         class_name,
                         test_name,
-                        duration.as_secs()
+                        duration.as_secs_f64()
                     ))?;
                     self.write_message(&*format!("<failure message=\"{}\" type=\"assert\"/>", m))?;
                     self.write_message("</testcase>")?;
@@ -103,7 +120,7 @@ fn write_run_finish(&mut self, state: &ConsoleTestState) -> io::Result<bool> {
                          name=\"{}\" time=\"{}\">",
                         class_name,
                         test_name,
-                        duration.as_secs()
+                        duration.as_secs_f64()
                     ))?;
                     self.write_message("<failure type=\"timeout\"/>")?;
                     self.write_message("</testcase>")?;
@@ -123,7 +140,7 @@ fn write_run_finish(&mut self, state: &ConsoleTestState) -> io::Result<bool> {
                          name=\"{}\" time=\"{}\"/>",
                         class_name,
                         test_name,
-                        duration.as_secs()
+                        duration.as_secs_f64()
                     ))?;
                 }
             }
@@ -133,7 +150,7 @@ fn write_run_finish(&mut self, state: &ConsoleTestState) -> io::Result<bool> {
         self.write_message("</testsuite>")?;
         self.write_message("</testsuites>")?;
 
-        Ok(state.failed == 0)
+        Ok(state.succeeded())
     }
 }
 
@@ -7,20 +7,38 @@
     types::{TestDesc, TestName},
 };
 
+mod bench_csv;
 mod json;
+mod json_summary;
 mod junit;
 mod pretty;
+mod tap;
+mod tee;
 mod terse;
 
-pub(crate) use self::json::JsonFormatter;
+pub(crate) use self::bench_csv::BenchCsvFormatter;
+pub(crate) use self::json::{json_log_line, JsonFormatter};
+#[cfg(test)]
+pub(crate) use self::json::JSON_SCHEMA_VERSION;
+pub(crate) use self::json_summary::JsonSummaryFormatter;
 pub(crate) use self::junit::JunitFormatter;
 pub(crate) use self::pretty::PrettyFormatter;
+pub(crate) use self::tap::TapFormatter;
+pub(crate) use self::tee::TeeFormatter;
 pub(crate) use self::terse::TerseFormatter;
 
 pub(crate) trait OutputFormatter {
     fn write_run_start(&mut self, test_count: usize) -> io::Result<()>;
     fn write_test_start(&mut self, desc: &TestDesc) -> io::Result<()>;
     fn write_timeout(&mut self, desc: &TestDesc) -> io::Result<()>;
+    // Reports a non-fatal problem with the run, e.g. truncated test output.
+    fn write_warning(&mut self, _msg: &str) -> io::Result<()> {
+        Ok(())
+    }
+    // Shows the captured output of a test that just finished, for `--tee-output`.
+    fn write_tee_output(&mut self, _desc: &TestDesc, _stdout: &[u8]) -> io::Result<()> {
+        Ok(())
+    }
     fn write_result(
         &mut self,
         desc: &TestDesc,
@@ -40,3 +58,38 @@ pub(crate) fn write_stderr_delimiter(test_output: &mut Vec<u8>, test_name: &Test
     }
     writeln!(test_output, "---- {} stderr ----", test_name).unwrap();
 }
+
+// Explains an empty run, which otherwise only shows up as a summary of zeros.
+pub(crate) fn fmt_no_tests_run(state: &ConsoleTestState) -> Option<String> {
+    if state.total != 0 {
+        return None;
+    }
+    Some(match state.filtered_out {
+        0 => String::from("\n0 tests run\n"),
+        n => format!("\n0 tests run: all {} filtered out\n", n),
+    })
+}
+
+// Renders the `--report-cu` total, if any test measured its compute units.
+pub(crate) fn fmt_compute_units(state: &ConsoleTestState) -> Option<String> {
+    state.compute_units.map(|total| format!("\ncompute units consumed: {}\n", total))
+}
+
+// Renders the `--show-cu` suffix of a test's result line.
+pub(crate) fn fmt_test_compute_units(state: &ConsoleTestState) -> Option<String> {
+    state.test_compute_units.map(|compute_units| format!(" ({} CU)", compute_units))
+}
+
+// Renders the `--slowest` report, if any test durations were recorded.
+pub(crate) fn fmt_slowest(state: &ConsoleTestState) -> Option<String> {
+    let slowest = state.slowest.as_ref()?.sorted();
+    if slowest.is_empty() {
+        return None;
+    }
+
+    let mut s = String::from("\nslowest tests:\n");
+    for (name, exec_time) in slowest {
+        s.push_str(&format!("    {} {}\n", exec_time, name));
+    }
+    Some(s)
+}
@@ -1,6 +1,8 @@
 use std::{io, io::prelude::Write};
 
-use super::OutputFormatter;
+use super::{
+    fmt_compute_units, fmt_no_tests_run, fmt_slowest, fmt_test_compute_units, OutputFormatter,
+};
 use crate::{
     bench::fmt_bench_samples,
     console::{ConsoleTestState, OutputLocation},
@@ -126,25 +128,31 @@ fn write_results(
         &mut self,
         inputs: &Vec<(TestDesc, Vec<u8>)>,
         results_type: &str,
+        show_diffs: bool,
     ) -> io::Result<()> {
         let results_out_str = format!("\n{}:\n", results_type);
 
         self.write_plain(&results_out_str)?;
 
         let mut results = Vec::new();
-        let mut stdouts = String::new();
+        let mut stdouts = Vec::new();
         for &(ref f, ref stdout) in inputs {
             results.push(f.name.to_string());
             if !stdout.is_empty() {
-                stdouts.push_str(&format!("---- {} stdout ----\n", f.name));
-                let output = String::from_utf8_lossy(stdout);
-                stdouts.push_str(&output);
-                stdouts.push('\n');
+                stdouts.push((f, String::from_utf8_lossy(stdout)));
             }
         }
         if !stdouts.is_empty() {
             self.write_plain("\n")?;
-            self.write_plain(&stdouts)?;
+            for (f, output) in stdouts {
+                self.write_plain(&format!("---- {} stdout ----\n{}\n", f.name, output))?;
+                // The diff only adds to the plain output through its colors.
+                if show_diffs && self.use_color {
+                    if let Some((left, right)) = parse_assert_eq(&output) {
+                        self.write_assert_eq_diff(left, right)?;
+                    }
+                }
+            }
         }
 
         self.write_plain(&results_out_str)?;
@@ -156,15 +164,33 @@ fn write_results(
     }
 
     pub fn write_successes(&mut self, state: &ConsoleTestState) -> io::Result<()> {
-        self.write_results(&state.not_failures, "successes")
+        self.write_results(&state.not_failures, "successes", false)
     }
 
     pub fn write_failures(&mut self, state: &ConsoleTestState) -> io::Result<()> {
-        self.write_results(&state.failures, "failures")
+        self.write_results(&state.failures, "failures", true)
     }
 
     pub fn write_time_failures(&mut self, state: &ConsoleTestState) -> io::Result<()> {
-        self.write_results(&state.time_failures, "failures (time limit exceeded)")
+        self.write_results(&state.time_failures, "failures (time limit exceeded)", false)
+    }
+
+    fn write_assert_eq_diff(&mut self, left: &str, right: &str) -> io::Result<()> {
+        self.write_plain("diff (- left, + right):\n")?;
+        let left: Vec<_> = left.split_inclusive(", ").collect();
+        let right: Vec<_> = right.split_inclusive(", ").collect();
+        for line in diff_lines(&left, &right) {
+            match line {
+                DiffLine::Same(s) => self.write_plain(&format!("  {}\n", s.trim_end()))?,
+                DiffLine::Left(s) => {
+                    self.write_pretty(&format!("- {}\n", s.trim_end()), term::color::RED)?
+                }
+                DiffLine::Right(s) => {
+                    self.write_pretty(&format!("+ {}\n", s.trim_end()), term::color::GREEN)?
+                }
+            }
+        }
+        self.write_plain("\n")
     }
 
     fn write_test_name(&mut self, desc: &TestDesc) -> io::Result<()> {
@@ -203,7 +229,7 @@ fn write_result(
         result: &TestResult,
         exec_time: Option<&time::TestExecTime>,
         _: &[u8],
-        _: &ConsoleTestState,
+        state: &ConsoleTestState,
     ) -> io::Result<()> {
         if self.is_multithreaded {
             self.write_test_name(desc)?;
@@ -222,6 +248,9 @@ fn write_result(
         }
 
         self.write_time(desc, exec_time)?;
+        if let (TestResult::TrOk, Some(compute_units)) = (result, fmt_test_compute_units(state)) {
+            self.write_plain(&compute_units)?;
+        }
         self.write_plain("\n")
     }
 
@@ -233,11 +262,33 @@ fn write_timeout(&mut self, desc: &TestDesc) -> io::Result<()> {
         ))
     }
 
+    fn write_warning(&mut self, msg: &str) -> io::Result<()> {
+        self.write_pretty("warning", term::color::YELLOW)?;
+        self.write_plain(&format!(": {}\n", msg))
+    }
+
+    fn write_tee_output(&mut self, desc: &TestDesc, stdout: &[u8]) -> io::Result<()> {
+        // Serial runs have already written the test name, so end that line first.
+        if !self.is_multithreaded {
+            self.write_plain("\n")?;
+        }
+        self.write_plain(&format!("---- {} stdout ----\n", desc.name))?;
+        self.write_plain(&String::from_utf8_lossy(stdout))?;
+        if !stdout.ends_with(b"\n") {
+            self.write_plain("\n")?;
+        }
+        // And repeat it, so that the result does not end up on a line of its own.
+        if !self.is_multithreaded {
+            self.write_test_name(desc)?;
+        }
+        Ok(())
+    }
+
     fn write_run_finish(&mut self, state: &ConsoleTestState) -> io::Result<bool> {
         if state.options.display_output {
             self.write_successes(state)?;
         }
-        let success = state.failed == 0;
+        let success = state.succeeded();
         if !success {
             if !state.failures.is_empty() {
                 self.write_failures(state)?;
@@ -248,6 +299,18 @@ fn write_run_finish(&mut self, state: &ConsoleTestState) -> io::Result<bool> {
             }
         }
 
+        if let Some(slowest) = fmt_slowest(state) {
+            self.write_plain(&slowest)?;
+        }
+
+        if let Some(compute_units) = fmt_compute_units(state) {
+            self.write_plain(&compute_units)?;
+        }
+
+        if let Some(no_tests_run) = fmt_no_tests_run(state) {
+            self.write_plain(&no_tests_run)?;
+        }
+
         self.write_plain("\ntest result: ")?;
 
         if success {
@@ -286,3 +349,62 @@ fn write_run_finish(&mut self, state: &ConsoleTestState) -> io::Result<bool> {
         Ok(success)
     }
 }
+
+// Extracts the `left` and `right` values from the panic message of a failed
+// `assert_eq!`, if `output` contains one.
+fn parse_assert_eq(output: &str) -> Option<(&str, &str)> {
+    const LEFT: &str = "assertion failed: `(left == right)`\n  left: `";
+    const RIGHT: &str = "`,\n right: `";
+
+    let rest = &output[output.find(LEFT)? + LEFT.len()..];
+    let left_end = rest.find(RIGHT)?;
+    let left = &rest[..left_end];
+    let rest = &rest[left_end + RIGHT.len()..];
+
+    // The value is followed by the end of the panic message, or by `: ` and a
+    // custom message.
+    let line = rest.lines().next()?;
+    let right_end = line.match_indices('`').map(|(i, _)| i).find(|&i| {
+        let after = &line[i + 1..];
+        after.is_empty() || after.starts_with("',") || after.starts_with(": ")
+    })?;
+    Some((left, &line[..right_end]))
+}
+
+enum DiffLine<'a> {
+    Same(&'a str),
+    Left(&'a str),
+    Right(&'a str),
+}
+
+// Line-based diff through the longest common subsequence of `left` and `right`.
+fn diff_lines<'a>(left: &[&'a str], right: &[&'a str]) -> Vec<DiffLine<'a>> {
+    // lcs[i][j] is the length of the LCS of left[i..] and right[j..].
+    let mut lcs = vec![vec![0usize; right.len() + 1]; left.len() + 1];
+    for i in (0..left.len()).rev() {
+        for j in (0..right.len()).rev() {
+            lcs[i][j] = if left[i] == right[j] {
+                lcs[i + 1][j + 1] + 1
+            } else {
+                lcs[i + 1][j].max(lcs[i][j + 1])
+            };
+        }
+    }
+
+    let mut lines = Vec::new();
+    let (mut i, mut j) = (0, 0);
+    while i < left.len() || j < right.len() {
+        if i < left.len() && j < right.len() && left[i] == right[j] {
+            lines.push(DiffLine::Same(left[i]));
+            i += 1;
+            j += 1;
+        } else if j == right.len() || (i < left.len() && lcs[i + 1][j] >= lcs[i][j + 1]) {
+            lines.push(DiffLine::Left(left[i]));
+            i += 1;
+        } else {
+            lines.push(DiffLine::Right(right[j]));
+            j += 1;
+        }
+    }
+    lines
+}
@@ -0,0 +1,159 @@
+// ignore-tidy-todo
+
+use std::io::{self, prelude::Write};
+
+use super::{fmt_compute_units, fmt_slowest, OutputFormatter};
+use crate::{
+    bench::fmt_bench_samples,
+    console::{ConsoleTestState, OutputLocation},
+    test_result::TestResult,
+    time,
+    types::TestDesc,
+};
+
+// Emits a Test Anything Protocol (https://testanything.org) stream.
+pub(crate) struct TapFormatter<T> {
+    out: OutputLocation<T>,
+    test_number: usize,
+}
+
+impl<T: Write> TapFormatter<T> {
+    pub fn new(out: OutputLocation<T>) -> Self {
+        Self { out, test_number: 0 }
+    }
+
+    #[cfg(test)]
+    pub fn output_location(&self) -> &OutputLocation<T> {
+        &self.out
+    }
+
+    fn writeln_message(&mut self, s: &str) -> io::Result<()> {
+        assert!(!s.contains('\n'));
+
+        self.out.write_all(s.as_ref())?;
+        self.out.write_all(b"\n")
+    }
+
+    // Writes `s` as diagnostic lines, which TAP consumers ignore.
+    fn write_diagnostics(&mut self, s: &str) -> io::Result<()> {
+        for line in s.lines() {
+            if line.is_empty() {
+                self.writeln_message("#")?;
+            } else {
+                self.writeln_message(&format!("# {}", line))?;
+            }
+        }
+        Ok(())
+    }
+
+    fn write_test_line(
+        &mut self,
+        ok: bool,
+        desc: &TestDesc,
+        directive: Option<&str>,
+    ) -> io::Result<()> {
+        self.test_number += 1;
+        let status = if ok { "ok" } else { "not ok" };
+        // A `#` in the description would start a directive.
+        let name = desc.name.as_slice().replace('#', "\\#");
+        match directive {
+            Some(directive) => self.writeln_message(&format!(
+                "{} {} - {} # {}",
+                status, self.test_number, name, directive
+            )),
+            None => self.writeln_message(&format!("{} {} - {}", status, self.test_number, name)),
+        }
+    }
+
+    fn write_outputs(&mut self, outputs: &[(TestDesc, Vec<u8>)]) -> io::Result<()> {
+        for &(ref desc, ref stdout) in outputs {
+            if !stdout.is_empty() {
+                self.writeln_message(&format!("# ---- {} stdout ----", desc.name))?;
+                self.write_diagnostics(&String::from_utf8_lossy(stdout))?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl<T: Write> OutputFormatter for TapFormatter<T> {
+    fn write_run_start(&mut self, test_count: usize) -> io::Result<()> {
+        self.writeln_message(&format!("1..{}", test_count))
+    }
+
+    fn write_test_start(&mut self, _desc: &TestDesc) -> io::Result<()> {
+        // We do not output anything on test start.
+        Ok(())
+    }
+
+    fn write_timeout(&mut self, desc: &TestDesc) -> io::Result<()> {
+        self.writeln_message(&format!(
+            "# test {} has been running for over {} seconds",
+            desc.name,
+            time::TEST_WARN_TIMEOUT_S
+        ))
+    }
+
+    fn write_warning(&mut self, msg: &str) -> io::Result<()> {
+        self.write_diagnostics(&format!("warning: {}", msg))
+    }
+
+    fn write_result(
+        &mut self,
+        desc: &TestDesc,
+        result: &TestResult,
+        _exec_time: Option<&time::TestExecTime>,
+        _stdout: &[u8],
+        _state: &ConsoleTestState,
+    ) -> io::Result<()> {
+        match *result {
+            TestResult::TrOk => self.write_test_line(true, desc, None),
+            // The failure message is appended to the captured output, which is reported
+            // once the run finishes.
+            TestResult::TrFailed | TestResult::TrFailedMsg(_) => {
+                self.write_test_line(false, desc, None)
+            }
+            TestResult::TrTimedFail => {
+                self.write_test_line(false, desc, None)?;
+                self.write_diagnostics("time limit exceeded")
+            }
+            TestResult::TrIgnored => {
+                #[cfg(not(bootstrap))]
+                // The directive has to stay on the test line.
+                let directive =
+                    desc.ignore_message.map(|msg| format!("SKIP {}", msg.replace('\n', " ")));
+                #[cfg(bootstrap)]
+                let directive: Option<String> = None;
+                self.write_test_line(true, desc, Some(directive.as_deref().unwrap_or("SKIP")))
+            }
+            TestResult::TrAllowedFail => {
+                // TAP's directive for a failure that is expected.
+                self.write_test_line(false, desc, Some("TODO allowed to fail"))
+            }
+            TestResult::TrBench(ref bs) => {
+                self.write_test_line(true, desc, None)?;
+                self.write_diagnostics(&format!("bench: {}", fmt_bench_samples(bs)))
+            }
+        }
+    }
+
+    fn write_run_finish(&mut self, state: &ConsoleTestState) -> io::Result<bool> {
+        self.write_outputs(&state.failures)?;
+        self.write_outputs(&state.time_failures)?;
+        if state.options.display_output {
+            self.write_outputs(&state.not_failures)?;
+        }
+        if let Some(slowest) = fmt_slowest(state) {
+            self.write_diagnostics(&slowest)?;
+        }
+        if let Some(compute_units) = fmt_compute_units(state) {
+            self.write_diagnostics(&compute_units)?;
+        }
+        self.writeln_message(&format!(
+            "# {} passed; {} failed; {} ignored; {} measured; {} filtered out",
+            state.passed, state.failed, state.ignored, state.measured, state.filtered_out
+        ))?;
+
+        Ok(state.succeeded())
+    }
+}
@@ -0,0 +1,62 @@
+use std::io;
+
+use super::OutputFormatter;
+use crate::{console::ConsoleTestState, test_result::TestResult, time, types::TestDesc};
+
+// Forwards every event to two formatters, e.g. the console one and a report file. The run's
+// success is the primary formatter's verdict.
+pub(crate) struct TeeFormatter {
+    primary: Box<dyn OutputFormatter>,
+    secondary: Box<dyn OutputFormatter>,
+}
+
+impl TeeFormatter {
+    pub fn new(primary: Box<dyn OutputFormatter>, secondary: Box<dyn OutputFormatter>) -> Self {
+        Self { primary, secondary }
+    }
+}
+
+impl OutputFormatter for TeeFormatter {
+    fn write_run_start(&mut self, test_count: usize) -> io::Result<()> {
+        self.primary.write_run_start(test_count)?;
+        self.secondary.write_run_start(test_count)
+    }
+
+    fn write_test_start(&mut self, desc: &TestDesc) -> io::Result<()> {
+        self.primary.write_test_start(desc)?;
+        self.secondary.write_test_start(desc)
+    }
+
+    fn write_timeout(&mut self, desc: &TestDesc) -> io::Result<()> {
+        self.primary.write_timeout(desc)?;
+        self.secondary.write_timeout(desc)
+    }
+
+    fn write_warning(&mut self, msg: &str) -> io::Result<()> {
+        self.primary.write_warning(msg)?;
+        self.secondary.write_warning(msg)
+    }
+
+    fn write_tee_output(&mut self, desc: &TestDesc, stdout: &[u8]) -> io::Result<()> {
+        self.primary.write_tee_output(desc, stdout)?;
+        self.secondary.write_tee_output(desc, stdout)
+    }
+
+    fn write_result(
+        &mut self,
+        desc: &TestDesc,
+        result: &TestResult,
+        exec_time: Option<&time::TestExecTime>,
+        stdout: &[u8],
+        state: &ConsoleTestState,
+    ) -> io::Result<()> {
+        self.primary.write_result(desc, result, exec_time, stdout, state)?;
+        self.secondary.write_result(desc, result, exec_time, stdout, state)
+    }
+
+    fn write_run_finish(&mut self, state: &ConsoleTestState) -> io::Result<bool> {
+        let success = self.primary.write_run_finish(state)?;
+        self.secondary.write_run_finish(state)?;
+        Ok(success)
+    }
+}
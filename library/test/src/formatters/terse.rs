@@ -1,6 +1,8 @@
 use std::{io, io::prelude::Write};
 
-use super::OutputFormatter;
+use super::{
+    fmt_compute_units, fmt_no_tests_run, fmt_slowest, fmt_test_compute_units, OutputFormatter,
+};
 use crate::{
     bench::fmt_bench_samples,
     console::{ConsoleTestState, OutputLocation},
@@ -41,6 +43,11 @@ pub fn new(
         }
     }
 
+    #[cfg(test)]
+    pub fn output_location(&self) -> &OutputLocation<T> {
+        &self.out
+    }
+
     pub fn write_ok(&mut self) -> io::Result<()> {
         self.write_short_result(".", term::color::GREEN)
     }
@@ -193,10 +200,19 @@ fn write_result(
         result: &TestResult,
         _: Option<&time::TestExecTime>,
         _: &[u8],
-        _: &ConsoleTestState,
+        state: &ConsoleTestState,
     ) -> io::Result<()> {
         match *result {
-            TestResult::TrOk => self.write_ok(),
+            // A passing test is just a `.`, so with `--show-cu` it gets a line of its own, like a
+            // benchmark, to show its compute units on.
+            TestResult::TrOk => match fmt_test_compute_units(state) {
+                Some(compute_units) => {
+                    self.write_test_name(desc)?;
+                    self.write_pretty("ok", term::color::GREEN)?;
+                    self.write_plain(&format!("{}\n", compute_units))
+                }
+                None => self.write_ok(),
+            },
             TestResult::TrFailed | TestResult::TrFailedMsg(_) | TestResult::TrTimedFail => {
                 self.write_failed()
             }
@@ -220,15 +236,32 @@ fn write_timeout(&mut self, desc: &TestDesc) -> io::Result<()> {
         ))
     }
 
+    fn write_warning(&mut self, msg: &str) -> io::Result<()> {
+        self.write_pretty("warning", term::color::YELLOW)?;
+        self.write_plain(&format!(": {}\n", msg))
+    }
+
     fn write_run_finish(&mut self, state: &ConsoleTestState) -> io::Result<bool> {
         if state.options.display_output {
             self.write_outputs(state)?;
         }
-        let success = state.failed == 0;
-        if !success {
+        let success = state.succeeded();
+        if state.failed > 0 {
             self.write_failures(state)?;
         }
 
+        if let Some(slowest) = fmt_slowest(state) {
+            self.write_plain(&slowest)?;
+        }
+
+        if let Some(compute_units) = fmt_compute_units(state) {
+            self.write_plain(&compute_units)?;
+        }
+
+        if let Some(no_tests_run) = fmt_no_tests_run(state) {
+            self.write_plain(&no_tests_run)?;
+        }
+
         self.write_plain("\ntest result: ")?;
 
         if success {
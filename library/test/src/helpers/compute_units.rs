@@ -0,0 +1,30 @@
+//! Helper module to measure the compute units consumed by tests on BPF.
+
+#[cfg(all(target_arch = "bpf", feature = "bpf-remaining-compute-units"))]
+extern "C" {
+    fn sol_remaining_compute_units() -> u64;
+}
+
+/// Returns the number of compute units the program has left, or `None` if
+/// compute units are not metered on this target.
+#[cfg(all(target_arch = "bpf", feature = "bpf-remaining-compute-units"))]
+pub fn remaining() -> Option<u64> {
+    Some(unsafe { sol_remaining_compute_units() })
+}
+
+#[cfg(not(any(test, all(target_arch = "bpf", feature = "bpf-remaining-compute-units"))))]
+pub fn remaining() -> Option<u64> {
+    None
+}
+
+// The host meters nothing, so tests set the compute units left on the current
+// thread instead.
+#[cfg(all(test, not(all(target_arch = "bpf", feature = "bpf-remaining-compute-units"))))]
+thread_local! {
+    pub static MOCK_REMAINING: std::cell::Cell<Option<u64>> = std::cell::Cell::new(None);
+}
+
+#[cfg(all(test, not(all(target_arch = "bpf", feature = "bpf-remaining-compute-units"))))]
+pub fn remaining() -> Option<u64> {
+    MOCK_REMAINING.with(|remaining| remaining.get())
+}
@@ -19,3 +19,17 @@ pub fn get_concurrency() -> usize {
 pub fn get_concurrency() -> usize {
     1
 }
+
+/// Upper bound on the number of threads picked by `--test-threads=0`.
+pub const AUTO_TEST_THREADS_CAP: usize = 64;
+
+/// Resolves the `--test-threads` option into a thread count. No value means
+/// `get_concurrency()`, while `0` auto-detects the same way but is capped at
+/// `AUTO_TEST_THREADS_CAP`.
+pub fn resolve_test_threads(test_threads: Option<usize>) -> usize {
+    match test_threads {
+        Some(0) => get_concurrency().min(AUTO_TEST_THREADS_CAP),
+        Some(n) => n,
+        None => get_concurrency(),
+    }
+}
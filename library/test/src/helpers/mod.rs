@@ -1,7 +1,9 @@
 //! Module with common helpers not directly related to tests
 //! but used in `libtest`.
 
+pub mod compute_units;
 pub mod concurrency;
 pub mod exit_code;
 pub mod isatty;
 pub mod metrics;
+pub mod program_log;
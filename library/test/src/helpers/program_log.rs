@@ -0,0 +1,16 @@
+//! Helper module to write to the BPF program log.
+
+#[cfg(target_arch = "bpf")]
+extern "C" {
+    fn sol_log_(message: *const u8, length: u64);
+}
+
+/// Writes `message` as an entry of its own in the program log. Does nothing
+/// on other targets, which have no program log.
+#[cfg(target_arch = "bpf")]
+pub fn log(message: &str) {
+    unsafe { sol_log_(message.as_ptr(), message.len() as u64) }
+}
+
+#[cfg(not(target_arch = "bpf"))]
+pub fn log(_message: &str) {}
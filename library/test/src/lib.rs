@@ -23,6 +23,7 @@
 #![cfg_attr(unix, feature(libc))]
 #![feature(rustc_private)]
 #![feature(nll)]
+#![feature(once_cell)]
 #![feature(available_concurrency)]
 #![feature(bench_black_box)]
 #![feature(internal_output_capture)]
@@ -35,7 +36,9 @@
 // Public reexports
 pub use self::bench::{black_box, Bencher};
 pub use self::console::run_tests_console;
-pub use self::options::{ColorConfig, Options, OutputFormat, RunIgnored, ShouldPanic};
+pub use self::options::{
+    BenchFormat, ColorConfig, LogfileFormat, Options, OutputFormat, RunIgnored, ShouldPanic,
+};
 pub use self::types::TestName::*;
 pub use self::types::*;
 pub use self::ColorConfig::*;
@@ -54,8 +57,8 @@ pub mod test {
         test_result::{TestResult, TrFailed, TrFailedMsg, TrIgnored, TrOk},
         time::{TestExecTime, TestTimeOptions},
         types::{
-            DynTestFn, DynTestName, StaticBenchFn, StaticTestFn, StaticTestName, TestDesc,
-            TestDescAndFn, TestId, TestName, TestType,
+            DynTestFn, DynTestName, StaticBenchFn, StaticTestFn, StaticTestName, TargetFilter,
+            TestDesc, TestDescAndFn, TestId, TestName, TestType,
         },
     };
 }
@@ -64,6 +67,7 @@ pub mod test {
     collections::VecDeque,
     env, io,
     io::prelude::Write,
+    lazy::SyncLazy,
     panic::{self, catch_unwind, AssertUnwindSafe, PanicInfo},
     process::{self, Command, Termination},
     sync::mpsc::{channel, Sender},
@@ -88,7 +92,7 @@ pub mod test {
 mod tests;
 
 use event::{CompletedTest, TestEvent};
-use helpers::concurrency::get_concurrency;
+use helpers::concurrency::resolve_test_threads;
 use helpers::exit_code::get_exit_code;
 use options::{Concurrent, RunStrategy};
 use test_result::*;
@@ -201,6 +205,41 @@ pub fn assert_test_result<T: Termination>(result: T) {
     );
 }
 
+/// A callback that `run_tests` invokes with the description of each test.
+pub type TestHook = fn(&TestDesc);
+
+// Registered hooks. They belong to the process, not to a `run_tests` call.
+static BEFORE_EACH: SyncLazy<Mutex<Option<TestHook>>> = SyncLazy::new(|| Mutex::new(None));
+static AFTER_EACH: SyncLazy<Mutex<Option<TestHook>>> = SyncLazy::new(|| Mutex::new(None));
+
+/// Registers a hook that `run_tests` calls right before starting each test,
+/// or removes it when given `None`.
+///
+/// Hooks are global process state: once set, they apply to every later
+/// `run_tests` call, from any thread, until they are replaced. They are called
+/// on the thread driving the test run. With more than one test thread, that is
+/// not the thread the test runs on, so a hook cannot reach the test's
+/// thread-local state, and the calls for different tests may interleave. This
+/// is mostly useful for single-threaded targets like BPF, where tests share
+/// the in-memory filesystem, environment and heap and need to reset them.
+pub fn set_before_each(hook: Option<TestHook>) {
+    *BEFORE_EACH.lock().unwrap() = hook;
+}
+
+/// Registers a hook that `run_tests` calls right after each test completes,
+/// or removes it when given `None`. See [`set_before_each`].
+pub fn set_after_each(hook: Option<TestHook>) {
+    *AFTER_EACH.lock().unwrap() = hook;
+}
+
+fn call_hook(hook: &Mutex<Option<TestHook>>, desc: &TestDesc) {
+    // Copied out first, so that the hook itself may replace the hooks.
+    let hook = *hook.lock().unwrap();
+    if let Some(hook) = hook {
+        hook(desc);
+    }
+}
+
 pub fn run_tests<F>(
     opts: &TestOpts,
     tests: Vec<TestDescAndFn>,
@@ -258,7 +297,7 @@ struct TimeoutEntry {
         .map(|(i, e)| (TestId(i), e))
         .partition(|(_, e)| matches!(e.testfn, StaticTestFn(_) | DynTestFn(_)));
 
-    let concurrency = opts.test_threads.unwrap_or_else(get_concurrency);
+    let concurrency = resolve_test_threads(opts.test_threads);
 
     let mut remaining = filtered_tests;
     remaining.reverse();
@@ -304,10 +343,12 @@ fn calc_timeout(timeout_queue: &VecDeque<TimeoutEntry>) -> Option<Duration> {
             let (id, test) = remaining.pop().unwrap();
             let event = TestEvent::TeWait(test.desc.clone());
             notify_about_test_event(event)?;
+            call_hook(&BEFORE_EACH, &test.desc);
             let join_handle =
                 run_test(opts, !opts.run_tests, id, test, run_strategy, tx.clone(), Concurrent::No);
             assert!(join_handle.is_none());
             let completed_test = rx.recv().unwrap();
+            call_hook(&AFTER_EACH, &completed_test.desc);
 
             let event = TestEvent::TeResult(completed_test);
             notify_about_test_event(event)?;
@@ -321,6 +362,7 @@ fn calc_timeout(timeout_queue: &VecDeque<TimeoutEntry>) -> Option<Duration> {
 
                 let event = TestEvent::TeWait(desc.clone());
                 notify_about_test_event(event)?; //here no pad
+                call_hook(&BEFORE_EACH, &test.desc);
                 let join_handle = run_test(
                     opts,
                     !opts.run_tests,
@@ -369,6 +411,7 @@ fn calc_timeout(timeout_queue: &VecDeque<TimeoutEntry>) -> Option<Duration> {
                     }
                 }
             }
+            call_hook(&AFTER_EACH, &completed_test.desc);
 
             let event = TestEvent::TeResult(completed_test);
             notify_about_test_event(event)?;
@@ -381,8 +424,10 @@ fn calc_timeout(timeout_queue: &VecDeque<TimeoutEntry>) -> Option<Duration> {
         for (id, b) in filtered_benchs {
             let event = TestEvent::TeWait(b.desc.clone());
             notify_about_test_event(event)?;
+            call_hook(&BEFORE_EACH, &b.desc);
             run_test(opts, false, id, b, run_strategy, tx.clone(), Concurrent::No);
             let completed_test = rx.recv().unwrap();
+            call_hook(&AFTER_EACH, &completed_test.desc);
 
             let event = TestEvent::TeResult(completed_test);
             notify_about_test_event(event)?;
@@ -391,6 +436,35 @@ fn calc_timeout(timeout_queue: &VecDeque<TimeoutEntry>) -> Option<Duration> {
     Ok(())
 }
 
+/// Returns why `filter_tests` ignores `desc` whatever its `#[ignore]`: it is
+/// meant for another target, or needs more than `--test-cu-budget`.
+#[cfg(not(bootstrap))]
+pub(crate) fn filter_ignore_reason(opts: &TestOpts, desc: &TestDesc) -> Option<&'static str> {
+    let over_budget = match (desc.min_compute_units, opts.test_cu_budget) {
+        (Some(needed), Some(budget)) => needed > budget,
+        _ => false,
+    };
+    desc.target_filter.ignore_reason().or_else(|| over_budget.then(|| "insufficient CU budget"))
+}
+
+#[cfg(bootstrap)]
+pub(crate) fn filter_ignore_reason(_opts: &TestOpts, _desc: &TestDesc) -> Option<&'static str> {
+    None
+}
+
+/// Selects the tests to run. The filters are applied in this order, each one
+/// only seeing the tests kept by the ones before it:
+///
+/// 1. the name filters and `--skip`,
+/// 2. `--exclude-should-panic`,
+/// 3. `--ignored` / `--include-ignored`, which un-ignore the remaining tests,
+/// 4. the tests' `target_filter` and `min_compute_units`, which ignore tests
+///    meant for another target or needing more than `--test-cu-budget`, even
+///    with `--ignored` / `--include-ignored`.
+///
+/// So an ignored `#[should_panic]` test stays excluded with both
+/// `--exclude-should-panic` and `--include-ignored`. The result is sorted by
+/// name.
 pub fn filter_tests(opts: &TestOpts, tests: Vec<TestDescAndFn>) -> Vec<TestDescAndFn> {
     let mut filtered = tests;
     let matches_filter = |test: &TestDescAndFn, filter: &str| {
@@ -427,6 +501,15 @@ pub fn filter_tests(opts: &TestOpts, tests: Vec<TestDescAndFn>) -> Vec<TestDescA
         RunIgnored::No => {}
     }
 
+    // Ignore tests that can't run on this target or within the compute budget
+    #[cfg(not(bootstrap))]
+    filtered.iter_mut().for_each(|test| {
+        if let Some(reason) = filter_ignore_reason(opts, &test.desc) {
+            test.desc.ignore = true;
+            test.desc.ignore_message = Some(reason);
+        }
+    });
+
     // Sort the tests alphabetically
     filtered.sort_by(|t1, t2| t1.desc.name.as_slice().cmp(t2.desc.name.as_slice()));
 
@@ -479,6 +562,7 @@ struct TestRunOpts {
         pub nocapture: bool,
         pub concurrency: Concurrent,
         pub time: Option<time::TestTimeOptions>,
+        pub measure_cu: bool,
     }
 
     fn run_test_inner(
@@ -497,6 +581,7 @@ fn run_test_inner(
                 desc,
                 opts.nocapture,
                 opts.time.is_some(),
+                opts.measure_cu,
                 testfn,
                 monitor_ch,
                 opts.time,
@@ -535,8 +620,13 @@ fn run_test_inner(
         }
     }
 
-    let test_run_opts =
-        TestRunOpts { strategy, nocapture: opts.nocapture, concurrency, time: opts.time_options };
+    let test_run_opts = TestRunOpts {
+        strategy,
+        nocapture: opts.nocapture,
+        concurrency,
+        time: opts.time_options,
+        measure_cu: opts.report_cu || opts.show_cu || opts.test_cu_limit.is_some(),
+    };
 
     match testfn {
         DynBenchFn(bencher) => {
@@ -588,6 +678,7 @@ fn run_test_in_process(
     desc: TestDesc,
     nocapture: bool,
     report_time: bool,
+    measure_cu: bool,
     testfn: Box<dyn FnOnce() + Send>,
     monitor_ch: Sender<CompletedTest>,
     time_opts: Option<time::TestTimeOptions>,
@@ -600,11 +691,15 @@ fn run_test_in_process(
     }
 
     let start = report_time.then(Instant::now);
+    let start_cu = if measure_cu { helpers::compute_units::remaining() } else { None };
     let result = catch_unwind(AssertUnwindSafe(testfn));
     let exec_time = start.map(|start| {
         let duration = start.elapsed();
         TestExecTime(duration)
     });
+    let compute_units = start_cu.and_then(|start_cu| {
+        helpers::compute_units::remaining().map(|end_cu| start_cu.saturating_sub(end_cu))
+    });
 
     io::set_output_capture(None);
 
@@ -613,7 +708,8 @@ fn run_test_in_process(
         Err(e) => calc_result(&desc, Err(e.as_ref()), &time_opts, &exec_time),
     };
     let stdout = data.lock().unwrap_or_else(|e| e.into_inner()).to_vec();
-    let message = CompletedTest::new(id, desc, test_result, exec_time, stdout);
+    let mut message = CompletedTest::new(id, desc, test_result, exec_time, stdout);
+    message.compute_units = compute_units;
     monitor_ch.send(message).unwrap();
 }
 
@@ -39,8 +39,30 @@ pub enum OutputFormat {
     Terse,
     /// JSON output
     Json,
+    /// A single JSON object with all results, written at the end of the run
+    JsonSummary,
     /// JUnit output
     Junit,
+    /// TAP (Test Anything Protocol) output
+    Tap,
+}
+
+/// Format of the entries written to the logfile
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum LogfileFormat {
+    /// One `<result> <name>` line per test
+    Text,
+    /// One JSON object per line and test
+    Json,
+}
+
+/// Format of the benchmark results output
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum BenchFormat {
+    /// Reported by the output format like any other result
+    Text,
+    /// A `name,median_ns,deviation_ns` CSV table in place of the usual output
+    Csv,
 }
 
 /// Whether ignored test should be run or not
@@ -1,9 +1,17 @@
+// ignore-tidy-todo
+
 use super::*;
 
 use crate::{
-    bench::Bencher,
+    bench::{BenchSamples, Bencher},
+    cli::{optgroups, resolve_format},
     console::OutputLocation,
-    formatters::PrettyFormatter,
+    event::{CompletedTest, TestEvent},
+    formatters::{
+        BenchCsvFormatter, JsonFormatter, JsonSummaryFormatter, JunitFormatter, OutputFormatter,
+        PrettyFormatter, TapFormatter, TeeFormatter, TerseFormatter, JSON_SCHEMA_VERSION,
+    },
+    helpers::concurrency::{get_concurrency, resolve_test_threads, AUTO_TEST_THREADS_CAP},
     options::OutputFormat,
     test::{
         filter_tests,
@@ -48,6 +56,23 @@ fn new() -> TestOpts {
             test_threads: None,
             skip: vec![],
             time_options: None,
+            slowest: None,
+            time_threshold: None,
+            capture_max_bytes: None,
+            logfile_format: LogfileFormat::Text,
+            no_capture_on_success: false,
+            report_cu: false,
+            list_failures_to: None,
+            test_cu_limit: None,
+            tee_output: false,
+            bpf_test_markers: false,
+            junit_output: None,
+            run_id: None,
+            test_cu_budget: None,
+            bench_format: BenchFormat::Text,
+            list_output: None,
+            error_on_ignored: false,
+            show_cu: false,
             options: Options::new(),
         }
     }
@@ -59,12 +84,18 @@ fn one_ignored_one_unignored_test() -> Vec<TestDescAndFn> {
             desc: TestDesc {
                 name: StaticTestName("1"),
                 ignore: true,
+                #[cfg(not(bootstrap))]
+                ignore_message: None,
                 should_panic: ShouldPanic::No,
                 allow_fail: false,
                 #[cfg(not(bootstrap))]
                 compile_fail: false,
                 #[cfg(not(bootstrap))]
                 no_run: false,
+                #[cfg(not(bootstrap))]
+                target_filter: TargetFilter::Any,
+                #[cfg(not(bootstrap))]
+                min_compute_units: None,
                 test_type: TestType::Unknown,
             },
             testfn: DynTestFn(Box::new(move || {})),
@@ -73,12 +104,18 @@ fn one_ignored_one_unignored_test() -> Vec<TestDescAndFn> {
             desc: TestDesc {
                 name: StaticTestName("2"),
                 ignore: false,
+                #[cfg(not(bootstrap))]
+                ignore_message: None,
                 should_panic: ShouldPanic::No,
                 allow_fail: false,
                 #[cfg(not(bootstrap))]
                 compile_fail: false,
                 #[cfg(not(bootstrap))]
                 no_run: false,
+                #[cfg(not(bootstrap))]
+                target_filter: TargetFilter::Any,
+                #[cfg(not(bootstrap))]
+                min_compute_units: None,
                 test_type: TestType::Unknown,
             },
             testfn: DynTestFn(Box::new(move || {})),
@@ -95,12 +132,18 @@ fn f() {
         desc: TestDesc {
             name: StaticTestName("whatever"),
             ignore: true,
+            #[cfg(not(bootstrap))]
+            ignore_message: None,
             should_panic: ShouldPanic::No,
             allow_fail: false,
             #[cfg(not(bootstrap))]
             compile_fail: false,
             #[cfg(not(bootstrap))]
             no_run: false,
+            #[cfg(not(bootstrap))]
+            target_filter: TargetFilter::Any,
+            #[cfg(not(bootstrap))]
+            min_compute_units: None,
             test_type: TestType::Unknown,
         },
         testfn: DynTestFn(Box::new(f)),
@@ -118,12 +161,18 @@ fn f() {}
         desc: TestDesc {
             name: StaticTestName("whatever"),
             ignore: true,
+            #[cfg(not(bootstrap))]
+            ignore_message: None,
             should_panic: ShouldPanic::No,
             allow_fail: false,
             #[cfg(not(bootstrap))]
             compile_fail: false,
             #[cfg(not(bootstrap))]
             no_run: false,
+            #[cfg(not(bootstrap))]
+            target_filter: TargetFilter::Any,
+            #[cfg(not(bootstrap))]
+            min_compute_units: None,
             test_type: TestType::Unknown,
         },
         testfn: DynTestFn(Box::new(f)),
@@ -145,12 +194,18 @@ fn f() {
         desc: TestDesc {
             name: StaticTestName("whatever"),
             ignore: false,
+            #[cfg(not(bootstrap))]
+            ignore_message: None,
             should_panic: ShouldPanic::Yes,
             allow_fail: false,
             #[cfg(not(bootstrap))]
             compile_fail: false,
             #[cfg(not(bootstrap))]
             no_run: false,
+            #[cfg(not(bootstrap))]
+            target_filter: TargetFilter::Any,
+            #[cfg(not(bootstrap))]
+            min_compute_units: None,
             test_type: TestType::Unknown,
         },
         testfn: DynTestFn(Box::new(f)),
@@ -172,12 +227,18 @@ fn f() {
         desc: TestDesc {
             name: StaticTestName("whatever"),
             ignore: false,
+            #[cfg(not(bootstrap))]
+            ignore_message: None,
             should_panic: ShouldPanic::YesWithMessage("error message"),
             allow_fail: false,
             #[cfg(not(bootstrap))]
             compile_fail: false,
             #[cfg(not(bootstrap))]
             no_run: false,
+            #[cfg(not(bootstrap))]
+            target_filter: TargetFilter::Any,
+            #[cfg(not(bootstrap))]
+            min_compute_units: None,
             test_type: TestType::Unknown,
         },
         testfn: DynTestFn(Box::new(f)),
@@ -204,12 +265,18 @@ fn f() {
         desc: TestDesc {
             name: StaticTestName("whatever"),
             ignore: false,
+            #[cfg(not(bootstrap))]
+            ignore_message: None,
             should_panic: ShouldPanic::YesWithMessage(expected),
             allow_fail: false,
             #[cfg(not(bootstrap))]
             compile_fail: false,
             #[cfg(not(bootstrap))]
             no_run: false,
+            #[cfg(not(bootstrap))]
+            target_filter: TargetFilter::Any,
+            #[cfg(not(bootstrap))]
+            min_compute_units: None,
             test_type: TestType::Unknown,
         },
         testfn: DynTestFn(Box::new(f)),
@@ -240,12 +307,18 @@ fn f() {
         desc: TestDesc {
             name: StaticTestName("whatever"),
             ignore: false,
+            #[cfg(not(bootstrap))]
+            ignore_message: None,
             should_panic: ShouldPanic::YesWithMessage(expected),
             allow_fail: false,
             #[cfg(not(bootstrap))]
             compile_fail: false,
             #[cfg(not(bootstrap))]
             no_run: false,
+            #[cfg(not(bootstrap))]
+            target_filter: TargetFilter::Any,
+            #[cfg(not(bootstrap))]
+            min_compute_units: None,
             test_type: TestType::Unknown,
         },
         testfn: DynTestFn(Box::new(f)),
@@ -268,12 +341,18 @@ fn f() {}
             desc: TestDesc {
                 name: StaticTestName("whatever"),
                 ignore: false,
+                #[cfg(not(bootstrap))]
+                ignore_message: None,
                 should_panic,
                 allow_fail: false,
                 #[cfg(not(bootstrap))]
                 compile_fail: false,
                 #[cfg(not(bootstrap))]
                 no_run: false,
+                #[cfg(not(bootstrap))]
+                target_filter: TargetFilter::Any,
+                #[cfg(not(bootstrap))]
+                min_compute_units: None,
                 test_type: TestType::Unknown,
             },
             testfn: DynTestFn(Box::new(f)),
@@ -304,12 +383,18 @@ fn f() {}
         desc: TestDesc {
             name: StaticTestName("whatever"),
             ignore: false,
+            #[cfg(not(bootstrap))]
+            ignore_message: None,
             should_panic: ShouldPanic::No,
             allow_fail: false,
             #[cfg(not(bootstrap))]
             compile_fail: false,
             #[cfg(not(bootstrap))]
             no_run: false,
+            #[cfg(not(bootstrap))]
+            target_filter: TargetFilter::Any,
+            #[cfg(not(bootstrap))]
+            min_compute_units: None,
             test_type: TestType::Unknown,
         },
         testfn: DynTestFn(Box::new(f)),
@@ -341,12 +426,18 @@ fn f() {}
         desc: TestDesc {
             name: StaticTestName("whatever"),
             ignore: false,
+            #[cfg(not(bootstrap))]
+            ignore_message: None,
             should_panic: ShouldPanic::No,
             allow_fail: false,
             #[cfg(not(bootstrap))]
             compile_fail: false,
             #[cfg(not(bootstrap))]
             no_run: false,
+            #[cfg(not(bootstrap))]
+            target_filter: TargetFilter::Any,
+            #[cfg(not(bootstrap))]
+            min_compute_units: None,
             test_type,
         },
         testfn: DynTestFn(Box::new(f)),
@@ -382,16 +473,26 @@ fn typed_test_desc(test_type: TestType) -> TestDesc {
     TestDesc {
         name: StaticTestName("whatever"),
         ignore: false,
+        #[cfg(not(bootstrap))]
+        ignore_message: None,
         should_panic: ShouldPanic::No,
         allow_fail: false,
         #[cfg(not(bootstrap))]
         compile_fail: false,
         #[cfg(not(bootstrap))]
         no_run: false,
+        #[cfg(not(bootstrap))]
+        target_filter: TargetFilter::Any,
+        #[cfg(not(bootstrap))]
+        min_compute_units: None,
         test_type,
     }
 }
 
+fn named_test_desc(name: &'static str) -> TestDesc {
+    TestDesc { name: StaticTestName(name), ..typed_test_desc(TestType::UnitTest) }
+}
+
 fn test_exec_time(millis: u64) -> TestExecTime {
     TestExecTime(Duration::from_millis(millis))
 }
@@ -445,6 +546,55 @@ fn parse_show_output_flag() {
     assert!(opts.options.display_output);
 }
 
+#[test]
+fn parse_test_threads_zero_means_auto() {
+    let args = vec!["progname".to_string(), "--test-threads=0".to_string()];
+    let opts = parse_opts(&args).unwrap().unwrap();
+    assert_eq!(opts.test_threads, Some(0));
+    assert_eq!(
+        resolve_test_threads(opts.test_threads),
+        get_concurrency().min(AUTO_TEST_THREADS_CAP)
+    );
+    assert_eq!(resolve_test_threads(Some(3)), 3);
+
+    // The flag's help spells out the cap.
+    let help = optgroups().usage("");
+    let cap = format!("0 uses the number of CPUs, at most {}", AUTO_TEST_THREADS_CAP);
+    assert!(help.contains(&cap));
+}
+
+#[test]
+fn tee_output_needs_the_pretty_format() {
+    let args = |format: &str| {
+        vec!["progname", "-Zunstable-options", "--tee-output", format]
+            .into_iter()
+            .map(String::from)
+            .collect::<Vec<_>>()
+    };
+    assert!(parse_opts(&args("--format=pretty")).unwrap().unwrap().tee_output);
+    for format in &["--format=terse", "--format=json", "--format=tap", "--format=junit"] {
+        let err = parse_opts(&args(format)).unwrap().unwrap_err();
+        assert_eq!(err, "The \"tee-output\" flag is only supported with --format=pretty");
+    }
+}
+
+#[test]
+fn format_defaults_to_rust_test_format() {
+    let from_env = resolve_format(None, false, Some("terse"), false).unwrap();
+    let from_flag = resolve_format(Some("pretty"), false, Some("terse"), false).unwrap();
+    let from_quiet = resolve_format(None, true, Some("pretty"), false).unwrap();
+    let from_invalid_env = resolve_format(None, false, Some("no-such-format"), false).unwrap();
+    let from_unstable_env = resolve_format(None, false, Some("json"), false).unwrap();
+    let without_env = resolve_format(None, false, None, false).unwrap();
+
+    assert_eq!(from_env, OutputFormat::Terse);
+    assert_eq!(from_flag, OutputFormat::Pretty);
+    assert_eq!(from_quiet, OutputFormat::Terse);
+    assert_eq!(from_invalid_env, OutputFormat::Pretty);
+    assert_eq!(from_unstable_env, OutputFormat::Pretty);
+    assert_eq!(without_env, OutputFormat::Pretty);
+}
+
 #[test]
 fn parse_include_ignored_flag() {
     let args = vec!["progname".to_string(), "filter".to_string(), "--include-ignored".to_string()];
@@ -497,12 +647,18 @@ pub fn exclude_should_panic_option() {
         desc: TestDesc {
             name: StaticTestName("3"),
             ignore: false,
+            #[cfg(not(bootstrap))]
+            ignore_message: None,
             should_panic: ShouldPanic::Yes,
             allow_fail: false,
             #[cfg(not(bootstrap))]
             compile_fail: false,
             #[cfg(not(bootstrap))]
             no_run: false,
+            #[cfg(not(bootstrap))]
+            target_filter: TargetFilter::Any,
+            #[cfg(not(bootstrap))]
+            min_compute_units: None,
             test_type: TestType::Unknown,
         },
         testfn: DynTestFn(Box::new(move || {})),
@@ -514,6 +670,108 @@ pub fn exclude_should_panic_option() {
     assert!(filtered.iter().all(|test| test.desc.should_panic == ShouldPanic::No));
 }
 
+fn filter_order_tests() -> Vec<TestDescAndFn> {
+    vec![
+        ("a::ignored", true, ShouldPanic::No),
+        ("a::ignored_panics", true, ShouldPanic::Yes),
+        ("a::panics", false, ShouldPanic::Yes),
+        ("b::ignored", true, ShouldPanic::No),
+        ("b::plain", false, ShouldPanic::No),
+    ]
+    .into_iter()
+    .map(|(name, ignore, should_panic)| TestDescAndFn {
+        desc: TestDesc {
+            name: StaticTestName(name),
+            ignore,
+            should_panic,
+            ..typed_test_desc(TestType::Unknown)
+        },
+        testfn: DynTestFn(Box::new(move || {})),
+    })
+    .collect()
+}
+
+fn filtered_names(opts: &TestOpts) -> Vec<String> {
+    filter_tests(opts, filter_order_tests()).iter().map(|t| t.desc.name.to_string()).collect()
+}
+
+#[test]
+pub fn exclude_should_panic_applies_before_include_ignored() {
+    let opts =
+        TestOpts { run_ignored: RunIgnored::Yes, exclude_should_panic: true, ..TestOpts::new() };
+    assert_eq!(filtered_names(&opts), ["a::ignored", "b::ignored", "b::plain"]);
+
+    let opts = TestOpts { run_ignored: RunIgnored::Only, ..opts };
+    assert_eq!(filtered_names(&opts), ["a::ignored", "b::ignored"]);
+}
+
+#[test]
+pub fn name_filter_applies_before_include_ignored() {
+    let opts = TestOpts {
+        filters: vec!["a::".to_string()],
+        run_ignored: RunIgnored::Only,
+        ..TestOpts::new()
+    };
+    assert_eq!(filtered_names(&opts), ["a::ignored", "a::ignored_panics"]);
+
+    let opts = TestOpts { run_ignored: RunIgnored::Yes, ..opts };
+    let filtered = filter_tests(&opts, filter_order_tests());
+    let names: Vec<_> = filtered.iter().map(|t| t.desc.name.to_string()).collect();
+    assert_eq!(names, ["a::ignored", "a::ignored_panics", "a::panics"]);
+    assert!(filtered.iter().all(|t| !t.desc.ignore));
+}
+
+#[test]
+#[cfg(not(bootstrap))]
+#[cfg(not(target_arch = "bpf"))]
+pub fn bpf_only_tests_are_ignored_on_the_host() {
+    let test = |name, target_filter| TestDescAndFn {
+        desc: TestDesc { target_filter, ..named_test_desc(name) },
+        testfn: DynTestFn(Box::new(move || {})),
+    };
+    let tests = vec![test("bpf", TargetFilter::BpfOnly), test("host", TargetFilter::HostOnly)];
+
+    // `--include-ignored` does not run them either.
+    let opts = TestOpts { run_ignored: RunIgnored::Yes, ..TestOpts::new() };
+    let filtered = filter_tests(&opts, tests);
+    assert_eq!(filtered[0].desc.name.as_slice(), "bpf");
+    assert!(filtered[0].desc.ignore);
+    assert_eq!(filtered[0].desc.ignore_message, Some("BPF-only"));
+    assert!(!filtered[1].desc.ignore);
+    assert_eq!(filtered[1].desc.ignore_message, None);
+}
+
+#[test]
+#[cfg(not(bootstrap))]
+pub fn tests_over_the_cu_budget_are_ignored() {
+    let test = |name, min_compute_units| TestDescAndFn {
+        desc: TestDesc { min_compute_units, ..named_test_desc(name) },
+        testfn: DynTestFn(Box::new(move || {})),
+    };
+    let tests = || vec![test("cheap", Some(10_000)), test("costly", Some(400_000))];
+
+    let opts = TestOpts { test_cu_budget: Some(200_000), ..TestOpts::new() };
+    let filtered = filter_tests(&opts, tests());
+    assert!(!filtered[0].desc.ignore);
+    assert!(filtered[1].desc.ignore);
+    assert_eq!(filtered[1].desc.ignore_message, Some("insufficient CU budget"));
+
+    // Without a budget, every test runs.
+    let filtered = filter_tests(&TestOpts::new(), tests());
+    assert!(filtered.iter().all(|test| !test.desc.ignore));
+}
+
+#[test]
+pub fn name_filter_applies_before_exclude_should_panic() {
+    let opts = TestOpts {
+        filters: vec!["panics".to_string()],
+        exclude_should_panic: true,
+        run_ignored: RunIgnored::Yes,
+        ..TestOpts::new()
+    };
+    assert!(filtered_names(&opts).is_empty());
+}
+
 #[test]
 pub fn exact_filter_match() {
     fn tests() -> Vec<TestDescAndFn> {
@@ -523,12 +781,18 @@ fn tests() -> Vec<TestDescAndFn> {
                 desc: TestDesc {
                     name: StaticTestName(name),
                     ignore: false,
+                    #[cfg(not(bootstrap))]
+                    ignore_message: None,
                     should_panic: ShouldPanic::No,
                     allow_fail: false,
                     #[cfg(not(bootstrap))]
                     compile_fail: false,
                     #[cfg(not(bootstrap))]
                     no_run: false,
+                    #[cfg(not(bootstrap))]
+                    target_filter: TargetFilter::Any,
+                    #[cfg(not(bootstrap))]
+                    min_compute_units: None,
                     test_type: TestType::Unknown,
                 },
                 testfn: DynTestFn(Box::new(move || {})),
@@ -619,12 +883,18 @@ fn testfn() {}
                 desc: TestDesc {
                     name: DynTestName((*name).clone()),
                     ignore: false,
+                    #[cfg(not(bootstrap))]
+                    ignore_message: None,
                     should_panic: ShouldPanic::No,
                     allow_fail: false,
                     #[cfg(not(bootstrap))]
                     compile_fail: false,
                     #[cfg(not(bootstrap))]
                     no_run: false,
+                    #[cfg(not(bootstrap))]
+                    target_filter: TargetFilter::Any,
+                    #[cfg(not(bootstrap))]
+                    min_compute_units: None,
                     test_type: TestType::Unknown,
                 },
                 testfn: DynTestFn(Box::new(testfn)),
@@ -700,12 +970,18 @@ fn f(_: &mut Bencher) {}
     let desc = TestDesc {
         name: StaticTestName("f"),
         ignore: false,
+        #[cfg(not(bootstrap))]
+        ignore_message: None,
         should_panic: ShouldPanic::No,
         allow_fail: false,
         #[cfg(not(bootstrap))]
         compile_fail: false,
         #[cfg(not(bootstrap))]
         no_run: false,
+        #[cfg(not(bootstrap))]
+        target_filter: TargetFilter::Any,
+        #[cfg(not(bootstrap))]
+        min_compute_units: None,
         test_type: TestType::Unknown,
     };
 
@@ -724,12 +1000,18 @@ fn f(b: &mut Bencher) {
     let desc = TestDesc {
         name: StaticTestName("f"),
         ignore: false,
+        #[cfg(not(bootstrap))]
+        ignore_message: None,
         should_panic: ShouldPanic::No,
         allow_fail: false,
         #[cfg(not(bootstrap))]
         compile_fail: false,
         #[cfg(not(bootstrap))]
         no_run: false,
+        #[cfg(not(bootstrap))]
+        target_filter: TargetFilter::Any,
+        #[cfg(not(bootstrap))]
+        min_compute_units: None,
         test_type: TestType::Unknown,
     };
 
@@ -742,24 +1024,36 @@ fn should_sort_failures_before_printing_them() {
     let test_a = TestDesc {
         name: StaticTestName("a"),
         ignore: false,
+        #[cfg(not(bootstrap))]
+        ignore_message: None,
         should_panic: ShouldPanic::No,
         allow_fail: false,
         #[cfg(not(bootstrap))]
         compile_fail: false,
         #[cfg(not(bootstrap))]
         no_run: false,
+        #[cfg(not(bootstrap))]
+        target_filter: TargetFilter::Any,
+        #[cfg(not(bootstrap))]
+        min_compute_units: None,
         test_type: TestType::Unknown,
     };
 
     let test_b = TestDesc {
         name: StaticTestName("b"),
         ignore: false,
+        #[cfg(not(bootstrap))]
+        ignore_message: None,
         should_panic: ShouldPanic::No,
         allow_fail: false,
         #[cfg(not(bootstrap))]
         compile_fail: false,
         #[cfg(not(bootstrap))]
         no_run: false,
+        #[cfg(not(bootstrap))]
+        target_filter: TargetFilter::Any,
+        #[cfg(not(bootstrap))]
+        min_compute_units: None,
         test_type: TestType::Unknown,
     };
 
@@ -780,6 +1074,20 @@ fn should_sort_failures_before_printing_them() {
         options: Options::new(),
         not_failures: Vec::new(),
         time_failures: Vec::new(),
+        slowest: None,
+        time_threshold: None,
+        capture_max_bytes: None,
+        no_capture_on_success: false,
+        tee_output: false,
+        test_markers: None,
+        report_cu: false,
+        compute_units: None,
+        show_cu: false,
+        test_compute_units: None,
+        test_cu_limit: None,
+        logfile_format: LogfileFormat::Text,
+        ignored_tests: Vec::new(),
+        forbidden_ignores: false,
     };
 
     out.write_failures(&st).unwrap();
@@ -792,3 +1100,982 @@ fn should_sort_failures_before_printing_them() {
     let bpos = s.find("b").unwrap();
     assert!(apos < bpos);
 }
+
+#[test]
+#[cfg(not(target_arch = "bpf"))]
+fn terminal_factory_failure_falls_back_to_raw_output() {
+    let output = console::output_location_from(|| panic!("terminal unavailable"));
+    assert!(matches!(output, OutputLocation::Raw(_)));
+
+    let output = console::output_location_from(|| None);
+    assert!(matches!(output, OutputLocation::Raw(_)));
+}
+
+#[test]
+fn no_color_disables_auto_color() {
+    let opts = TestOpts { color: AutoColor, ..TestOpts::new() };
+    assert!(!opts.resolve_color(true, true));
+}
+
+#[test]
+fn always_color_overrides_no_color() {
+    let opts = TestOpts { color: AlwaysColor, ..TestOpts::new() };
+    assert!(opts.resolve_color(true, true));
+    assert!(opts.resolve_color(false, true));
+}
+
+#[test]
+fn auto_color_without_no_color() {
+    let opts = TestOpts { color: AutoColor, ..TestOpts::new() };
+    assert!(opts.resolve_color(true, false));
+    assert!(!opts.resolve_color(false, false));
+
+    let opts = TestOpts { color: AutoColor, nocapture: true, ..TestOpts::new() };
+    assert!(!opts.resolve_color(true, false));
+}
+
+fn raw_output(out: &OutputLocation<Vec<u8>>) -> String {
+    match out {
+        &OutputLocation::Raw(ref m) => String::from_utf8_lossy(&m[..]).into_owned(),
+        &OutputLocation::Pretty(_) => unreachable!(),
+    }
+}
+
+#[test]
+fn json_timeout_is_reported_once() {
+    let desc = typed_test_desc(TestType::UnitTest);
+    let st = console::ConsoleTestState::new(&TestOpts::new()).unwrap();
+    let mut out = JsonFormatter::new(OutputLocation::Raw(Vec::new()));
+
+    out.write_test_start(&desc).unwrap();
+    out.write_timeout(&desc).unwrap();
+    out.write_result(&desc, &TrOk, None, &[], &st).unwrap();
+
+    let s = raw_output(out.output_location());
+    let timeouts: Vec<_> = s.lines().filter(|l| l.contains(r#""event": "timeout""#)).collect();
+    assert_eq!(timeouts, vec![r#"{ "type": "test", "event": "timeout", "name": "whatever" }"#]);
+    assert_eq!(s.lines().filter(|l| l.contains(r#""event": "ok""#)).count(), 1);
+}
+
+// Checks that every string literal in a JSON line is terminated and free of
+// unescaped control characters, and that the line is a single object.
+fn is_well_formed_json_line(line: &str) -> bool {
+    let mut in_string = false;
+    let mut escaped = false;
+    for c in line.chars() {
+        if in_string {
+            if escaped {
+                escaped = false;
+            } else if c == '\\' {
+                escaped = true;
+            } else if c == '"' {
+                in_string = false;
+            } else if c.is_control() {
+                return false;
+            }
+        } else if c == '"' {
+            in_string = true;
+        } else if c.is_control() {
+            return false;
+        }
+    }
+    !in_string && line.starts_with('{') && line.ends_with('}')
+}
+
+#[test]
+fn json_escapes_test_names() {
+    let st = console::ConsoleTestState::new(&TestOpts::new()).unwrap();
+    let mut out = JsonFormatter::new(OutputLocation::Raw(Vec::new()));
+    let desc = TestDesc {
+        name: DynTestName("quote\"d\nname\\".to_string()),
+        ..typed_test_desc(TestType::UnitTest)
+    };
+
+    out.write_test_start(&desc).unwrap();
+    out.write_timeout(&desc).unwrap();
+    out.write_result(&desc, &TrFailedMsg("oops".to_string()), None, &[], &st).unwrap();
+
+    let s = raw_output(out.output_location());
+    let lines: Vec<_> = s.lines().collect();
+    assert_eq!(lines.len(), 3);
+    for line in &lines {
+        assert!(is_well_formed_json_line(line), "invalid JSON: {}", line);
+        assert!(line.contains(r#""name": "quote\"d\nname\\""#), "{}", line);
+    }
+}
+
+#[test]
+#[cfg(not(bootstrap))]
+fn json_ignored_test_reports_reason() {
+    let st = console::ConsoleTestState::new(&TestOpts::new()).unwrap();
+    let mut out = JsonFormatter::new(OutputLocation::Raw(Vec::new()));
+
+    let desc = TestDesc {
+        ignore: true,
+        ignore_message: Some("needs \"network\""),
+        ..typed_test_desc(TestType::UnitTest)
+    };
+    out.write_result(&desc, &TrIgnored, None, &[], &st).unwrap();
+    let desc = TestDesc { ignore: true, ignore_message: None, ..desc };
+    out.write_result(&desc, &TrIgnored, None, &[], &st).unwrap();
+
+    let s = raw_output(out.output_location());
+    let lines: Vec<_> = s.lines().collect();
+    assert_eq!(
+        lines,
+        vec![
+            r#"{ "type": "test", "name": "whatever", "event": "ignored", "reason": "needs \"network\"" }"#,
+            r#"{ "type": "test", "name": "whatever", "event": "ignored" }"#,
+        ]
+    );
+}
+
+#[test]
+fn slowest_tests_are_ranked_by_exec_time() {
+    let mut st =
+        console::ConsoleTestState::new(&TestOpts { slowest: Some(2), ..TestOpts::new() }).unwrap();
+    let slowest = st.slowest.as_mut().unwrap();
+    slowest.record(&named_test_desc("medium"), &test_exec_time(200));
+    slowest.record(&named_test_desc("fast"), &test_exec_time(10));
+    slowest.record(&named_test_desc("slow"), &test_exec_time(3000));
+
+    let ranking: Vec<_> = slowest.sorted().into_iter().map(|(name, _)| name).collect();
+    assert_eq!(ranking, vec!["slow", "medium"]);
+
+    let mut out = PrettyFormatter::new(OutputLocation::Raw(Vec::new()), false, 10, false, None);
+    out.write_run_finish(&st).unwrap();
+    let s = raw_output(out.output_location());
+    assert!(s.contains("slowest tests:\n    3.000s slow\n    0.200s medium\n"));
+}
+
+#[test]
+fn slowest_report_is_skipped_without_timings() {
+    let st =
+        console::ConsoleTestState::new(&TestOpts { slowest: Some(2), ..TestOpts::new() }).unwrap();
+
+    let mut out = PrettyFormatter::new(OutputLocation::Raw(Vec::new()), false, 10, false, None);
+    out.write_run_finish(&st).unwrap();
+    assert!(!raw_output(out.output_location()).contains("slowest tests"));
+}
+
+#[test]
+fn slow_test_over_threshold_is_a_time_failure() {
+    let opts = TestOpts { time_threshold: Some(Duration::from_millis(100)), ..TestOpts::new() };
+    let mut st = console::ConsoleTestState::new(&opts).unwrap();
+    let mut out = JsonFormatter::new(OutputLocation::Raw(Vec::new()));
+
+    for (i, (name, millis)) in [("fast", 10), ("slow", 500)].iter().enumerate() {
+        let completed_test = CompletedTest::new(
+            TestId(i),
+            named_test_desc(name),
+            TrOk,
+            Some(test_exec_time(*millis)),
+            Vec::new(),
+        );
+        console::on_test_event(&TestEvent::TeResult(completed_test), &mut st, &mut out).unwrap();
+    }
+
+    assert_eq!(st.passed, 1);
+    assert_eq!(st.failed, 1);
+    assert_eq!(st.time_failures.len(), 1);
+    assert_eq!(st.time_failures[0].0.name.as_slice(), "slow");
+    assert!(!out.write_run_finish(&st).unwrap());
+}
+
+#[test]
+fn reported_compute_units_are_summed_over_the_suite() {
+    let opts = TestOpts { report_cu: true, ..TestOpts::new() };
+    let mut st = console::ConsoleTestState::new(&opts).unwrap();
+    let mut out = PrettyFormatter::new(OutputLocation::Raw(Vec::new()), false, 10, false, None);
+
+    for (i, (name, compute_units)) in [("first", 1200), ("second", 345)].iter().enumerate() {
+        let mut completed_test =
+            CompletedTest::new(TestId(i), named_test_desc(name), TrOk, None, Vec::new());
+        completed_test.compute_units = Some(*compute_units);
+        console::on_test_event(&TestEvent::TeResult(completed_test), &mut st, &mut out).unwrap();
+    }
+
+    assert_eq!(st.compute_units, Some(1545));
+    out.write_run_finish(&st).unwrap();
+    assert!(raw_output(out.output_location()).contains("\ncompute units consumed: 1545\n"));
+}
+
+#[test]
+fn compute_units_are_omitted_when_not_measured() {
+    let opts = TestOpts { report_cu: true, ..TestOpts::new() };
+    let mut st = console::ConsoleTestState::new(&opts).unwrap();
+    let mut out = PrettyFormatter::new(OutputLocation::Raw(Vec::new()), false, 10, false, None);
+
+    let desc = typed_test_desc(TestType::UnitTest);
+    let completed_test = CompletedTest::new(TestId(0), desc, TrOk, None, Vec::new());
+    console::on_test_event(&TestEvent::TeResult(completed_test), &mut st, &mut out).unwrap();
+
+    out.write_run_finish(&st).unwrap();
+    assert!(!raw_output(out.output_location()).contains("compute units"));
+}
+
+#[test]
+fn show_cu_appends_compute_units_to_passing_tests() {
+    let opts = TestOpts { show_cu: true, ..TestOpts::new() };
+    let mut st = console::ConsoleTestState::new(&opts).unwrap();
+    let mut out = PrettyFormatter::new(OutputLocation::Raw(Vec::new()), false, 5, false, None);
+
+    let results = [("pass", TrOk, Some(1200)), ("fail", TrFailed, Some(345)), ("host", TrOk, None)];
+    for (i, (name, result, compute_units)) in results.iter().enumerate() {
+        out.write_test_start(&named_test_desc(name)).unwrap();
+        let mut completed_test =
+            CompletedTest::new(TestId(i), named_test_desc(name), result.clone(), None, Vec::new());
+        completed_test.compute_units = *compute_units;
+        console::on_test_event(&TestEvent::TeResult(completed_test), &mut st, &mut out).unwrap();
+    }
+
+    assert_eq!(
+        raw_output(out.output_location()),
+        "test pass ... ok (1200 CU)\ntest fail ... FAILED\ntest host ... ok\n"
+    );
+}
+
+#[test]
+fn show_cu_gives_passing_tests_a_line_in_terse_output() {
+    let opts = TestOpts { show_cu: true, ..TestOpts::new() };
+    let mut st = console::ConsoleTestState::new(&opts).unwrap();
+    let mut out = TerseFormatter::new(OutputLocation::Raw(Vec::new()), false, 5, true);
+
+    let results = [("pass", TrOk, Some(1200)), ("fail", TrFailed, Some(345)), ("host", TrOk, None)];
+    for (i, (name, result, compute_units)) in results.iter().enumerate() {
+        out.write_test_start(&named_test_desc(name)).unwrap();
+        let mut completed_test =
+            CompletedTest::new(TestId(i), named_test_desc(name), result.clone(), None, Vec::new());
+        completed_test.compute_units = *compute_units;
+        console::on_test_event(&TestEvent::TeResult(completed_test), &mut st, &mut out).unwrap();
+    }
+
+    assert_eq!(raw_output(out.output_location()), "test pass ... ok (1200 CU)\nF.");
+}
+
+#[test]
+fn show_cu_measures_compute_units_of_tests_it_runs() {
+    use crate::helpers::compute_units::MOCK_REMAINING;
+
+    fn run(opts: &TestOpts) -> String {
+        let test = |name, cost| TestDescAndFn {
+            desc: named_test_desc(name),
+            testfn: DynTestFn(Box::new(move || {
+                MOCK_REMAINING.with(|left| left.set(left.get().map(|left| left - cost)))
+            })),
+        };
+        let mut st = console::ConsoleTestState::new(opts).unwrap();
+        let mut out = PrettyFormatter::new(OutputLocation::Raw(Vec::new()), false, 5, false, None);
+        // A single test thread runs the tests on this one, which the mock is set for.
+        MOCK_REMAINING.with(|left| left.set(Some(200_000)));
+        crate::run_tests(opts, vec![test("a", 1200), test("b", 345)], |event| {
+            console::on_test_event(&event, &mut st, &mut out)
+        })
+        .unwrap();
+        MOCK_REMAINING.with(|left| left.set(None));
+        raw_output(out.output_location())
+    }
+
+    let opts = || TestOpts { run_tests: true, test_threads: Some(1), ..TestOpts::new() };
+    let shown = run(&TestOpts { show_cu: true, ..opts() });
+    assert!(shown.contains("test a ... ok (1200 CU)\ntest b ... ok (345 CU)\n"), "{}", shown);
+    let hidden = run(&opts());
+    assert!(hidden.contains("test a ... ok\ntest b ... ok\n"), "{}", hidden);
+}
+
+#[test]
+fn test_over_cu_limit_is_a_time_failure() {
+    let opts = TestOpts { test_cu_limit: Some(10_000), ..TestOpts::new() };
+    let mut st = console::ConsoleTestState::new(&opts).unwrap();
+    let mut out = JsonFormatter::new(OutputLocation::Raw(Vec::new()));
+
+    for (i, (name, compute_units)) in [("light", 2_000), ("heavy", 50_000)].iter().enumerate() {
+        let mut completed_test =
+            CompletedTest::new(TestId(i), named_test_desc(name), TrOk, None, Vec::new());
+        completed_test.compute_units = Some(*compute_units);
+        console::on_test_event(&TestEvent::TeResult(completed_test), &mut st, &mut out).unwrap();
+    }
+
+    assert_eq!(st.passed, 1);
+    assert_eq!(st.failed, 1);
+    assert_eq!(st.time_failures.len(), 1);
+    assert_eq!(st.time_failures[0].0.name.as_slice(), "heavy");
+    // Compute units are only summed up with `--report-cu`.
+    assert_eq!(st.compute_units, None);
+}
+
+#[test]
+fn tee_output_shows_output_as_tests_finish() {
+    let desc = named_test_desc("noisy");
+    let opts = TestOpts { tee_output: true, ..TestOpts::new() };
+    let mut st = console::ConsoleTestState::new(&opts).unwrap();
+    let mut out = PrettyFormatter::new(OutputLocation::Raw(Vec::new()), false, 5, false, None);
+
+    out.write_test_start(&desc).unwrap();
+    let completed_test =
+        CompletedTest::new(TestId(0), desc, TrFailed, None, b"hello\nworld".to_vec());
+    console::on_test_event(&TestEvent::TeResult(completed_test), &mut st, &mut out).unwrap();
+
+    assert_eq!(
+        raw_output(out.output_location()),
+        "test noisy ... \n---- noisy stdout ----\nhello\nworld\ntest noisy ... FAILED\n"
+    );
+    // The output is still kept for the report at the end of the run.
+    assert_eq!(st.failures[0].1, b"hello\nworld");
+}
+
+#[test]
+fn single_threaded_json_events_follow_test_order() {
+    let test = |name, millis| TestDescAndFn {
+        desc: named_test_desc(name),
+        testfn: DynTestFn(Box::new(move || thread::sleep(Duration::from_millis(millis)))),
+    };
+    // The slowest test goes first, so completion order would differ from test order.
+    let tests = vec![test("t1", 50), test("t2", 0), test("t3", 10)];
+    let opts = TestOpts { run_tests: true, test_threads: Some(1), ..TestOpts::new() };
+    let mut st = console::ConsoleTestState::new(&opts).unwrap();
+    let mut out = JsonFormatter::new(OutputLocation::Raw(Vec::new()));
+    crate::run_tests(&opts, tests, |event| console::on_test_event(&event, &mut st, &mut out))
+        .unwrap();
+
+    let output = raw_output(out.output_location());
+    let events: Vec<_> = output.lines().filter(|line| line.contains(r#""type": "test""#)).collect();
+    assert_eq!(
+        events,
+        [
+            r#"{ "type": "test", "event": "started", "name": "t1" }"#,
+            r#"{ "type": "test", "name": "t1", "event": "ok" }"#,
+            r#"{ "type": "test", "event": "started", "name": "t2" }"#,
+            r#"{ "type": "test", "name": "t2", "event": "ok" }"#,
+            r#"{ "type": "test", "event": "started", "name": "t3" }"#,
+            r#"{ "type": "test", "name": "t3", "event": "ok" }"#,
+        ]
+    );
+}
+
+#[test]
+fn json_summary_is_a_single_object() {
+    let opts = TestOpts::new();
+    let mut st = console::ConsoleTestState::new(&opts).unwrap();
+    let mut out = JsonSummaryFormatter::new(OutputLocation::Raw(Vec::new()));
+    out.write_run_start(3).unwrap();
+
+    let results = [("t1", TrOk), ("t2", TrFailed), ("t3", TrIgnored)];
+    for (i, (name, result)) in results.iter().enumerate() {
+        let desc = named_test_desc(name);
+        let completed_test = CompletedTest::new(TestId(i), desc, result.clone(), None, Vec::new());
+        console::on_test_event(&TestEvent::TeResult(completed_test), &mut st, &mut out).unwrap();
+    }
+    assert!(!out.write_run_finish(&st).unwrap());
+
+    let expected = concat!(
+        r#"{ "tests": ["#,
+        r#"{ "type": "test", "name": "t1", "event": "ok" }, "#,
+        r#"{ "type": "test", "name": "t2", "event": "failed" }, "#,
+        r#"{ "type": "test", "name": "t3", "event": "ignored" }], "#,
+        r#""summary": { "result": "failed", "passed": 1, "failed": 1, "allowed_fail": 0, "#,
+        r#""ignored": 1, "measured": 0, "filtered_out": 0 } }"#,
+        "\n",
+    );
+    assert_eq!(raw_output(out.output_location()), expected);
+}
+
+#[test]
+fn empty_run_succeeds_with_pretty_and_json() {
+    fn run_empty(out: &mut dyn OutputFormatter) -> bool {
+        let test = TestDescAndFn {
+            desc: typed_test_desc(TestType::UnitTest),
+            testfn: DynTestFn(Box::new(move || {})),
+        };
+        let opts = TestOpts {
+            run_tests: true,
+            filters: vec!["no such test".to_string()],
+            ..TestOpts::new()
+        };
+        let mut st = console::ConsoleTestState::new(&opts).unwrap();
+        crate::run_tests(&opts, vec![test], |event| console::on_test_event(&event, &mut st, out))
+            .unwrap();
+        assert_eq!(st.total, 0);
+        out.write_run_finish(&st).unwrap()
+    }
+
+    let mut pretty = PrettyFormatter::new(OutputLocation::Raw(Vec::new()), false, 10, false, None);
+    assert!(run_empty(&mut pretty));
+    assert_eq!(
+        raw_output(pretty.output_location()),
+        "\nrunning 0 tests\n\n0 tests run: all 1 filtered out\n\n\
+         test result: ok. 0 passed; 0 failed; 0 ignored; 0 measured; 1 filtered out\n\n"
+    );
+
+    let mut json = JsonFormatter::new(OutputLocation::Raw(Vec::new()));
+    assert!(run_empty(&mut json));
+    assert_eq!(
+        raw_output(json.output_location()),
+        concat!(
+            r#"{ "type": "suite", "event": "started", "schema_version": 1, "test_count": 0 }"#,
+            "\n",
+            r#"{ "type": "suite", "event": "ok", "passed": 0, "failed": 0, "allowed_fail": 0, "#,
+            r#""ignored": 0, "measured": 0, "filtered_out": 1 }"#,
+            "\n",
+        )
+    );
+}
+
+#[test]
+fn run_id_is_added_to_the_suite_events() {
+    let opts = TestOpts::new();
+    let st = console::ConsoleTestState::new(&opts).unwrap();
+    let mut out = JsonFormatter::new(OutputLocation::Raw(Vec::new()))
+        .with_run_id(Some("ci-42 \"nightly\"".to_string()));
+    out.write_run_start(0).unwrap();
+    out.write_run_finish(&st).unwrap();
+
+    let output = raw_output(out.output_location());
+    let events: Vec<_> = output.lines().collect();
+    assert_eq!(events.len(), 2);
+    for event in events {
+        assert!(event.ends_with(r#", "run_id": "ci-42 \"nightly\"" }"#), "{}", event);
+    }
+}
+
+#[test]
+fn json_suite_start_reports_the_schema_version() {
+    let mut out = JsonFormatter::new(OutputLocation::Raw(Vec::new()));
+    out.write_run_start(3).unwrap();
+
+    assert_eq!(JSON_SCHEMA_VERSION, 1);
+    assert_eq!(
+        raw_output(out.output_location()),
+        format!(
+            r#"{{ "type": "suite", "event": "started", "schema_version": {}, "test_count": 3 }}"#,
+            JSON_SCHEMA_VERSION
+        ) + "\n"
+    );
+}
+
+#[test]
+fn captured_output_is_truncated_to_the_cap() {
+    let opts = TestOpts { capture_max_bytes: Some(8), ..TestOpts::new() };
+    let mut st = console::ConsoleTestState::new(&opts).unwrap();
+    let mut out = JsonFormatter::new(OutputLocation::Raw(Vec::new()));
+
+    let stdout = b"0123456789abcdef".to_vec();
+    let desc = typed_test_desc(TestType::UnitTest);
+    let completed_test = CompletedTest::new(TestId(0), desc, TrFailed, None, stdout);
+    console::on_test_event(&TestEvent::TeResult(completed_test), &mut st, &mut out).unwrap();
+
+    assert_eq!(st.failures.len(), 1);
+    let expected = format!("01234567{}", console::OUTPUT_TRUNCATED_MARKER);
+    assert_eq!(String::from_utf8_lossy(&st.failures[0].1), expected);
+}
+
+#[test]
+fn failed_test_names_are_listed_one_per_line() {
+    let path = std::env::temp_dir().join(format!("libtest-failures-{}", std::process::id()));
+    let mut st = console::ConsoleTestState::new(&TestOpts::new()).unwrap();
+
+    console::write_failure_list(&path, &st).unwrap();
+    assert_eq!(std::fs::read_to_string(&path).unwrap(), "");
+
+    let mut out = JsonFormatter::new(OutputLocation::Raw(Vec::new()));
+    let results = [("first", TrFailed), ("passes", TrOk), ("second", TrFailedMsg("bad".into()))];
+    for (i, (name, result)) in results.iter().enumerate() {
+        let completed_test =
+            CompletedTest::new(TestId(i), named_test_desc(name), result.clone(), None, Vec::new());
+        console::on_test_event(&TestEvent::TeResult(completed_test), &mut st, &mut out).unwrap();
+    }
+
+    console::write_failure_list(&path, &st).unwrap();
+    let list = std::fs::read_to_string(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+    assert_eq!(list, "first\nsecond\n");
+}
+
+#[test]
+fn junit_output_is_written_next_to_the_console_output() {
+    let path = std::env::temp_dir().join(format!("libtest-junit-{}", std::process::id()));
+    let test = |name| TestDescAndFn {
+        desc: named_test_desc(name),
+        testfn: DynTestFn(Box::new(move || {})),
+    };
+    let opts = TestOpts { run_tests: true, junit_output: Some(path.clone()), ..TestOpts::new() };
+    let mut st = console::ConsoleTestState::new(&opts).unwrap();
+    let pretty = PrettyFormatter::new(OutputLocation::Raw(Vec::new()), false, 10, false, None);
+    let junit = JunitFormatter::new(OutputLocation::Raw(std::fs::File::create(&path).unwrap()));
+    let mut out = TeeFormatter::new(Box::new(pretty), Box::new(junit));
+    crate::run_tests(&opts, vec![test("a::one"), test("a::two")], |event| {
+        console::on_test_event(&event, &mut st, &mut out)
+    })
+    .unwrap();
+    assert!(out.write_run_finish(&st).unwrap());
+
+    let xml = std::fs::read_to_string(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+    assert!(xml.starts_with(r#"<?xml version="1.0" encoding="UTF-8"?><testsuites><testsuite "#));
+    assert!(xml.ends_with("</testsuite></testsuites>"));
+    assert!(xml.contains(r#" tests="2" "#));
+    assert_eq!(xml.matches("<testcase ").count(), 2);
+}
+
+#[test]
+fn list_output_matches_the_stdout_listing() {
+    let path = std::env::temp_dir().join(format!("libtest-list-{}", std::process::id()));
+    let test = |name, testfn| TestDescAndFn { desc: named_test_desc(name), testfn };
+    let tests = || {
+        vec![
+            test("a::one", StaticTestFn(|| {})),
+            test("a::bench", StaticBenchFn(|_| {})),
+            test("b::two", StaticTestFn(|| {})),
+        ]
+    };
+    let opts = TestOpts { filters: vec!["a::".to_string()], ..TestOpts::new() };
+
+    let mut stdout = OutputLocation::Raw(Vec::new());
+    console::list_tests(&opts, tests(), &mut stdout).unwrap();
+    let opts = TestOpts { list_output: Some(path.clone()), ..opts };
+    console::list_tests_console(&opts, tests()).unwrap();
+
+    let listed = std::fs::read_to_string(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+    assert_eq!(listed, raw_output(&stdout));
+    assert_eq!(listed, "a::bench: benchmark\na::one: test\n\n1 test, 1 benchmark\n");
+
+    let opts = TestOpts { list_output: Some(path.join("missing-dir/list")), ..opts };
+    assert!(console::list_tests_console(&opts, tests()).is_err());
+}
+
+#[test]
+#[cfg(not(bootstrap))]
+fn error_on_ignored_fails_runs_with_ignored_tests() {
+    fn run(opts: &TestOpts) -> (bool, String) {
+        let test = |name, ignore, target_filter| TestDescAndFn {
+            desc: TestDesc { ignore, target_filter, ..named_test_desc(name) },
+            testfn: DynTestFn(Box::new(move || {})),
+        };
+        let tests = vec![
+            test("runs", false, TargetFilter::Any),
+            test("skipped", true, TargetFilter::Any),
+            // Ignored by the filter on the host, which doesn't count.
+            test("bpf", false, TargetFilter::BpfOnly),
+        ];
+        let mut st = console::ConsoleTestState::new(opts).unwrap();
+        let mut out = PrettyFormatter::new(OutputLocation::Raw(Vec::new()), false, 10, false, None);
+        crate::run_tests(opts, tests, |event| console::on_test_event(&event, &mut st, &mut out))
+            .unwrap();
+        console::report_forbidden_ignores(opts, &mut st, &mut out).unwrap();
+        let success = out.write_run_finish(&st).unwrap();
+        (success, raw_output(out.output_location()))
+    }
+
+    let (success, output) = run(&TestOpts { run_tests: true, ..TestOpts::new() });
+    assert!(success);
+    assert!(!output.contains("not allowed"));
+    assert!(output.contains("test result: ok."));
+
+    let (success, output) =
+        run(&TestOpts { run_tests: true, error_on_ignored: true, ..TestOpts::new() });
+    assert!(!success);
+    assert!(output.contains("warning: ignored tests are not allowed: skipped\n"));
+    // The summary agrees with the exit status, even though no test failed.
+    assert!(output.contains("test result: FAILED. 1 passed; 0 failed; 2 ignored;"));
+    assert!(!output.contains("failures:"));
+}
+
+#[test]
+fn json_logfile_has_one_object_per_test() {
+    let path = std::env::temp_dir().join(format!("libtest-json-log-{}", std::process::id()));
+    let opts = TestOpts {
+        logfile: Some(path.clone()),
+        logfile_format: LogfileFormat::Json,
+        ..TestOpts::new()
+    };
+    let mut st = console::ConsoleTestState::new(&opts).unwrap();
+    let desc = typed_test_desc(TestType::UnitTest);
+    st.write_log_result(&desc, &TrOk, Some(&test_exec_time(20))).unwrap();
+    st.write_log_result(&desc, &TrFailedMsg("bad \"input\"".to_string()), None).unwrap();
+    st.write_log_result(&desc, &TrIgnored, None).unwrap();
+    drop(st);
+
+    let log = std::fs::read_to_string(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+    let lines: Vec<_> = log.lines().collect();
+    assert_eq!(
+        lines,
+        vec![
+            r#"{ "type": "test", "name": "whatever", "event": "ok", "exec_time": 0.02 }"#,
+            r#"{ "type": "test", "name": "whatever", "event": "failed", "message": "bad \"input\"" }"#,
+            r#"{ "type": "test", "name": "whatever", "event": "ignored" }"#,
+        ]
+    );
+    assert!(lines.iter().all(|line| is_well_formed_json_line(line)));
+}
+
+#[test]
+fn max_name_len_only_counts_padded_names() {
+    let test = |name: &'static str, bench: bool| TestDescAndFn {
+        desc: named_test_desc(name),
+        testfn: if bench { StaticBenchFn(|_| {}) } else { StaticTestFn(|| {}) },
+    };
+
+    // Plain tests are not padded, whatever their order.
+    let mut tests = vec![test("a::first", false), test("b::other", false)];
+    assert_eq!(console::max_name_len(&tests), 0);
+    tests.reverse();
+    assert_eq!(console::max_name_len(&tests), 0);
+
+    let mut tests = vec![
+        test("a::very_long_test_name", false),
+        test("bench_one", true),
+        test("bench_two", true),
+        test("b", true),
+    ];
+    assert_eq!(console::max_name_len(&tests), 9);
+    tests.reverse();
+    assert_eq!(console::max_name_len(&tests), 9);
+}
+
+#[test]
+fn output_location_flushes_its_sink() {
+    use std::{cell::RefCell, rc::Rc};
+
+    // Only hands written bytes to `flushed` when flushed.
+    struct FlushOnly {
+        pending: Vec<u8>,
+        flushed: Rc<RefCell<Vec<u8>>>,
+    }
+    impl io::Write for FlushOnly {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.pending.extend_from_slice(buf);
+            Ok(buf.len())
+        }
+        fn flush(&mut self) -> io::Result<()> {
+            self.flushed.borrow_mut().append(&mut self.pending);
+            Ok(())
+        }
+    }
+
+    let flushed = Rc::default();
+    let mut out =
+        OutputLocation::Raw(FlushOnly { pending: Vec::new(), flushed: Rc::clone(&flushed) });
+    out.write_all(b"test result: ok").unwrap();
+    assert!(flushed.borrow().is_empty());
+    out.flush().unwrap();
+    assert_eq!(&*flushed.borrow(), b"test result: ok");
+
+    // The sink can be taken back out.
+    if let OutputLocation::Raw(sink) = out {
+        assert!(sink.pending.is_empty());
+    }
+}
+
+#[test]
+fn warnings_are_rendered_by_each_formatter() {
+    let msg = "output of a was truncated -- \"again\"";
+
+    let mut pretty = PrettyFormatter::new(OutputLocation::Raw(Vec::new()), false, 10, false, None);
+    pretty.write_warning(msg).unwrap();
+    assert_eq!(raw_output(pretty.output_location()), format!("warning: {}\n", msg));
+
+    let mut terse = TerseFormatter::new(OutputLocation::Raw(Vec::new()), false, 10, false);
+    terse.write_warning(msg).unwrap();
+    assert_eq!(raw_output(terse.output_location()), format!("warning: {}\n", msg));
+
+    let mut json = JsonFormatter::new(OutputLocation::Raw(Vec::new()));
+    json.write_warning(msg).unwrap();
+    let expected = r#"{ "type": "warning", "message": "output of a was truncated -- \"again\"" }"#;
+    assert_eq!(raw_output(json.output_location()), format!("{}\n", expected));
+
+    let mut junit = JunitFormatter::new(OutputLocation::Raw(Vec::new()));
+    junit.write_warning(msg).unwrap();
+    assert_eq!(
+        raw_output(junit.output_location()),
+        "<!-- warning: output of a was truncated - - \"again\" -->"
+    );
+}
+
+#[test]
+fn junit_reports_test_and_suite_times() {
+    let opts = TestOpts::new();
+    let mut st = console::ConsoleTestState::new(&opts).unwrap();
+    let mut junit = JunitFormatter::new(OutputLocation::Raw(Vec::new()));
+
+    let results = [("a::fast", Some(test_exec_time(250))), ("a::untimed", None)];
+    for (name, exec_time) in results.iter() {
+        let desc = named_test_desc(name);
+        junit.write_result(&desc, &TrOk, exec_time.as_ref(), &[], &st).unwrap();
+        st.passed += 1;
+    }
+    st.total = 2;
+    junit.write_run_finish(&st).unwrap();
+
+    let xml = raw_output(junit.output_location());
+    let times: Vec<f64> = xml
+        .split(" time=\"")
+        .skip(1)
+        .map(|rest| rest[..rest.find('"').unwrap()].parse().unwrap())
+        .collect();
+    // The suite's time comes first, then one per test case.
+    assert_eq!(times, [0.25, 0.25, 0.0]);
+    assert!(xml.contains(r#"<testcase classname="a" name="untimed" time="0"/>"#));
+}
+
+#[test]
+fn truncated_output_emits_a_warning() {
+    let opts = TestOpts { capture_max_bytes: Some(4), ..TestOpts::new() };
+    let mut st = console::ConsoleTestState::new(&opts).unwrap();
+    let mut out = JsonFormatter::new(OutputLocation::Raw(Vec::new()));
+
+    let desc = typed_test_desc(TestType::UnitTest);
+    for (i, stdout) in [&b"1234"[..], &b"12345"[..]].iter().enumerate() {
+        let completed_test =
+            CompletedTest::new(TestId(i), desc.clone(), TrOk, None, stdout.to_vec());
+        console::on_test_event(&TestEvent::TeResult(completed_test), &mut st, &mut out).unwrap();
+    }
+
+    let s = raw_output(out.output_location());
+    let warnings: Vec<_> = s.lines().filter(|l| l.contains(r#""type": "warning""#)).collect();
+    assert_eq!(
+        warnings,
+        vec![r#"{ "type": "warning", "message": "output of whatever was truncated to 4 bytes" }"#]
+    );
+}
+
+#[test]
+fn json_reports_bench_statistics() {
+    let st = console::ConsoleTestState::new(&TestOpts::new()).unwrap();
+    let mut out = JsonFormatter::new(OutputLocation::Raw(Vec::new()));
+    let desc = named_test_desc("bench");
+
+    let ns_iter_summ = crate::stats::Summary::new(&[100.0, 120.0, 80.0]);
+    let bs = crate::bench::BenchSamples { ns_iter_summ, mb_s: 0 };
+    out.write_result(&desc, &TrBench(bs), None, &[], &st).unwrap();
+    let bs = crate::bench::BenchSamples { ns_iter_summ, mb_s: 64 };
+    out.write_result(&desc, &TrBench(bs), None, &[], &st).unwrap();
+
+    let s = raw_output(out.output_location());
+    let lines: Vec<_> = s.lines().collect();
+    assert_eq!(
+        lines,
+        vec![
+            r#"{ "type": "bench", "name": "bench", "median": 100, "deviation": 40 }"#,
+            r#"{ "type": "bench", "name": "bench", "median": 100, "deviation": 40, "mib_per_second": 64 }"#,
+        ]
+    );
+}
+
+#[test]
+fn bench_csv_has_a_header_and_one_row_per_benchmark() {
+    let st = console::ConsoleTestState::new(&TestOpts::new()).unwrap();
+    let mut out = BenchCsvFormatter::new(OutputLocation::Raw(Vec::new()));
+    let fast = named_test_desc("fast");
+    let slow = TestDesc { name: StaticTestName("slow<A, B>"), ..fast.clone() };
+
+    out.write_run_start(3).unwrap();
+    let ns_iter_summ = crate::stats::Summary::new(&[100.0, 120.0, 80.0]);
+    out.write_result(&fast, &TrBench(BenchSamples { ns_iter_summ, mb_s: 0 }), None, &[], &st)
+        .unwrap();
+    out.write_result(&fast, &TrOk, None, &[], &st).unwrap();
+    let ns_iter_summ = crate::stats::Summary::new(&[5000.0, 5500.0, 4900.0]);
+    out.write_result(&slow, &TrBench(BenchSamples { ns_iter_summ, mb_s: 64 }), None, &[], &st)
+        .unwrap();
+    assert!(out.write_run_finish(&st).unwrap());
+
+    assert_eq!(
+        raw_output(out.output_location()),
+        "name,median_ns,deviation_ns\nfast,100,40\n\"slow<A, B>\",5000,600\n"
+    );
+}
+
+#[test]
+fn passing_test_output_is_discarded_with_no_capture_on_success() {
+    let opts = TestOpts { no_capture_on_success: true, ..TestOpts::new() };
+    let mut st = console::ConsoleTestState::new(&opts).unwrap();
+    let mut out = JsonFormatter::new(OutputLocation::Raw(Vec::new()));
+
+    let desc = typed_test_desc(TestType::UnitTest);
+    for (i, result) in [TrOk, TrFailed].iter().enumerate() {
+        let stdout = b"captured".to_vec();
+        let completed_test =
+            CompletedTest::new(TestId(i), desc.clone(), result.clone(), None, stdout);
+        console::on_test_event(&TestEvent::TeResult(completed_test), &mut st, &mut out).unwrap();
+    }
+
+    assert_eq!(st.not_failures.len(), 1);
+    assert!(st.not_failures[0].1.is_empty());
+    assert_eq!(st.failures.len(), 1);
+    assert_eq!(st.failures[0].1, b"captured");
+}
+
+#[test]
+fn before_and_after_each_hooks_wrap_every_test() {
+    use std::cell::RefCell;
+
+    thread_local! {
+        static EVENTS: RefCell<Vec<String>> = RefCell::new(Vec::new());
+    }
+    fn record(event: String) {
+        EVENTS.with(|events| events.borrow_mut().push(event));
+    }
+
+    let tests = ["a", "b"]
+        .iter()
+        .map(|&name| TestDescAndFn {
+            desc: named_test_desc(name),
+            testfn: DynTestFn(Box::new(move || record(format!("run {}", name)))),
+        })
+        .collect();
+    let opts = TestOpts { run_tests: true, test_threads: Some(1), ..TestOpts::new() };
+
+    set_before_each(Some(|desc| record(format!("before {}", desc.name))));
+    set_after_each(Some(|desc| record(format!("after {}", desc.name))));
+    run_tests(&opts, tests, |_| Ok(())).unwrap();
+    set_before_each(None);
+    set_after_each(None);
+
+    let events = EVENTS.with(|events| events.borrow().clone());
+    assert_eq!(events, ["before a", "run a", "after a", "before b", "run b", "after b"]);
+}
+
+#[test]
+fn bpf_test_markers_bracket_each_test() {
+    use std::cell::RefCell;
+
+    thread_local! {
+        static LOG: RefCell<Vec<String>> = RefCell::new(Vec::new());
+    }
+    fn log(message: &str) {
+        LOG.with(|log| log.borrow_mut().push(message.to_string()));
+    }
+
+    let tests = [("a", false), ("b", true)]
+        .iter()
+        .map(|&(name, fails)| TestDescAndFn {
+            desc: named_test_desc(name),
+            testfn: DynTestFn(Box::new(move || {
+                log(&format!("output of {}", name));
+                assert!(!fails);
+            })),
+        })
+        .collect();
+    let opts = TestOpts {
+        run_tests: true,
+        test_threads: Some(1),
+        bpf_test_markers: true,
+        ..TestOpts::new()
+    };
+    let mut st = console::ConsoleTestState::new(&opts).unwrap();
+    st.test_markers = Some(log);
+    let mut out = JsonFormatter::new(OutputLocation::Raw(Vec::new()));
+    run_tests(&opts, tests, |event| console::on_test_event(&event, &mut st, &mut out)).unwrap();
+
+    let log = LOG.with(|log| log.borrow().clone());
+    assert_eq!(
+        log,
+        [
+            "TEST_START a",
+            "output of a",
+            "TEST_END a ok",
+            "TEST_START b",
+            "output of b",
+            "TEST_END b failed",
+        ]
+    );
+}
+
+#[test]
+#[cfg(not(bootstrap))]
+fn tap_skip_directive_stays_on_one_line() {
+    let st = console::ConsoleTestState::new(&TestOpts::new()).unwrap();
+    let mut out = TapFormatter::new(OutputLocation::Raw(Vec::new()));
+
+    let desc = TestDesc {
+        ignore: true,
+        ignore_message: Some("needs\na network"),
+        ..typed_test_desc(TestType::UnitTest)
+    };
+    out.write_result(&desc, &TrIgnored, None, &[], &st).unwrap();
+    assert_eq!(raw_output(out.output_location()), "ok 1 - whatever # SKIP needs a network\n");
+}
+
+#[test]
+fn tap_formatter_emits_plan_and_directives() {
+    let mut st = console::ConsoleTestState::new(&TestOpts::new()).unwrap();
+    let mut out = TapFormatter::new(OutputLocation::Raw(Vec::new()));
+
+    let results = vec![
+        ("a", TrOk),
+        ("b", TrFailedMsg("boom".to_string())),
+        ("c", TrIgnored),
+        ("d #1", TrAllowedFail),
+    ];
+    let descs: Vec<_> = results.iter().map(|&(name, _)| named_test_desc(name)).collect();
+    console::on_test_event(&TestEvent::TeFiltered(descs.clone()), &mut st, &mut out).unwrap();
+    for (i, (desc, (_, result))) in descs.into_iter().zip(results).enumerate() {
+        let stdout = if i == 1 { b"oh no\n".to_vec() } else { Vec::new() };
+        let completed_test = CompletedTest::new(TestId(i), desc, result, None, stdout);
+        console::on_test_event(&TestEvent::TeResult(completed_test), &mut st, &mut out).unwrap();
+    }
+    assert!(!out.write_run_finish(&st).unwrap());
+
+    let s = raw_output(out.output_location());
+    let lines: Vec<_> = s.lines().collect();
+    assert_eq!(
+        lines,
+        vec![
+            "1..4",
+            "ok 1 - a",
+            "not ok 2 - b",
+            "ok 3 - c # SKIP",
+            "not ok 4 - d \\#1 # TODO allowed to fail",
+            "# ---- b stdout ----",
+            "# oh no",
+            "# note: boom",
+            "# 1 passed; 1 failed; 1 ignored; 0 measured; 0 filtered out",
+        ]
+    );
+    let tests = lines.iter().filter(|l| l.starts_with("ok ") || l.starts_with("not ok ")).count();
+    assert_eq!(tests, 4);
+}
+
+#[test]
+fn pretty_formatter_diffs_assert_eq_failures() {
+    let stdout = "thread 'x' panicked at 'assertion failed: `(left == right)`\n  \
+                  left: `[1, 2, 3]`,\n right: `[1, 5, 3]`: values differ', src/lib.rs:1:1\n";
+    let desc = named_test_desc("x");
+    let mut st = console::ConsoleTestState::new(&TestOpts::new()).unwrap();
+    st.failures.push((desc, stdout.as_bytes().to_vec()));
+
+    let mut out = PrettyFormatter::new(OutputLocation::Raw(Vec::new()), true, 10, false, None);
+    out.write_failures(&st).unwrap();
+    let s = raw_output(out.output_location());
+    assert!(s.contains("diff (- left, + right):\n  [1,\n- 2,\n+ 5,\n  3]\n"), "{}", s);
+
+    // Without colors the output is left as is.
+    let mut out = PrettyFormatter::new(OutputLocation::Raw(Vec::new()), false, 10, false, None);
+    out.write_failures(&st).unwrap();
+    assert!(!raw_output(out.output_location()).contains("diff (- left, + right)"));
+}
+
+#[test]
+fn json_failure_reports_panic_location() {
+    let st = console::ConsoleTestState::new(&TestOpts::new()).unwrap();
+    let mut out = JsonFormatter::new(OutputLocation::Raw(Vec::new()));
+    let desc = named_test_desc("a");
+
+    let stdout = b"thread 'a' panicked at 'boom', src/foo.rs:10:5\n";
+    out.write_result(&desc, &TrFailed, None, stdout, &st).unwrap();
+    let stdout = b"reading data.txt:3:1\nthread 'a' panicked at 'two\nlines', src/foo.rs:12:5\n";
+    out.write_result(&desc, &TrFailedMsg("oops".to_string()), None, stdout, &st).unwrap();
+    out.write_result(&desc, &TrFailed, None, b"failed at src/foo.rs:10:5\n", &st).unwrap();
+
+    let s = raw_output(out.output_location());
+    let lines: Vec<_> = s.lines().collect();
+    assert_eq!(
+        lines,
+        vec![
+            r#"{ "type": "test", "name": "a", "event": "failed", "stdout": "thread 'a' panicked at 'boom', src/foo.rs:10:5\n", "file": "src/foo.rs", "line": 10 }"#,
+            r#"{ "type": "test", "name": "a", "event": "failed", "stdout": "reading data.txt:3:1\nthread 'a' panicked at 'two\nlines', src/foo.rs:12:5\n", "message": "oops", "file": "src/foo.rs", "line": 12 }"#,
+            r#"{ "type": "test", "name": "a", "event": "failed", "stdout": "failed at src/foo.rs:10:5\n" }"#,
+        ]
+    );
+}
@@ -25,6 +25,29 @@ pub enum TestType {
     Unknown,
 }
 
+/// The targets a test can run on. A test meant for another target is reported as ignored
+/// instead of being run.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum TargetFilter {
+    /// The test runs everywhere.
+    Any,
+    /// The test only runs on BPF, e.g. because it calls syscalls.
+    BpfOnly,
+    /// The test only runs on the host, e.g. because it needs threads or a file system.
+    HostOnly,
+}
+
+impl TargetFilter {
+    /// Returns why a test with this filter is ignored on the current target, if it is.
+    pub fn ignore_reason(self) -> Option<&'static str> {
+        match self {
+            TargetFilter::BpfOnly if !cfg!(target_arch = "bpf") => Some("BPF-only"),
+            TargetFilter::HostOnly if cfg!(target_arch = "bpf") => Some("host-only"),
+            _ => None,
+        }
+    }
+}
+
 #[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
 pub enum NamePadding {
     PadNone,
@@ -122,12 +145,20 @@ fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
 pub struct TestDesc {
     pub name: TestName,
     pub ignore: bool,
+    #[cfg(not(bootstrap))]
+    pub ignore_message: Option<&'static str>,
     pub should_panic: options::ShouldPanic,
     pub allow_fail: bool,
     #[cfg(not(bootstrap))]
     pub compile_fail: bool,
     #[cfg(not(bootstrap))]
     pub no_run: bool,
+    #[cfg(not(bootstrap))]
+    pub target_filter: TargetFilter,
+    /// Compute units the test needs to be meaningful, from `#[min_compute_units = "N"]`. It is
+    /// ignored if `--test-cu-budget` is lower.
+    #[cfg(not(bootstrap))]
+    pub min_compute_units: Option<u64>,
     pub test_type: TestType,
 }
 
@@ -0,0 +1,6 @@
+// check that #[min_compute_units] is feature-gated
+
+#[min_compute_units = "1000"] //~ ERROR the `#[min_compute_units]` attribute is an experimental feature
+fn needs_compute_units() {}
+
+fn main() {}
@@ -0,0 +1,9 @@
+// run-pass
+// compile-flags: --test
+#![feature(min_compute_units)]
+
+#[test]
+#[min_compute_units = "200000"]
+fn test1() {
+    assert!(true);
+}
@@ -0,0 +1,7 @@
+// compile-flags: --test
+#![feature(min_compute_units)]
+
+#[test]
+#[min_compute_units = "lots"]
+//~^ ERROR `min_compute_units` must be a non-negative integer
+fn needs_compute_units() {}
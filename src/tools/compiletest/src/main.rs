@@ -507,6 +507,23 @@ pub fn test_opts(config: &Config) -> test::TestOpts {
         list: false,
         options: test::Options::new(),
         time_options: None,
+        slowest: None,
+        time_threshold: None,
+        capture_max_bytes: None,
+        logfile_format: test::LogfileFormat::Text,
+        no_capture_on_success: false,
+        report_cu: false,
+        list_failures_to: None,
+        test_cu_limit: None,
+        tee_output: false,
+        bpf_test_markers: false,
+        junit_output: None,
+        run_id: None,
+        test_cu_budget: None,
+        bench_format: test::BenchFormat::Text,
+        list_output: None,
+        error_on_ignored: false,
+        show_cu: false,
         force_run_in_process: false,
     }
 }
@@ -664,12 +681,18 @@ fn make_test(config: &Config, testpaths: &TestPaths, inputs: &Stamp) -> Vec<test
                 desc: test::TestDesc {
                     name: make_test_name(config, testpaths, revision),
                     ignore,
+                    #[cfg(not(bootstrap))]
+                    ignore_message: None,
                     should_panic,
                     allow_fail: false,
                     #[cfg(not(bootstrap))]
                     compile_fail: false,
                     #[cfg(not(bootstrap))]
                     no_run: false,
+                    #[cfg(not(bootstrap))]
+                    target_filter: test::TargetFilter::Any,
+                    #[cfg(not(bootstrap))]
+                    min_compute_units: None,
                     test_type: test::TestType::Unknown,
                 },
                 testfn: make_test_closure(config, testpaths, revision),
@@ -266,6 +266,7 @@ fn skip(path: &Path) -> bool {
         let mut skip_trailing_newlines =
             contains_ignore_directive(can_contain, &contents, "trailing-newlines");
         let mut skip_copyright = contains_ignore_directive(can_contain, &contents, "copyright");
+        let mut skip_todo = contains_ignore_directive(can_contain, &contents, "todo");
         let mut leading_new_lines = false;
         let mut trailing_new_lines = 0;
         let mut lines = 0;
@@ -298,7 +299,7 @@ fn skip(path: &Path) -> bool {
             }
             if filename != "style.rs" {
                 if line.contains("TODO") {
-                    err("TODO is deprecated; use FIXME")
+                    suppressible_tidy_err!(err, skip_todo, "TODO is deprecated; use FIXME")
                 }
                 if line.contains("//") && line.contains(" XXX") {
                     err("XXX is deprecated; use FIXME")
@@ -394,5 +395,8 @@ fn skip(path: &Path) -> bool {
         if let Directive::Ignore(false) = skip_copyright {
             tidy_error!(bad, "{}: ignoring copyright unnecessarily", file.display());
         }
+        if let Directive::Ignore(false) = skip_todo {
+            tidy_error!(bad, "{}: ignoring TODO unnecessarily", file.display());
+        }
     })
 }